@@ -0,0 +1,939 @@
+use anyhow::{anyhow, Result};
+use ceremony::{
+    attestation::AttestationConfig,
+    beacon::{check_beacon, BeaconConfig},
+    cache::VerificationCache,
+    check::{check_contribution_chain, check_recomputed_s},
+    checkpoint::{prompt_passphrase, CheckpointConfig},
+    chunked::convert_contribution,
+    config::{CeremonyConfig, CeremonyFile, PointEncoding, DEFAULT_CONFIG_FILENAME},
+    contribute::{contribute, contribute_beacon, contribute_shares, contribute_streaming},
+    contribution::{KZGContribution, DEFAULT_CONTRIBUTION_K},
+    export::ParamsFormat,
+    finalize::{export_ptau, finalize, finalize_export, finalize_k_range, parse_k_range},
+    inspect::{inspect_contribution, ContributionInfo},
+    ppot::{read_verified_ppot, PpotProvenance},
+    progress::ProgressStyle,
+    proof::MAX_PROOF_METADATA_LENGTH,
+    ptau::read_ptau,
+    secrets::{SecretsEncoding, SecretsGenConfig},
+    shares::write_share,
+    utils::{get_contributions_list, hash_file},
+};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum};
+use halo2_proofs::halo2curves::bn256::Bn256;
+use log::{info, LevelFilter};
+use serde::Serialize;
+use std::{
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OutputFormat {
+    /// Human-readable output
+    Text,
+    /// JSON output
+    Json,
+}
+
+#[derive(Parser)]
+#[clap(author = "Zircuit Labs", version, about, long_about = None)]
+struct Cli {
+    #[command(flatten)]
+    global: GlobalArgs,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Args)]
+struct GlobalArgs {
+    #[arg(
+        short = 'C',
+        long = "config",
+        global = true,
+        value_parser,
+        help = "The ceremony config file [default: ceremony.toml, if present]"
+    )]
+    config_path: Option<PathBuf>,
+    #[arg(
+        short = 'c',
+        long = "contributions",
+        global = true,
+        value_parser,
+        help = "The directory containing the contributions"
+    )]
+    contributions_path: Option<PathBuf>,
+    #[arg(
+        short = 's',
+        long = "size",
+        global = true,
+        value_parser,
+        help = "The ceremony size k, i.e. the SRS contains 2^k powers of tau in G1 [default: 28]"
+    )]
+    k: Option<u32>,
+    #[arg(
+        long = "format",
+        global = true,
+        value_parser = PossibleValuesParser::new(PointEncoding::NAMES).map(|encoding| encoding.parse::<PointEncoding>().unwrap()),
+        help = "The encoding of points in new contributions [default: uncompressed]"
+    )]
+    format: Option<PointEncoding>,
+    #[arg(
+        long = "chunk-size",
+        global = true,
+        value_parser,
+        help = "Write new contributions as a directory of chunk files of the provided size in bytes [default: single file]"
+    )]
+    chunk_size: Option<u64>,
+    #[arg(
+        short = 'o',
+        long = "output",
+        global = true,
+        value_enum,
+        default_value_t = OutputFormat::Text,
+        help = "The output format"
+    )]
+    output: OutputFormat,
+    #[arg(
+        short = 'v',
+        long = "verbose",
+        global = true,
+        action = ArgAction::Count,
+        help = "Log debug (-v) or trace (-vv) messages"
+    )]
+    verbose: u8,
+    #[arg(
+        short = 'q',
+        long = "quiet",
+        global = true,
+        conflicts_with = "verbose",
+        help = "Log errors only"
+    )]
+    quiet: bool,
+    #[arg(
+        long = "progress",
+        global = true,
+        value_parser = PossibleValuesParser::new(ProgressStyle::NAMES).map(|style| style.parse::<ProgressStyle>().unwrap()),
+        default_value_t = ProgressStyle::None,
+        help = "Report the progress of long-running phases on stderr, with throughput and ETA"
+    )]
+    progress: ProgressStyle,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create the first contribution, either trivial or from a PPoT challenge
+    Start(StartArgs),
+    /// Compute a new contribution from the latest one
+    Contribute(ContributeArgs),
+    /// Generate a secret share offline, to be combined with other shares by contribute --shares
+    Share(ShareArgs),
+    /// Compute the final contribution from a public random beacon
+    Beacon(BeaconArgs),
+    /// Verify the contributions' chain
+    Check(CheckArgs),
+    /// Convert the latest contribution to halo2 ParamsKZG
+    Finalize(FinalizeArgs),
+    /// Print the metadata of contributions without loading their points
+    Inspect(InspectArgs),
+    /// Split contributions into chunk files of --chunk-size bytes, or join chunked contributions into single files
+    Convert(ConvertArgs),
+}
+
+#[derive(Args)]
+struct StartArgs {
+    #[arg(
+        long = "ppot",
+        value_parser,
+        requires = "challenge_k",
+        help = "The file path for the PPoT challenge"
+    )]
+    challenge_path: Option<String>,
+    #[arg(
+        long = "ppot-k",
+        value_parser,
+        requires = "challenge_path",
+        help = "The k value used to compute the PPoT challenge"
+    )]
+    challenge_k: Option<u32>,
+    #[arg(
+        long = "hash-ppot",
+        requires = "challenge_path",
+        help = "Hash the PPoT challenge file for verification purposes"
+    )]
+    hash_challenge: bool,
+    #[arg(
+        long = "response",
+        requires = "challenge_path",
+        help = "Read the PPoT file as a compressed response file instead of a challenge file"
+    )]
+    is_response: bool,
+    #[arg(
+        long = "expected-hash",
+        value_parser,
+        requires = "challenge_path",
+        help = "Check that the Blake2b hash of the PPoT file matches the provided hex-encoded hash"
+    )]
+    expected_hash: Option<String>,
+    #[arg(
+        long = "expected-prev-hash",
+        value_parser,
+        requires = "challenge_path",
+        help = "Check that the hash embedded in the PPoT file, i.e. the hash of the previous PPoT file, matches the provided hex-encoded hash"
+    )]
+    expected_prev_hash: Option<String>,
+    #[arg(
+        long = "ptau",
+        value_parser,
+        conflicts_with = "challenge_path",
+        help = "The file path for a snarkjs .ptau file to start from"
+    )]
+    ptau_path: Option<String>,
+}
+
+#[derive(Args)]
+struct SecretsArgs {
+    #[arg(
+        short = 'f',
+        long = "files-to-hash",
+        value_parser,
+        help = "Hash the provided file into the hash state"
+    )]
+    files_to_hash: Option<Vec<String>>,
+    #[arg(
+        short = 'i',
+        long = "stdin",
+        help = "Hash input from stdin into the hash state"
+    )]
+    from_stdin: bool,
+    #[arg(
+        short = 'r',
+        long = "random-size",
+        value_parser,
+        help = "Hash the specified number of random bytes into the hash state"
+    )]
+    random_bytes_size: Option<usize>,
+    #[arg(
+        long = "hash-iterations",
+        value_parser,
+        help = "Hash the hash state for the specified number of iterations"
+    )]
+    hash_iterations: Option<u32>,
+    #[arg(
+        short = 'p',
+        long = "public",
+        help = "Reveal the secret used for contribution"
+    )]
+    reveal_s: bool,
+    #[arg(
+        long = "command",
+        value_parser,
+        help = "Hash the standard output of the provided local command into the hash state"
+    )]
+    commands: Option<Vec<String>>,
+    #[arg(
+        long = "fifo",
+        value_parser,
+        help = "Hash the content of the provided named pipe, read until EOF, into the hash state"
+    )]
+    fifos: Option<Vec<String>>,
+    #[arg(
+        long = "env-seed",
+        value_parser,
+        help = "Hash the seed stored in the provided environment variable into the hash state"
+    )]
+    env_seed: Option<String>,
+}
+
+impl SecretsArgs {
+    // Secrets are generated with the default settings unless any source is set
+    fn into_config(self) -> SecretsGenConfig {
+        if self.files_to_hash.is_some()
+            || self.from_stdin
+            || self.random_bytes_size.is_some()
+            || self.hash_iterations.is_some()
+            || self.reveal_s
+            || self.commands.is_some()
+            || self.fifos.is_some()
+            || self.env_seed.is_some()
+        {
+            SecretsGenConfig {
+                files_to_hash: self.files_to_hash,
+                from_stdin: self.from_stdin,
+                random_bytes_size: self.random_bytes_size,
+                hash_iterations: self.hash_iterations,
+                reveal_s: self.reveal_s,
+                commands: self.commands,
+                fifos: self.fifos,
+                env_seed: self.env_seed,
+                encoding: SecretsEncoding::Tagged,
+            }
+        } else {
+            SecretsGenConfig::default()
+        }
+    }
+}
+
+#[derive(Args)]
+struct ContributeArgs {
+    #[command(flatten)]
+    secrets: SecretsArgs,
+    #[arg(
+        long = "shares",
+        value_parser,
+        num_args = 1..,
+        conflicts_with_all = ["files_to_hash", "from_stdin", "random_bytes_size", "hash_iterations", "reveal_s", "commands", "fifos", "env_seed", "chunk_budget"],
+        help = "Rescale the last contribution by the product of the provided secret shares"
+    )]
+    shares: Option<Vec<PathBuf>>,
+    #[arg(
+        short = 'm',
+        long = "chunk-budget",
+        value_parser,
+        help = "Stream the last contribution using at most the specified memory (in MiB) for points"
+    )]
+    chunk_budget: Option<usize>,
+    #[arg(
+        long = "checkpoint",
+        help = "Write a checkpoint after each chunk of points, with the secrets encrypted under a passphrase (streaming contributions only, requires --chunk-budget)"
+    )]
+    checkpoint: bool,
+    #[arg(
+        long = "resume",
+        conflicts_with_all = ["files_to_hash", "from_stdin", "random_bytes_size", "hash_iterations", "reveal_s", "commands", "fifos", "env_seed"],
+        help = "Resume an interrupted checkpointed contribution after verifying its partial output (requires --chunk-budget)"
+    )]
+    resume: bool,
+    #[arg(
+        short = 'd',
+        long = "metadata",
+        value_parser,
+        help = "Bind the provided contributor metadata (e.g. a name or a public key) to the contribution proof"
+    )]
+    metadata: Option<String>,
+    #[arg(
+        long = "attestation-key",
+        value_parser,
+        requires = "handle",
+        help = "Sign an attestation of the contribution with the hex-encoded Ed25519 secret key stored in the provided file"
+    )]
+    attestation_key: Option<PathBuf>,
+    #[arg(
+        long = "handle",
+        value_parser,
+        requires = "attestation_key",
+        help = "The contributor handle included in the attestation"
+    )]
+    handle: Option<String>,
+    #[arg(
+        long = "attestation-message",
+        value_parser,
+        requires = "attestation_key",
+        help = "A message included in the attestation"
+    )]
+    attestation_message: Option<String>,
+}
+
+#[derive(Args)]
+struct ShareArgs {
+    #[arg(value_parser, help = "The output filepath for the secret share")]
+    share_path: PathBuf,
+    #[command(flatten)]
+    secrets: SecretsArgs,
+}
+
+#[derive(Args)]
+struct BeaconArgs {
+    #[arg(
+        long = "beacon",
+        value_parser,
+        required = true,
+        help = "The file containing the public random beacon, e.g. a block hash"
+    )]
+    beacon_path: PathBuf,
+    #[arg(
+        long = "beacon-iterations-exp",
+        value_parser,
+        required = true,
+        help = "Hash the beacon 2^EXP times to derive the secret"
+    )]
+    beacon_iterations_exp: u32,
+}
+
+#[derive(Args)]
+struct CheckArgs {
+    #[arg(
+        long = "full",
+        help = "Verify all contributions, ignoring previously cached results"
+    )]
+    full: bool,
+    #[arg(
+        long = "cache",
+        value_parser,
+        help = "The filepath of the verification cache [default: a file in the user cache directory]"
+    )]
+    cache_path: Option<PathBuf>,
+    #[arg(
+        long = "beacon",
+        value_parser,
+        requires = "beacon_iterations_exp",
+        help = "Check that the last contribution is derived from the public random beacon stored in the provided file"
+    )]
+    beacon_path: Option<PathBuf>,
+    #[arg(
+        long = "beacon-iterations-exp",
+        value_parser,
+        requires = "beacon_path",
+        help = "The beacon is hashed 2^EXP times to derive the secret"
+    )]
+    beacon_iterations_exp: Option<u32>,
+    #[arg(
+        long = "recompute-public",
+        value_parser,
+        help = "Recompute the secret revealed by the contribution with the provided id from its declared inputs"
+    )]
+    recompute_public: Option<u32>,
+    #[arg(
+        long = "files",
+        value_parser,
+        num_args = 1..,
+        requires = "recompute_public",
+        help = "The files hashed into the hash state of the recomputed contribution"
+    )]
+    recompute_files: Option<Vec<String>>,
+    #[arg(
+        long = "stdin",
+        requires = "recompute_public",
+        help = "Hash input from stdin into the hash state of the recomputed contribution"
+    )]
+    recompute_stdin: bool,
+    #[arg(
+        long = "hash-iterations",
+        value_parser,
+        requires = "recompute_public",
+        help = "The number of iterations the hash state of the recomputed contribution was hashed for"
+    )]
+    recompute_hash_iterations: Option<u32>,
+    #[arg(
+        long = "legacy-encoding",
+        requires = "recompute_public",
+        help = "Recompute the secret with the legacy encoding, which hashes files and stdin raw, used by contributions computed before tagged sources"
+    )]
+    recompute_legacy_encoding: bool,
+}
+
+#[derive(Args)]
+struct FinalizeArgs {
+    #[arg(
+        long = "params",
+        value_parser,
+        help = "The output filepath for the finalized parameters"
+    )]
+    params_filepath: Option<String>,
+    #[arg(
+        long = "all-k",
+        conflicts_with_all = ["params_filepath", "k_range"],
+        help = "Write parameters downsized to every k from 1 to the ceremony k, along with a SHA256 manifest"
+    )]
+    all_k: bool,
+    #[arg(
+        long = "k-range",
+        value_parser = parse_k_range,
+        conflicts_with = "params_filepath",
+        help = "Write parameters downsized to every k in the range MIN..MAX (both included), along with a SHA256 manifest"
+    )]
+    k_range: Option<RangeInclusive<u32>>,
+    #[arg(
+        long = "params-dir",
+        value_parser,
+        help = "The output directory for downsized parameters [default: CONTRIBUTIONS_PATH]"
+    )]
+    params_dir: Option<String>,
+    #[arg(
+        long = "ptau",
+        value_parser,
+        conflicts_with_all = ["params_filepath", "all_k", "k_range"],
+        help = "Export the last contribution to the provided snarkjs .ptau file instead of ParamsKZG"
+    )]
+    ptau_filepath: Option<String>,
+    #[arg(
+        long = "params-format",
+        value_parser = PossibleValuesParser::new(ParamsFormat::NAMES).map(|format| format.parse::<ParamsFormat>().unwrap()),
+        default_value_t = ParamsFormat::Halo2,
+        help = "The format of the finalized parameters"
+    )]
+    params_format: ParamsFormat,
+    #[arg(
+        long = "verify",
+        help = "Read back the finalized arkworks or gnark parameters and check them against the last contribution"
+    )]
+    verify: bool,
+}
+
+#[derive(Args)]
+struct InspectArgs {
+    #[arg(
+        value_parser,
+        help = "The contribution files to inspect [default: all contributions in the contributions directory]"
+    )]
+    files: Vec<PathBuf>,
+}
+
+#[derive(Args)]
+struct ConvertArgs {
+    #[arg(
+        value_parser,
+        num_args = 1..,
+        required = true,
+        help = "The contributions to convert"
+    )]
+    files: Vec<PathBuf>,
+}
+
+/// A file written by a subcommand
+#[derive(Serialize)]
+struct WrittenFile {
+    path: PathBuf,
+    sha256: String,
+}
+
+// Settings resolved from command line options, the config file and defaults, in this order of precedence
+struct Settings {
+    contributions_path: Option<PathBuf>,
+    config: CeremonyConfig,
+    output: OutputFormat,
+}
+
+impl Settings {
+    fn resolve(global: &GlobalArgs) -> Result<Self> {
+        let file = match &global.config_path {
+            Some(path) => CeremonyFile::load(path)?,
+            None if Path::new(DEFAULT_CONFIG_FILENAME).is_file() => {
+                CeremonyFile::load(Path::new(DEFAULT_CONFIG_FILENAME))?
+            }
+            None => CeremonyFile::default(),
+        };
+
+        let config = CeremonyConfig::new(global.k.or(file.k).unwrap_or(DEFAULT_CONTRIBUTION_K))?
+            .with_encoding(
+                global
+                    .format
+                    .or(file.format)
+                    .unwrap_or(PointEncoding::Uncompressed),
+            )
+            .with_chunk_size(global.chunk_size.or(file.chunk_size))?;
+
+        Ok(Settings {
+            contributions_path: global.contributions_path.clone().or(file.contributions),
+            config,
+            output: global.output,
+        })
+    }
+
+    fn contributions_path(&self) -> Result<&Path> {
+        self.contributions_path.as_deref().ok_or_else(|| {
+            anyhow!("No contributions directory set: pass --contributions or set `contributions` in the config file")
+        })
+    }
+
+    fn contributions_path_str(&self) -> Result<String> {
+        Ok(self.contributions_path()?.to_string_lossy().into_owned())
+    }
+
+    // Prints a file written by a subcommand, either in the sha256sum format or as JSON
+    fn print_written(&self, path: PathBuf) -> Result<()> {
+        let written = WrittenFile {
+            sha256: hash_file(&path)?,
+            path,
+        };
+        match self.output {
+            OutputFormat::Text => println!("{}  {}", written.sha256, written.path.display()),
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&written)?),
+        }
+        Ok(())
+    }
+
+    // Prints the files written by a subcommand, either in the sha256sum format or as a JSON list
+    fn print_written_all(&self, paths: Vec<PathBuf>) -> Result<()> {
+        let written = paths
+            .into_iter()
+            .map(|path| {
+                Ok(WrittenFile {
+                    sha256: hash_file(&path)?,
+                    path,
+                })
+            })
+            .collect::<Result<Vec<WrittenFile>>>()?;
+        self.print_written_files(written)
+    }
+
+    // Prints files whose SHA256 is already known
+    fn print_written_files(&self, written: Vec<WrittenFile>) -> Result<()> {
+        match self.output {
+            OutputFormat::Text => {
+                for file in written.iter() {
+                    println!("{}  {}", file.sha256, file.path.display());
+                }
+            }
+            OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&written)?),
+        }
+        Ok(())
+    }
+}
+
+fn init_logging(global: &GlobalArgs) {
+    let level = if global.quiet {
+        LevelFilter::Error
+    } else {
+        match global.verbose {
+            0 => LevelFilter::Info,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        }
+    };
+
+    // RUST_LOG, if set, takes precedence over the verbosity flags
+    let mut builder = env_logger::Builder::new();
+    builder.filter_level(level);
+    if let Ok(filters) = std::env::var("RUST_LOG") {
+        builder.parse_filters(&filters);
+    }
+    builder.init();
+}
+
+fn start(settings: &Settings, args: StartArgs) -> Result<()> {
+    let config = &settings.config;
+
+    let contribution = match (&args.challenge_path, &args.ptau_path) {
+        (Some(challenge_path), _) => {
+            let challenge_k = args.challenge_k.unwrap();
+            let provenance = PpotProvenance {
+                file_hash: args.expected_hash,
+                embedded_hash: args.expected_prev_hash,
+            };
+            if args.is_response {
+                info!("Reading PPOT Response {:#?}", challenge_path);
+            } else {
+                info!("Reading PPOT Challenge {:#?}", challenge_path);
+            }
+            read_verified_ppot(
+                challenge_path,
+                challenge_k,
+                args.is_response,
+                args.hash_challenge,
+                &provenance,
+                config,
+            )?
+        }
+        (None, Some(ptau_path)) => {
+            info!("Reading ptau file {:#?}", ptau_path);
+            read_ptau(ptau_path, config)?
+        }
+        (None, None) => {
+            info!("Creating a trivial SRS with k = {}", config.k());
+            KZGContribution::<Bn256>::trivial(config.k())
+        }
+    };
+
+    let path = contribution.write_default_custom(
+        &settings.contributions_path_str()?,
+        config.format(),
+        config.chunk_size(),
+    )?;
+    settings.print_written(path)
+}
+
+fn contribute_command(settings: &Settings, args: ContributeArgs) -> Result<()> {
+    let secrets_config = args.secrets.into_config();
+
+    let metadata = args.metadata.unwrap_or_default().into_bytes();
+    if metadata.len() > MAX_PROOF_METADATA_LENGTH {
+        return Err(anyhow!(
+            "Contributor metadata has length {} but at most {} bytes are allowed",
+            metadata.len(),
+            MAX_PROOF_METADATA_LENGTH
+        ));
+    }
+
+    // Checkpoints record the points written so far, which only the streaming contribution writes chunk by chunk:
+    // in-memory contributions write the whole contribution at once and cannot be resumed
+    if (args.checkpoint || args.resume) && args.chunk_budget.is_none() {
+        return Err(anyhow!(
+            "Checkpoints are only supported by streaming contributions: pass --chunk-budget to use --checkpoint or --resume"
+        ));
+    }
+
+    // The checkpoint passphrase is confirmed when it is set
+    let checkpoint_config = if args.checkpoint || args.resume {
+        Some(CheckpointConfig {
+            passphrase: prompt_passphrase(!args.resume)?,
+            resume: args.resume,
+        })
+    } else {
+        None
+    };
+
+    let attestation_config = args
+        .attestation_key
+        .zip(args.handle)
+        .map(|(key_path, handle)| AttestationConfig {
+            key_path,
+            handle,
+            message: args.attestation_message.unwrap_or_default(),
+        });
+
+    let contributions_path = settings.contributions_path_str()?;
+    let config = &settings.config;
+    let path = match (args.shares, args.chunk_budget) {
+        (Some(shares), _) => contribute_shares::<Bn256>(
+            &contributions_path,
+            config,
+            &shares,
+            metadata,
+            attestation_config,
+        )?,
+        (None, Some(chunk_budget)) => contribute_streaming::<Bn256>(
+            &contributions_path,
+            config,
+            secrets_config,
+            chunk_budget * 1024 * 1024,
+            metadata,
+            attestation_config,
+            checkpoint_config,
+        )?,
+        (None, None) => contribute::<Bn256>(
+            &contributions_path,
+            config,
+            secrets_config,
+            metadata,
+            attestation_config,
+        )?,
+    };
+
+    settings.print_written(path)
+}
+
+fn share(settings: &Settings, args: ShareArgs) -> Result<()> {
+    let secrets_config = args.secrets.into_config();
+
+    write_share::<Bn256>(&args.share_path, secrets_config)?;
+
+    // Share files are secret, so only their path is printed
+    match settings.output {
+        OutputFormat::Text => println!("{}", args.share_path.display()),
+        OutputFormat::Json => println!("{}", serde_json::json!({ "path": args.share_path })),
+    }
+
+    Ok(())
+}
+
+fn beacon(settings: &Settings, args: BeaconArgs) -> Result<()> {
+    let beacon = BeaconConfig {
+        beacon_path: args.beacon_path,
+        iterations_exp: args.beacon_iterations_exp,
+    };
+
+    let path = contribute_beacon::<Bn256>(
+        &settings.contributions_path_str()?,
+        &settings.config,
+        &beacon,
+        None,
+    )?;
+
+    settings.print_written(path)
+}
+
+fn check(settings: &Settings, args: CheckArgs) -> Result<()> {
+    let contributions_path = settings.contributions_path()?;
+    let cache_path = match args.cache_path {
+        Some(cache_path) => cache_path,
+        None => VerificationCache::default_path(contributions_path)?,
+    };
+
+    let mut report = check_contribution_chain::<Bn256>(
+        &settings.contributions_path_str()?,
+        &settings.config,
+        Some(&cache_path),
+        args.full,
+    )?;
+
+    if let Some(beacon_path) = args.beacon_path {
+        let beacon = BeaconConfig {
+            beacon_path,
+            iterations_exp: args.beacon_iterations_exp.unwrap(),
+        };
+        check_beacon::<Bn256>(&mut report, &beacon)?;
+    }
+
+    if let Some(id) = args.recompute_public {
+        let secrets_config = SecretsGenConfig {
+            files_to_hash: args.recompute_files,
+            from_stdin: args.recompute_stdin,
+            random_bytes_size: None,
+            hash_iterations: args.recompute_hash_iterations,
+            reveal_s: true,
+            commands: None,
+            fifos: None,
+            env_seed: None,
+            encoding: if args.recompute_legacy_encoding {
+                SecretsEncoding::Legacy
+            } else {
+                SecretsEncoding::Tagged
+            },
+        };
+        check_recomputed_s::<Bn256>(&mut report, id, secrets_config)?;
+    }
+
+    if let OutputFormat::Json = settings.output {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
+
+    // Each class of failure is reported with a distinct exit code
+    if let Some(failure) = report.failure() {
+        std::process::exit(failure.exit_code());
+    }
+
+    Ok(())
+}
+
+fn finalize_command(settings: &Settings, args: FinalizeArgs) -> Result<()> {
+    let k_range = if args.all_k {
+        Some(1..=settings.config.k())
+    } else {
+        args.k_range
+    };
+
+    if k_range.is_some() && args.params_format != ParamsFormat::Halo2 {
+        return Err(anyhow!(
+            "Downsized parameters are only available in the halo2 format"
+        ));
+    }
+
+    match k_range {
+        _ if args.ptau_filepath.is_some() => {
+            let path = export_ptau(
+                settings.contributions_path_str()?,
+                args.ptau_filepath.unwrap(),
+                &settings.config,
+            )?;
+            settings.print_written(path)
+        }
+        Some(k_range) => {
+            let paths = finalize_k_range::<Bn256>(
+                settings.contributions_path_str()?,
+                args.params_dir,
+                k_range,
+                &settings.config,
+            )?;
+            settings.print_written_all(paths)
+        }
+        None if args.params_format != ParamsFormat::Halo2 => {
+            let path = finalize_export(
+                settings.contributions_path_str()?,
+                args.params_filepath,
+                args.params_format,
+                args.verify,
+                &settings.config,
+            )?;
+            settings.print_written(path)
+        }
+        None => {
+            let path = finalize::<Bn256>(
+                settings.contributions_path_str()?,
+                args.params_filepath,
+                &settings.config,
+            )?;
+            settings.print_written(path)
+        }
+    }
+}
+
+fn inspect(settings: &Settings, args: InspectArgs) -> Result<()> {
+    let files = if args.files.is_empty() {
+        let contributions_list =
+            get_contributions_list(&settings.contributions_path()?.to_path_buf())?;
+        let mut ids: Vec<u32> = contributions_list.keys().cloned().collect();
+        ids.sort_unstable();
+        ids.iter()
+            .map(|id| contributions_list.get(id).unwrap().to_owned())
+            .collect()
+    } else {
+        args.files
+    };
+
+    let infos = files
+        .iter()
+        .map(|file| inspect_contribution(file))
+        .collect::<Result<Vec<ContributionInfo>>>()?;
+
+    match settings.output {
+        OutputFormat::Text => {
+            for info in infos.iter() {
+                print_info(info);
+            }
+        }
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(&infos)?),
+    }
+
+    Ok(())
+}
+
+fn convert(settings: &Settings, args: ConvertArgs) -> Result<()> {
+    let chunk_size = settings.config.chunk_size();
+    let written = args
+        .files
+        .into_iter()
+        .map(|path| {
+            let sha256 = convert_contribution::<Bn256>(&path, chunk_size)?;
+            Ok(WrittenFile { path, sha256 })
+        })
+        .collect::<Result<Vec<WrittenFile>>>()?;
+    settings.print_written_files(written)
+}
+
+fn print_info(info: &ContributionInfo) {
+    println!("{}", info.path.display());
+    println!("  id:            {}", info.id);
+    println!("  sha256:        {}", info.sha256);
+    match info.version {
+        Some(version) => println!("  layout:        versioned (version {})", version),
+        None => println!("  layout:        legacy"),
+    }
+    if let Some(chunks) = info.chunks {
+        println!("  chunks:        {}", chunks);
+    }
+    println!("  curve:         {:?}", info.curve);
+    println!("  encoding:      {:?}", info.encoding);
+    println!("  k:             {}", info.k);
+    println!("  proof version: {:?}", info.proof_version);
+    if !info.metadata.is_empty() {
+        println!("  metadata:      {}", info.metadata);
+    }
+    if let Some(attestation) = &info.attestation {
+        println!(
+            "  attestation:   {} at {} ({:#?})",
+            attestation.handle, attestation.timestamp, attestation.message
+        );
+    }
+}
+
+fn main() -> Result<()> {
+    let cli = Cli::parse();
+
+    init_logging(&cli.global);
+    cli.global.progress.install();
+    let settings = Settings::resolve(&cli.global)?;
+
+    match cli.command {
+        Command::Start(args) => start(&settings, args),
+        Command::Contribute(args) => contribute_command(&settings, args),
+        Command::Share(args) => share(&settings, args),
+        Command::Beacon(args) => beacon(&settings, args),
+        Command::Check(args) => check(&settings, args),
+        Command::Finalize(args) => finalize_command(&settings, args),
+        Command::Inspect(args) => inspect(&settings, args),
+        Command::Convert(args) => convert(&settings, args),
+    }
+}
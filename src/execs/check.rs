@@ -1,6 +1,25 @@
 use anyhow::Result;
-use ceremony::check::check_contribution_chain;
-use clap::{ArgAction, Parser};
+use ceremony::{
+    beacon::{check_beacon, BeaconConfig},
+    cache::VerificationCache,
+    check::{check_contribution_chain, check_recomputed_s},
+    config::CeremonyConfig,
+    contribution::DEFAULT_CONTRIBUTION_K,
+    progress::ProgressStyle,
+    secrets::{SecretsEncoding, SecretsGenConfig},
+};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
+use clap::{ArgAction, Parser, ValueEnum};
+use halo2_proofs::halo2curves::bn256::Bn256;
+use std::path::{Path, PathBuf};
+
+#[derive(Clone, Copy, ValueEnum)]
+enum ReportFormat {
+    /// Log results only
+    Text,
+    /// Print a JSON report to stdout
+    Json,
+}
 
 #[derive(Parser)]
 #[clap(author = "Zircuit Labs", version, about, long_about = None)]
@@ -14,6 +33,89 @@ struct Arguments {
         help = "The directory containing the contributions"
     )]
     contributions_path: String,
+    #[arg(
+        short = 's',
+        long = "size",
+        value_parser,
+        default_value_t = DEFAULT_CONTRIBUTION_K,
+        help = "The ceremony size k, i.e. the SRS contains 2^k powers of tau in G1"
+    )]
+    k: u32,
+    #[arg(
+        short = 'r',
+        long = "report",
+        value_enum,
+        default_value_t = ReportFormat::Text,
+        help = "The format of the verification report"
+    )]
+    report: ReportFormat,
+    #[arg(
+        short = 'f',
+        long = "full",
+        help = "Verify all contributions, ignoring previously cached results"
+    )]
+    full: bool,
+    #[arg(
+        long = "cache",
+        value_parser,
+        help = "The filepath of the verification cache [default: a file in the user cache directory]"
+    )]
+    cache_path: Option<PathBuf>,
+    #[arg(
+        long = "beacon",
+        value_parser,
+        requires = "beacon_iterations_exp",
+        help = "Check that the last contribution is derived from the public random beacon stored in the provided file"
+    )]
+    beacon_path: Option<PathBuf>,
+    #[arg(
+        long = "beacon-iterations-exp",
+        alias = "beacon_iterations_exp",
+        value_parser,
+        requires = "beacon_path",
+        help = "The beacon is hashed 2^EXP times to derive the secret"
+    )]
+    beacon_iterations_exp: Option<u32>,
+    #[arg(
+        long = "recompute-public",
+        value_parser,
+        help = "Recompute the secret revealed by the contribution with the provided id from its declared inputs"
+    )]
+    recompute_public: Option<u32>,
+    #[arg(
+        long = "files",
+        value_parser,
+        num_args = 1..,
+        requires = "recompute_public",
+        help = "The files hashed into the hash state of the recomputed contribution"
+    )]
+    recompute_files: Option<Vec<String>>,
+    #[arg(
+        long = "stdin",
+        requires = "recompute_public",
+        help = "Hash input from stdin into the hash state of the recomputed contribution"
+    )]
+    recompute_stdin: bool,
+    #[arg(
+        long = "hash-iterations",
+        value_parser,
+        requires = "recompute_public",
+        help = "The number of iterations the hash state of the recomputed contribution was hashed for"
+    )]
+    recompute_hash_iterations: Option<u32>,
+    #[arg(
+        long = "legacy-encoding",
+        requires = "recompute_public",
+        help = "Recompute the secret with the legacy encoding, which hashes files and stdin raw, used by contributions computed before tagged sources"
+    )]
+    recompute_legacy_encoding: bool,
+    #[arg(
+        long = "progress",
+        value_parser = PossibleValuesParser::new(ProgressStyle::NAMES).map(|style| style.parse::<ProgressStyle>().unwrap()),
+        default_value_t = ProgressStyle::None,
+        help = "Report the progress of long-running phases on stderr, with throughput and ETA"
+    )]
+    progress: ProgressStyle,
     #[arg(short = 'H', long = "help", action = ArgAction::Help, help = "Print help information")]
     help: Option<bool>,
 }
@@ -22,8 +124,57 @@ fn main() -> Result<()> {
     env_logger::init();
 
     let args = Arguments::parse();
+    args.progress.install();
+
+    let config = CeremonyConfig::new(args.k)?;
+
+    let cache_path = match args.cache_path {
+        Some(cache_path) => cache_path,
+        None => VerificationCache::default_path(Path::new(&args.contributions_path))?,
+    };
+
+    let mut report = check_contribution_chain::<Bn256>(
+        &args.contributions_path,
+        &config,
+        Some(&cache_path),
+        args.full,
+    )?;
+
+    if let Some(beacon_path) = args.beacon_path {
+        let beacon = BeaconConfig {
+            beacon_path,
+            iterations_exp: args.beacon_iterations_exp.unwrap(),
+        };
+        check_beacon::<Bn256>(&mut report, &beacon)?;
+    }
+
+    if let Some(id) = args.recompute_public {
+        let secrets_config = SecretsGenConfig {
+            files_to_hash: args.recompute_files,
+            from_stdin: args.recompute_stdin,
+            random_bytes_size: None,
+            hash_iterations: args.recompute_hash_iterations,
+            reveal_s: true,
+            commands: None,
+            fifos: None,
+            env_seed: None,
+            encoding: if args.recompute_legacy_encoding {
+                SecretsEncoding::Legacy
+            } else {
+                SecretsEncoding::Tagged
+            },
+        };
+        check_recomputed_s::<Bn256>(&mut report, id, secrets_config)?;
+    }
+
+    if let ReportFormat::Json = args.report {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+    }
 
-    assert!(check_contribution_chain(&args.contributions_path)?);
+    // Each class of failure is reported with a distinct exit code
+    if let Some(failure) = report.failure() {
+        std::process::exit(failure.exit_code());
+    }
 
     Ok(())
 }
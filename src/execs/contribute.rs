@@ -1,6 +1,20 @@
-use anyhow::Result;
-use ceremony::{contribute::contribute, secrets::SecretsGenConfig};
+use anyhow::{anyhow, Result};
+use ceremony::{
+    attestation::AttestationConfig,
+    beacon::BeaconConfig,
+    checkpoint::{prompt_passphrase, CheckpointConfig},
+    config::CeremonyConfig,
+    contribute::{contribute, contribute_beacon, contribute_shares, contribute_streaming},
+    contribution::DEFAULT_CONTRIBUTION_K,
+    progress::ProgressStyle,
+    proof::MAX_PROOF_METADATA_LENGTH,
+    secrets::{SecretsEncoding, SecretsGenConfig},
+    shares::write_share,
+};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::{ArgAction, Parser};
+use halo2_proofs::halo2curves::bn256::Bn256;
+use std::path::PathBuf;
 
 #[derive(Parser)]
 #[clap(author = "Zircuit Labs", version, about, long_about = None)]
@@ -10,13 +24,14 @@ struct Arguments {
         short = 'c',
         long,
         value_parser,
-        required = true,
+        required_unless_present = "write_share",
         help = "The directory containing the contributions"
     )]
-    contributions_path: String,
+    contributions_path: Option<String>,
     #[arg(
         short = 'f',
-        long = "files_to_hash",
+        long = "files-to-hash",
+        alias = "files_to_hash",
         value_parser,
         help = "Hash the provided file into the hash state"
     )]
@@ -29,14 +44,16 @@ struct Arguments {
     from_stdin: bool,
     #[arg(
         short = 'r',
-        long = "random_size",
+        long = "random-size",
+        alias = "random_size",
         value_parser,
         help = "Hash the specified number of random bytes into the hash state"
     )]
     random_bytes_size: Option<usize>,
     #[arg(
         short = 'h',
-        long = "hash_iterations",
+        long = "hash-iterations",
+        alias = "hash_iterations",
         value_parser,
         help = "Hash the hash state for the specified number of iterations"
     )]
@@ -47,6 +64,124 @@ struct Arguments {
         help = "Reveal the secret used for contribution"
     )]
     reveal_s: bool,
+    #[arg(
+        long = "command",
+        value_parser,
+        help = "Hash the standard output of the provided local command into the hash state"
+    )]
+    commands: Option<Vec<String>>,
+    #[arg(
+        long = "fifo",
+        value_parser,
+        help = "Hash the content of the provided named pipe, read until EOF, into the hash state"
+    )]
+    fifos: Option<Vec<String>>,
+    #[arg(
+        long = "env-seed",
+        alias = "env_seed",
+        value_parser,
+        help = "Hash the seed stored in the provided environment variable into the hash state"
+    )]
+    env_seed: Option<String>,
+    #[arg(
+        short = 's',
+        long = "size",
+        value_parser,
+        default_value_t = DEFAULT_CONTRIBUTION_K,
+        help = "The ceremony size k, i.e. the SRS contains 2^k powers of tau in G1"
+    )]
+    k: u32,
+    #[arg(
+        short = 'm',
+        long = "chunk-budget",
+        alias = "chunk_budget",
+        value_parser,
+        help = "Stream the last contribution using at most the specified memory (in MiB) for points"
+    )]
+    chunk_budget: Option<usize>,
+    #[arg(
+        long = "checkpoint",
+        help = "Write a checkpoint after each chunk of points, with the secrets encrypted under a passphrase (streaming contributions only, requires --chunk-budget)"
+    )]
+    checkpoint: bool,
+    #[arg(
+        long = "resume",
+        conflicts_with_all = ["files_to_hash", "from_stdin", "random_bytes_size", "hash_iterations", "reveal_s", "commands", "fifos", "env_seed"],
+        help = "Resume an interrupted checkpointed contribution after verifying its partial output (requires --chunk-budget)"
+    )]
+    resume: bool,
+    #[arg(
+        short = 'd',
+        long = "metadata",
+        value_parser,
+        help = "Bind the provided contributor metadata (e.g. a name or a public key) to the contribution proof"
+    )]
+    metadata: Option<String>,
+    #[arg(
+        short = 'k',
+        long = "attestation-key",
+        alias = "attestation_key",
+        value_parser,
+        requires = "handle",
+        help = "Sign an attestation of the contribution with the hex-encoded Ed25519 secret key stored in the provided file"
+    )]
+    attestation_key: Option<PathBuf>,
+    #[arg(
+        short = 'n',
+        long = "handle",
+        value_parser,
+        requires = "attestation_key",
+        help = "The contributor handle included in the attestation"
+    )]
+    handle: Option<String>,
+    #[arg(
+        short = 'a',
+        long = "attestation-message",
+        alias = "attestation_message",
+        value_parser,
+        requires = "attestation_key",
+        help = "A message included in the attestation"
+    )]
+    attestation_message: Option<String>,
+    #[arg(
+        long = "beacon",
+        value_parser,
+        requires = "beacon_iterations_exp",
+        conflicts_with_all = ["files_to_hash", "from_stdin", "random_bytes_size", "hash_iterations", "reveal_s", "commands", "fifos", "env_seed", "chunk_budget", "metadata"],
+        help = "Compute the final contribution from the public random beacon stored in the provided file"
+    )]
+    beacon_path: Option<PathBuf>,
+    #[arg(
+        long = "beacon-iterations-exp",
+        alias = "beacon_iterations_exp",
+        value_parser,
+        requires = "beacon_path",
+        help = "Hash the beacon 2^EXP times to derive the secret"
+    )]
+    beacon_iterations_exp: Option<u32>,
+    #[arg(
+        long = "shares",
+        value_parser,
+        num_args = 1..,
+        conflicts_with_all = ["files_to_hash", "from_stdin", "random_bytes_size", "hash_iterations", "reveal_s", "commands", "fifos", "env_seed", "chunk_budget", "beacon_path"],
+        help = "Rescale the last contribution by the product of the provided secret shares"
+    )]
+    shares: Option<Vec<PathBuf>>,
+    #[arg(
+        long = "write-share",
+        alias = "write_share",
+        value_parser,
+        conflicts_with_all = ["contributions_path", "chunk_budget", "metadata", "attestation_key", "beacon_path", "shares"],
+        help = "Generate a secret share, to be combined with other shares by --shares, and write it to the provided file instead of contributing"
+    )]
+    write_share: Option<PathBuf>,
+    #[arg(
+        long = "progress",
+        value_parser = PossibleValuesParser::new(ProgressStyle::NAMES).map(|style| style.parse::<ProgressStyle>().unwrap()),
+        default_value_t = ProgressStyle::None,
+        help = "Report the progress of long-running phases on stderr, with throughput and ETA"
+    )]
+    progress: ProgressStyle,
     #[arg(short = 'H', long = "help", action = ArgAction::Help, help = "Print help information")]
     help: Option<bool>,
 }
@@ -55,12 +190,18 @@ fn main() -> Result<()> {
     env_logger::init();
 
     let args = Arguments::parse();
+    args.progress.install();
+
+    let ceremony_config = CeremonyConfig::new(args.k)?;
 
     let config = if args.files_to_hash.is_some()
         || args.from_stdin
         || args.random_bytes_size.is_some()
         || args.hash_iterations.is_some()
         || args.reveal_s
+        || args.commands.is_some()
+        || args.fifos.is_some()
+        || args.env_seed.is_some()
     {
         SecretsGenConfig {
             files_to_hash: args.files_to_hash,
@@ -68,10 +209,94 @@ fn main() -> Result<()> {
             random_bytes_size: args.random_bytes_size,
             hash_iterations: args.hash_iterations,
             reveal_s: args.reveal_s,
+            commands: args.commands,
+            fifos: args.fifos,
+            env_seed: args.env_seed,
+            encoding: SecretsEncoding::Tagged,
         }
     } else {
         SecretsGenConfig::default()
     };
 
-    contribute(&args.contributions_path, config)
+    let metadata = args.metadata.unwrap_or_default().into_bytes();
+    if metadata.len() > MAX_PROOF_METADATA_LENGTH {
+        return Err(anyhow!(
+            "Contributor metadata has length {} but at most {} bytes are allowed",
+            metadata.len(),
+            MAX_PROOF_METADATA_LENGTH
+        ));
+    }
+
+    // Checkpoints record the points written so far, which only the streaming contribution writes chunk by chunk:
+    // in-memory contributions write the whole contribution at once and cannot be resumed
+    if (args.checkpoint || args.resume) && args.chunk_budget.is_none() {
+        return Err(anyhow!(
+            "Checkpoints are only supported by streaming contributions: pass --chunk-budget to use --checkpoint or --resume"
+        ));
+    }
+
+    // The checkpoint passphrase is confirmed when it is set
+    let checkpoint_config = if args.checkpoint || args.resume {
+        Some(CheckpointConfig {
+            passphrase: prompt_passphrase(!args.resume)?,
+            resume: args.resume,
+        })
+    } else {
+        None
+    };
+
+    let attestation_config = args
+        .attestation_key
+        .zip(args.handle)
+        .map(|(key_path, handle)| AttestationConfig {
+            key_path,
+            handle,
+            message: args.attestation_message.unwrap_or_default(),
+        });
+
+    if let Some(share_path) = args.write_share {
+        return write_share::<Bn256>(&share_path, config);
+    }
+    let contributions_path = args.contributions_path.unwrap();
+
+    if let Some(shares) = args.shares {
+        contribute_shares::<Bn256>(
+            &contributions_path,
+            &ceremony_config,
+            &shares,
+            metadata,
+            attestation_config,
+        )?;
+    } else if let Some(beacon_path) = args.beacon_path {
+        let beacon = BeaconConfig {
+            beacon_path,
+            iterations_exp: args.beacon_iterations_exp.unwrap(),
+        };
+        contribute_beacon::<Bn256>(
+            &contributions_path,
+            &ceremony_config,
+            &beacon,
+            attestation_config,
+        )?;
+    } else if let Some(chunk_budget) = args.chunk_budget {
+        contribute_streaming::<Bn256>(
+            &contributions_path,
+            &ceremony_config,
+            config,
+            chunk_budget * 1024 * 1024,
+            metadata,
+            attestation_config,
+            checkpoint_config,
+        )?;
+    } else {
+        contribute::<Bn256>(
+            &contributions_path,
+            &ceremony_config,
+            config,
+            metadata,
+            attestation_config,
+        )?;
+    }
+
+    Ok(())
 }
@@ -1,6 +1,15 @@
-use anyhow::Result;
-use ceremony::finalize::finalize;
+use anyhow::{anyhow, Result};
+use ceremony::{
+    config::CeremonyConfig,
+    contribution::DEFAULT_CONTRIBUTION_K,
+    export::ParamsFormat,
+    finalize::{export_ptau, finalize, finalize_export, finalize_k_range, parse_k_range},
+    progress::ProgressStyle,
+};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::{ArgAction, Parser};
+use halo2_proofs::halo2curves::bn256::Bn256;
+use std::ops::RangeInclusive;
 
 #[derive(Parser)]
 #[clap(author = "Zircuit Labs", version, about, long_about = None)]
@@ -21,6 +30,62 @@ struct Arguments {
         help = "The output filepath for the finalized parameters"
     )]
     params_filepath: Option<String>,
+    #[arg(
+        long = "all-k",
+        conflicts_with_all = ["params_filepath", "k_range"],
+        help = "Write parameters downsized to every k from 1 to the ceremony k, along with a SHA256 manifest"
+    )]
+    all_k: bool,
+    #[arg(
+        long = "k-range",
+        value_parser = parse_k_range,
+        conflicts_with = "params_filepath",
+        help = "Write parameters downsized to every k in the range MIN..MAX (both included), along with a SHA256 manifest"
+    )]
+    k_range: Option<RangeInclusive<u32>>,
+    #[arg(
+        short = 'd',
+        long = "params-dir",
+        alias = "params_dir",
+        value_parser,
+        help = "The output directory for downsized parameters [default: CONTRIBUTIONS_PATH]"
+    )]
+    params_dir: Option<String>,
+    #[arg(
+        long = "ptau",
+        value_parser,
+        conflicts_with_all = ["params_filepath", "all_k", "k_range"],
+        help = "Export the last contribution to the provided snarkjs .ptau file instead of ParamsKZG"
+    )]
+    ptau_filepath: Option<String>,
+    #[arg(
+        short = 'f',
+        long = "format",
+        value_parser = PossibleValuesParser::new(ParamsFormat::NAMES).map(|format| format.parse::<ParamsFormat>().unwrap()),
+        default_value_t = ParamsFormat::Halo2,
+        help = "The format of the finalized parameters"
+    )]
+    params_format: ParamsFormat,
+    #[arg(
+        long = "verify",
+        help = "Read back the finalized arkworks or gnark parameters and check them against the last contribution"
+    )]
+    verify: bool,
+    #[arg(
+        short = 's',
+        long = "size",
+        value_parser,
+        default_value_t = DEFAULT_CONTRIBUTION_K,
+        help = "The ceremony size k, i.e. the SRS contains 2^k powers of tau in G1"
+    )]
+    k: u32,
+    #[arg(
+        long = "progress",
+        value_parser = PossibleValuesParser::new(ProgressStyle::NAMES).map(|style| style.parse::<ProgressStyle>().unwrap()),
+        default_value_t = ProgressStyle::None,
+        help = "Report the progress of long-running phases on stderr, with throughput and ETA"
+    )]
+    progress: ProgressStyle,
     #[arg(short = 'H', long = "help", action = ArgAction::Help, help = "Print help information")]
     help: Option<bool>,
 }
@@ -29,6 +94,46 @@ fn main() -> Result<()> {
     env_logger::init();
 
     let args = Arguments::parse();
+    args.progress.install();
+
+    let config = CeremonyConfig::new(args.k)?;
+
+    let k_range = if args.all_k {
+        Some(1..=config.k())
+    } else {
+        args.k_range
+    };
+
+    if k_range.is_some() && args.params_format != ParamsFormat::Halo2 {
+        return Err(anyhow!(
+            "Downsized parameters are only available in the halo2 format"
+        ));
+    }
+
+    match k_range {
+        _ if args.ptau_filepath.is_some() => {
+            export_ptau(
+                args.contributions_path,
+                args.ptau_filepath.unwrap(),
+                &config,
+            )?;
+        }
+        Some(k_range) => {
+            finalize_k_range::<Bn256>(args.contributions_path, args.params_dir, k_range, &config)?;
+        }
+        None if args.params_format != ParamsFormat::Halo2 => {
+            finalize_export(
+                args.contributions_path,
+                args.params_filepath,
+                args.params_format,
+                args.verify,
+                &config,
+            )?;
+        }
+        None => {
+            finalize::<Bn256>(args.contributions_path, args.params_filepath, &config)?;
+        }
+    }
 
-    finalize(args.contributions_path, args.params_filepath)
+    Ok(())
 }
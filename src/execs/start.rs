@@ -1,5 +1,12 @@
 use anyhow::Result;
-use ceremony::{contribution::KZGContribution, ppot::read_challenge};
+use ceremony::{
+    config::CeremonyConfig,
+    contribution::{KZGContribution, DEFAULT_CONTRIBUTION_K},
+    ppot::{read_verified_ppot, PpotProvenance},
+    progress::ProgressStyle,
+    ptau::read_ptau,
+};
+use clap::builder::{PossibleValuesParser, TypedValueParser};
 use clap::{ArgAction, Parser};
 use halo2_proofs::halo2curves::bn256::Bn256;
 use log::info;
@@ -26,7 +33,8 @@ struct Arguments {
     challenge_path: Option<String>,
     #[arg(
         short = 'k',
-        long = "ppot_k",
+        long = "ppot-k",
+        alias = "ppot_k",
         value_parser,
         requires = "challenge_path",
         help = "The k value used to compute the PPoT challenge"
@@ -40,6 +48,49 @@ struct Arguments {
         help = "Hash the PPoT challenge file for verification purposes"
     )]
     hash_challenge: bool,
+    #[arg(
+        short = 'r',
+        long = "response",
+        requires = "challenge_path",
+        help = "Read the PPoT file as a compressed response file instead of a challenge file"
+    )]
+    is_response: bool,
+    #[arg(
+        long = "expected-hash",
+        value_parser,
+        requires = "challenge_path",
+        help = "Check that the Blake2b hash of the PPoT file matches the provided hex-encoded hash"
+    )]
+    expected_hash: Option<String>,
+    #[arg(
+        long = "expected-prev-hash",
+        value_parser,
+        requires = "challenge_path",
+        help = "Check that the hash embedded in the PPoT file, i.e. the hash of the previous PPoT file, matches the provided hex-encoded hash"
+    )]
+    expected_prev_hash: Option<String>,
+    #[arg(
+        long = "ptau",
+        value_parser,
+        conflicts_with = "challenge_path",
+        help = "The file path for a snarkjs .ptau file to start from"
+    )]
+    ptau_path: Option<String>,
+    #[arg(
+        short = 's',
+        long = "size",
+        value_parser,
+        default_value_t = DEFAULT_CONTRIBUTION_K,
+        help = "The ceremony size k, i.e. the SRS contains 2^k powers of tau in G1"
+    )]
+    k: u32,
+    #[arg(
+        long = "progress",
+        value_parser = PossibleValuesParser::new(ProgressStyle::NAMES).map(|style| style.parse::<ProgressStyle>().unwrap()),
+        default_value_t = ProgressStyle::None,
+        help = "Report the progress of long-running phases on stderr, with throughput and ETA"
+    )]
+    progress: ProgressStyle,
     #[arg(short = 'H', long = "help", action = ArgAction::Help, help = "Print help information")]
     help: Option<bool>,
 }
@@ -48,14 +99,39 @@ fn main() -> Result<()> {
     env_logger::init();
 
     let args = Arguments::parse();
+    args.progress.install();
+
+    let config = CeremonyConfig::new(args.k)?;
 
-    let contribution = if let Some(challenge_path) = &args.challenge_path {
-        let challenge_k = args.challenge_k.unwrap();
-        info!("Reading PPOT Challenge {:#?}", challenge_path);
-        read_challenge(challenge_path, challenge_k, args.hash_challenge)?
-    } else {
-        info!("Creating a default SRS");
-        KZGContribution::<Bn256>::default()
+    let contribution = match (&args.challenge_path, &args.ptau_path) {
+        (Some(challenge_path), _) => {
+            let challenge_k = args.challenge_k.unwrap();
+            let provenance = PpotProvenance {
+                file_hash: args.expected_hash,
+                embedded_hash: args.expected_prev_hash,
+            };
+            if args.is_response {
+                info!("Reading PPOT Response {:#?}", challenge_path);
+            } else {
+                info!("Reading PPOT Challenge {:#?}", challenge_path);
+            }
+            read_verified_ppot(
+                challenge_path,
+                challenge_k,
+                args.is_response,
+                args.hash_challenge,
+                &provenance,
+                &config,
+            )?
+        }
+        (None, Some(ptau_path)) => {
+            info!("Reading ptau file {:#?}", ptau_path);
+            read_ptau(ptau_path, &config)?
+        }
+        (None, None) => {
+            info!("Creating a trivial SRS with k = {}", config.k());
+            KZGContribution::<Bn256>::trivial(config.k())
+        }
     };
 
     contribution.write_default(&args.contributions_path)?;
@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Result};
+use ed25519_dalek::{Signature, Signer, SigningKey, VerifyingKey};
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use zeroize::Zeroize;
+
+use crate::utils::decode_sha256;
+
+/// Extension of the attestation stored next to each contribution file
+pub const ATTESTATION_EXTENSION: &str = "attestation.json";
+
+/// Domain separation tag for attestation signatures
+const ATTESTATION_DOMAIN: &[u8] = b"zircuit-ceremony/attestation/v1";
+
+/// The contributor data used by `contribute` to attest a new contribution
+#[derive(Debug, Clone)]
+pub struct AttestationConfig {
+    /// A file containing the hex-encoded 32 bytes Ed25519 secret key of the contributor
+    pub key_path: PathBuf,
+    pub handle: String,
+    pub message: String,
+}
+
+/// A statement, signed by the contributor, linking a contributor identity to a contribution file
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    /// The contributor handle (e.g. a GitHub username)
+    pub handle: String,
+    /// Seconds since the Unix epoch at which the attestation was created
+    pub timestamp: u64,
+    pub message: String,
+    /// SHA256 of the attested contribution file, hex encoded
+    pub contribution_sha256: String,
+    /// The contributor Ed25519 public key, hex encoded
+    pub public_key: String,
+    /// The Ed25519 signature of the attestation, hex encoded
+    pub signature: String,
+}
+
+/// Results of the verification of an attestation
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AttestationReport {
+    pub handle: String,
+    pub timestamp: u64,
+    pub message: String,
+    pub public_key: String,
+    /// The signature is valid and the attestation refers to the contribution file
+    pub valid: bool,
+}
+
+// Computes the message signed by the contributor.
+// Variable-length fields are length-prefixed so that distinct attestations cannot produce the same message
+fn signed_message(
+    handle: &str,
+    timestamp: u64,
+    message: &str,
+    contribution_sha256: &[u8; 32],
+) -> Vec<u8> {
+    let mut signed_message = Vec::new();
+    for field in [ATTESTATION_DOMAIN, handle.as_bytes(), message.as_bytes()] {
+        signed_message.extend_from_slice(&(field.len() as u64).to_le_bytes());
+        signed_message.extend_from_slice(field);
+    }
+    signed_message.extend_from_slice(&timestamp.to_le_bytes());
+    signed_message.extend_from_slice(contribution_sha256);
+    signed_message
+}
+
+// Reads an Ed25519 secret key stored hex encoded in a file
+fn read_signing_key(key_path: &Path) -> Result<SigningKey> {
+    let mut key_hex = fs::read_to_string(key_path)?;
+    let decoded = hex::decode(key_hex.trim());
+    key_hex.zeroize();
+
+    let mut key_bytes =
+        decoded.map_err(|_| anyhow!("Key file {:#?} is not hex encoded", key_path.display()))?;
+    let key: Result<[u8; 32], _> = key_bytes.as_slice().try_into();
+    key_bytes.zeroize();
+
+    let mut key = key.map_err(|_| {
+        anyhow!(
+            "Key file {:#?} does not contain a 32 bytes Ed25519 secret key",
+            key_path.display()
+        )
+    })?;
+    let signing_key = SigningKey::from_bytes(&key);
+    key.zeroize();
+
+    Ok(signing_key)
+}
+
+impl Attestation {
+    /// Creates an attestation for a contribution file with the given SHA256, signed with the key found in the config
+    pub fn new(config: &AttestationConfig, contribution_sha256: &str) -> Result<Self> {
+        let signing_key = read_signing_key(&config.key_path)?;
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+        let signature = signing_key.sign(&signed_message(
+            &config.handle,
+            timestamp,
+            &config.message,
+            &decode_sha256(contribution_sha256)?,
+        ));
+
+        Ok(Attestation {
+            handle: config.handle.clone(),
+            timestamp,
+            message: config.message.clone(),
+            contribution_sha256: contribution_sha256.to_owned(),
+            public_key: hex::encode(signing_key.verifying_key().to_bytes()),
+            signature: hex::encode(signature.to_bytes()),
+        })
+    }
+
+    /// Returns the path of the attestation of a contribution file
+    pub fn filepath(contribution_filepath: &Path) -> PathBuf {
+        contribution_filepath.with_extension(ATTESTATION_EXTENSION)
+    }
+
+    /// Reads the attestation of a contribution file, if any
+    pub fn read(contribution_filepath: &Path) -> Result<Option<Self>> {
+        let filepath = Attestation::filepath(contribution_filepath);
+        if !filepath.is_file() {
+            return Ok(None);
+        }
+
+        let reader = BufReader::new(File::open(&filepath)?);
+        Ok(Some(serde_json::from_reader(reader)?))
+    }
+
+    /// Writes the attestation next to the contribution file
+    pub fn write(&self, contribution_filepath: &Path) -> Result<()> {
+        let filepath = Attestation::filepath(contribution_filepath);
+        let mut writer = BufWriter::new(File::create(&filepath)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+
+        info!("Attestation written to {:#?}", &filepath.display());
+        Ok(())
+    }
+
+    /// Checks that the attestation refers to a contribution file with the given SHA256 and that its signature is valid
+    pub fn verify(&self, contribution_sha256: &str) -> bool {
+        if self.contribution_sha256 != contribution_sha256 {
+            return false;
+        }
+
+        let public_key: Option<[u8; 32]> = hex::decode(&self.public_key)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok());
+        let signature: Option<[u8; 64]> = hex::decode(&self.signature)
+            .ok()
+            .and_then(|bytes| bytes.try_into().ok());
+        let sha256 = decode_sha256(contribution_sha256).ok();
+
+        match (public_key, signature, sha256) {
+            (Some(public_key), Some(signature), Some(sha256)) => {
+                let Ok(verifying_key) = VerifyingKey::from_bytes(&public_key) else {
+                    return false;
+                };
+                let signed_message =
+                    signed_message(&self.handle, self.timestamp, &self.message, &sha256);
+                verifying_key
+                    .verify_strict(&signed_message, &Signature::from_bytes(&signature))
+                    .is_ok()
+            }
+            _ => false,
+        }
+    }
+
+    /// Verifies the attestation and summarizes it in a report
+    pub fn report(&self, contribution_sha256: &str) -> AttestationReport {
+        AttestationReport {
+            handle: self.handle.clone(),
+            timestamp: self.timestamp,
+            message: self.message.clone(),
+            public_key: self.public_key.clone(),
+            valid: self.verify(contribution_sha256),
+        }
+    }
+}
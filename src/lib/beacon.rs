@@ -0,0 +1,154 @@
+use anyhow::{anyhow, Result};
+use blake2::Blake2b512;
+use group::ff::{Field, FromUniformBytes};
+use halo2_proofs::halo2curves::pairing::Engine;
+use log::{error, info};
+use sha2::{Digest, Sha256};
+use std::{fs, path::PathBuf};
+
+use crate::check::check_revealed_s;
+use crate::proof::MAX_PROOF_METADATA_LENGTH;
+use crate::report::ChainReport;
+use crate::secrets::Secret;
+
+// The beacon iterations are logged every 2^24 hashes
+const BEACON_LOG_INTERVAL: u64 = 1 << 24;
+
+/// A public random beacon, e.g. a block hash published after the contribution phase closed,
+/// and the number of SHA256 iterations used to derive the rescaling factor s from it
+#[derive(Debug, Clone)]
+pub struct BeaconConfig {
+    pub beacon_path: PathBuf,
+    /// The beacon is hashed 2^iterations_exp times
+    pub iterations_exp: u32,
+}
+
+impl BeaconConfig {
+    /// Reads the beacon bytes. Files containing a hex string (optionally 0x-prefixed and surrounded by whitespace)
+    /// are hex-decoded, otherwise the raw file content is used
+    pub fn read_beacon(&self) -> Result<Vec<u8>> {
+        if self.iterations_exp > 63 {
+            return Err(anyhow!(
+                "The beacon can be hashed at most 2^63 times, but 2^{} iterations were requested",
+                self.iterations_exp
+            ));
+        }
+
+        let bytes = fs::read(&self.beacon_path).map_err(|e| {
+            anyhow!(
+                "Cannot read the beacon file {:#?}: {}",
+                self.beacon_path.display(),
+                e
+            )
+        })?;
+
+        let beacon = std::str::from_utf8(&bytes)
+            .ok()
+            .map(|text| text.trim().trim_start_matches("0x"))
+            .filter(|text| !text.is_empty())
+            .and_then(|text| hex::decode(text).ok())
+            .unwrap_or(bytes);
+
+        if beacon.is_empty() {
+            return Err(anyhow!(
+                "The beacon file {:#?} is empty",
+                self.beacon_path.display()
+            ));
+        }
+
+        Ok(beacon)
+    }
+
+    /// The metadata bound to the beacon contribution proof, recording the beacon and the number of iterations
+    pub fn metadata(&self) -> Result<Vec<u8>> {
+        let metadata = format!(
+            "beacon:{}:2^{}",
+            hex::encode(self.read_beacon()?),
+            self.iterations_exp
+        )
+        .into_bytes();
+        if metadata.len() > MAX_PROOF_METADATA_LENGTH {
+            return Err(anyhow!(
+                "The beacon metadata has length {} but at most {} bytes are allowed",
+                metadata.len(),
+                MAX_PROOF_METADATA_LENGTH
+            ));
+        }
+        Ok(metadata)
+    }
+}
+
+/// Derives the rescaling factor s from the beacon: the beacon is hashed with SHA256,
+/// the digest is hashed 2^iterations_exp more times, and s is extracted from the final digest
+pub fn derive_beacon_s<E: Engine>(beacon: &BeaconConfig) -> Result<E::Fr>
+where
+    E::Fr: FromUniformBytes<64>,
+{
+    let bytes = beacon.read_beacon()?;
+    let iterations = 1u64 << beacon.iterations_exp;
+    info!(
+        "Hashing the beacon {} 2^{} times",
+        hex::encode(&bytes),
+        beacon.iterations_exp
+    );
+
+    let mut digest: [u8; 32] = Sha256::digest(&bytes).into();
+    for i in 1..=iterations {
+        digest = Sha256::digest(digest).into();
+        if i % BEACON_LOG_INTERVAL == 0 {
+            info!("Computed {}/{} beacon iterations", i, iterations);
+        }
+    }
+    info!("The final beacon digest is {}", hex::encode(digest));
+
+    // We extract s from the final digest as secrets are extracted from the hash state
+    let mut s_hasher = Blake2b512::new();
+    s_hasher.update(digest);
+    s_hasher.update("s");
+    let s_bytes: [u8; 64] = s_hasher.finalize().into();
+
+    Ok(E::Fr::from_uniform_bytes(&s_bytes))
+}
+
+/// Returns the rescaling factor s derived from the beacon and a zero blinding factor z,
+/// so that s can be recomputed from the contribution proof
+pub fn beacon_secrets<E: Engine>(beacon: &BeaconConfig) -> Result<(Secret<E>, Secret<E>)>
+where
+    E::Fr: FromUniformBytes<64>,
+{
+    let s = Secret::new(derive_beacon_s::<E>(beacon)?);
+    let z = Secret::new(E::Fr::ZERO);
+    Ok((s, z))
+}
+
+/// Checks that the last contribution of a verified chain revealed the rescaling factor s derived from the beacon,
+/// and records the result in the report
+pub fn check_beacon<E: Engine>(report: &mut ChainReport, beacon: &BeaconConfig) -> Result<bool>
+where
+    E::Fr: FromUniformBytes<64>,
+{
+    let last_id = report
+        .contributions
+        .last()
+        .map(|contribution| contribution.id)
+        .ok_or_else(|| anyhow!("No contribution found to check the beacon against"))?;
+
+    let s = derive_beacon_s::<E>(beacon)?;
+    let is_valid = check_revealed_s::<E>(report, last_id, &s)?;
+    if is_valid {
+        info!(
+            "Contribution with id {} is the beacon contribution",
+            last_id
+        );
+    } else {
+        error!(
+            "Contribution with id {} is NOT the beacon contribution",
+            last_id
+        );
+    }
+
+    report.beacon = Some(is_valid);
+    report.valid &= is_valid;
+
+    Ok(is_valid)
+}
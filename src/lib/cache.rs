@@ -0,0 +1,353 @@
+use anyhow::{anyhow, Result};
+use blake2::{digest::Mac, Blake2bMac512};
+use halo2_proofs::halo2curves::serde::SerdeObject;
+use log::{info, warn};
+use rand::{rngs::OsRng, RngCore};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    env,
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::config::CeremonyConfig;
+use crate::report::{ContributionReport, Timings};
+
+/// Name of the directory holding verification caches and their key in the user cache directory
+pub const CACHE_DIRNAME: &str = "ceremony";
+
+/// Filename of the key authenticating verification cache entries, stored in the user cache directory
+pub const CACHE_KEY_FILENAME: &str = "check_cache.key";
+
+/// Version of the verification cache layout. Caches with a different version are discarded
+const CACHE_VERSION: u32 = 2;
+
+const CACHE_KEY_LENGTH: usize = 32;
+
+/// A contribution which passed all checks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// SHA256 of the previous contribution file the contribution proof was verified against, if any
+    pub prev_sha256: Option<String>,
+    /// The contribution's s_g, hex encoded in RawBytes format
+    pub s_g: String,
+    pub report: ContributionReport,
+    /// Keyed Blake2b MAC of the entry, hex encoded. Set when the entry is inserted in the cache
+    #[serde(default)]
+    pub mac: String,
+}
+
+// The fields of an entry authenticated by its MAC. Fields of the report which are recomputed
+// on each run (path, timings, attestation) are left out
+#[derive(Serialize)]
+struct AuthenticatedEntry<'a> {
+    version: u32,
+    k: u32,
+    sha256: &'a str,
+    prev_sha256: &'a Option<String>,
+    s_g: &'a str,
+    report: &'a ContributionReport,
+}
+
+impl CacheEntry {
+    pub fn new<C: SerdeObject>(
+        prev_sha256: Option<String>,
+        s_g: &C,
+        report: ContributionReport,
+    ) -> Self {
+        CacheEntry {
+            prev_sha256,
+            s_g: hex::encode(s_g.to_raw_bytes()),
+            report,
+            mac: String::new(),
+        }
+    }
+
+    // Returns the MAC of the entry for a cache of ceremony size k
+    fn compute_mac(&self, key: &[u8], k: u32) -> Result<Blake2bMac512> {
+        let report = ContributionReport {
+            path: PathBuf::new(),
+            timings: Timings::default(),
+            attestation: None,
+            cached: false,
+            ..self.report.clone()
+        };
+        let message = serde_json::to_vec(&AuthenticatedEntry {
+            version: CACHE_VERSION,
+            k,
+            sha256: &self.report.sha256,
+            prev_sha256: &self.prev_sha256,
+            s_g: &self.s_g,
+            report: &report,
+        })?;
+
+        let mut mac = <Blake2bMac512 as Mac>::new_from_slice(key)
+            .map_err(|_| anyhow!("Invalid verification cache key"))?;
+        mac.update(&message);
+        Ok(mac)
+    }
+
+    // Returns true if the entry was authenticated with the given key
+    fn is_authentic(&self, key: &[u8], k: u32) -> bool {
+        match (hex::decode(&self.mac), self.compute_mac(key, k)) {
+            (Ok(tag), Ok(mac)) => mac.verify_slice(&tag).is_ok(),
+            _ => false,
+        }
+    }
+
+    /// Returns the contribution's s_g
+    pub fn s_g<C: SerdeObject>(&self) -> Result<C> {
+        let bytes = hex::decode(&self.s_g)?;
+        C::from_raw_bytes(&bytes)
+            .ok_or_else(|| anyhow!("Cached s_g for contribution {} is invalid", self.report.id))
+    }
+}
+
+/// Results of previous chain verifications, indexed by the SHA256 of contribution files.
+///
+/// Only contributions which passed all checks are recorded. A cached result is reused only if
+/// the contribution file and the previous contribution file are unchanged.
+///
+/// Each entry is authenticated with a keyed MAC under a key stored in the user cache directory,
+/// never next to the contributions: entries not written by the current user, e.g. shipped along
+/// with the contributions, are discarded and the corresponding contributions fully verified.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VerificationCache {
+    version: u32,
+    k: u32,
+    entries: HashMap<String, CacheEntry>,
+    #[serde(skip)]
+    key: Vec<u8>,
+}
+
+impl VerificationCache {
+    pub fn new(config: &CeremonyConfig) -> Result<Self> {
+        Ok(VerificationCache {
+            version: CACHE_VERSION,
+            k: config.k(),
+            entries: HashMap::new(),
+            key: load_or_create_key()?,
+        })
+    }
+
+    /// Returns the default cache path for a contributions directory: a file in the user cache directory
+    /// named after the SHA256 of the canonical contributions path
+    pub fn default_path(contributions_path: &Path) -> Result<PathBuf> {
+        let contributions_path = fs::canonicalize(contributions_path)?;
+        let path_hash = Sha256::digest(contributions_path.to_string_lossy().as_bytes());
+        Ok(user_cache_dir()?.join(format!(
+            "check_cache_{}.json",
+            &hex::encode(path_hash)[..16]
+        )))
+    }
+
+    /// Loads the cache from disk. A missing or incompatible cache results in an empty cache,
+    /// while entries which fail authentication are discarded
+    pub fn load(path: &Path, config: &CeremonyConfig) -> Result<Self> {
+        if !path.is_file() {
+            info!("No verification cache found in {:#?}", path.display());
+            return VerificationCache::new(config);
+        }
+
+        let reader = BufReader::new(File::open(path)?);
+        let mut cache: VerificationCache = match serde_json::from_reader(reader) {
+            Ok(cache) => cache,
+            Err(e) => {
+                warn!(
+                    "Discarding unreadable verification cache {:#?}: {}",
+                    path.display(),
+                    e
+                );
+                return VerificationCache::new(config);
+            }
+        };
+
+        if cache.version != CACHE_VERSION || cache.k != config.k() {
+            warn!(
+                "Discarding verification cache {:#?} computed with a different version or ceremony k",
+                path.display()
+            );
+            return VerificationCache::new(config);
+        }
+
+        cache.key = load_or_create_key()?;
+        let (key, k) = (&cache.key, cache.k);
+        let count = cache.entries.len();
+        cache
+            .entries
+            .retain(|sha256, entry| sha256 == &entry.report.sha256 && entry.is_authentic(key, k));
+        if cache.entries.len() < count {
+            warn!(
+                "Discarding {} entries of verification cache {:#?} not written by this user: the corresponding contributions will be fully verified",
+                count - cache.entries.len(),
+                path.display()
+            );
+        }
+
+        info!(
+            "Loaded {} verified contributions from cache {:#?}",
+            cache.entries.len(),
+            path.display()
+        );
+        Ok(cache)
+    }
+
+    /// Writes the cache to disk, replacing any previous version atomically
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let mut tmp_path = path.to_path_buf();
+        tmp_path.set_extension("tmp");
+
+        let mut writer = BufWriter::new(File::create(&tmp_path)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        drop(writer);
+
+        fs::rename(&tmp_path, path)?;
+        Ok(())
+    }
+
+    /// Returns the cached entry for a contribution file, if it was verified against the same previous contribution file
+    pub fn get(&self, sha256: &str, prev_sha256: Option<&str>) -> Option<&CacheEntry> {
+        self.entries
+            .get(sha256)
+            .filter(|entry| entry.prev_sha256.as_deref() == prev_sha256)
+    }
+
+    /// Records a contribution which passed all checks, authenticating it
+    pub fn insert(&mut self, mut entry: CacheEntry) -> Result<()> {
+        if entry.report.is_valid() {
+            entry.mac = hex::encode(
+                entry
+                    .compute_mac(&self.key, self.k)?
+                    .finalize()
+                    .into_bytes(),
+            );
+            self.entries.insert(entry.report.sha256.clone(), entry);
+        }
+        Ok(())
+    }
+}
+
+/// Returns the directory holding verification caches and their key:
+/// `ceremony` in $XDG_CACHE_HOME, ~/.cache or, on Windows, %LOCALAPPDATA%
+pub fn user_cache_dir() -> Result<PathBuf> {
+    let base = env::var_os("XDG_CACHE_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".cache")))
+        .or_else(|| env::var_os("LOCALAPPDATA").map(PathBuf::from))
+        .ok_or_else(|| anyhow!("Cannot determine the user cache directory"))?;
+    Ok(base.join(CACHE_DIRNAME))
+}
+
+// Reads the key authenticating cache entries, generating it on first use.
+// On Unix the key is readable by its owner only
+fn load_or_create_key() -> Result<Vec<u8>> {
+    let dir = user_cache_dir()?;
+    fs::create_dir_all(&dir)?;
+    let key_path = dir.join(CACHE_KEY_FILENAME);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    match options.open(&key_path) {
+        Ok(mut file) => {
+            let mut key = vec![0u8; CACHE_KEY_LENGTH];
+            OsRng.fill_bytes(&mut key);
+            file.write_all(&key)?;
+            file.sync_all()?;
+            info!("Generated verification cache key {:#?}", key_path.display());
+            Ok(key)
+        }
+        Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+            let mut key = Vec::new();
+            File::open(&key_path)?.read_to_end(&mut key)?;
+            if key.len() != CACHE_KEY_LENGTH {
+                return Err(anyhow!(
+                    "Verification cache key {:#?} is corrupted: remove it to discard all cached results",
+                    key_path.display()
+                ));
+            }
+            Ok(key)
+        }
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::report::{ProofStatus, SrsReport};
+
+    fn entry() -> CacheEntry {
+        let report = ContributionReport {
+            id: 1,
+            path: PathBuf::from("contributions/contribution_00001.csrs"),
+            sha256: "ab".repeat(32),
+            ceremony_size: true,
+            srs: SrsReport {
+                generators: true,
+                g2_order: true,
+                size: true,
+                well_formed: true,
+                non_degenerative: true,
+            },
+            proof: ProofStatus::Valid,
+            public_s: None,
+            timings: Timings::default(),
+            attestation: None,
+            cached: false,
+        };
+        CacheEntry {
+            prev_sha256: Some("cd".repeat(32)),
+            s_g: "01".repeat(64),
+            report,
+            mac: String::new(),
+        }
+    }
+
+    fn authenticate(mut entry: CacheEntry, key: &[u8]) -> CacheEntry {
+        entry.mac = hex::encode(entry.compute_mac(key, 22).unwrap().finalize().into_bytes());
+        entry
+    }
+
+    #[test]
+    fn authenticated_entry_survives_serialization() {
+        let key = [7u8; CACHE_KEY_LENGTH];
+        let mut entry = authenticate(entry(), &key);
+        // Fields recomputed on each run are not authenticated
+        entry.report.timings.hash = 1.5;
+        entry.report.path = PathBuf::from("elsewhere");
+
+        let entry: CacheEntry =
+            serde_json::from_slice(&serde_json::to_vec(&entry).unwrap()).unwrap();
+        assert!(entry.is_authentic(&key, 22));
+    }
+
+    #[test]
+    fn forged_entries_are_rejected() {
+        let key = [7u8; CACHE_KEY_LENGTH];
+
+        // No MAC, or a MAC under another key
+        assert!(!entry().is_authentic(&key, 22));
+        assert!(!authenticate(entry(), &[8u8; CACHE_KEY_LENGTH]).is_authentic(&key, 22));
+
+        // A MAC for another ceremony size
+        assert!(!authenticate(entry(), &key).is_authentic(&key, 23));
+
+        // Tampered results or s_g
+        let mut tampered = authenticate(entry(), &key);
+        tampered.report.proof = ProofStatus::Skipped;
+        assert!(!tampered.is_authentic(&key, 22));
+        let mut tampered = authenticate(entry(), &key);
+        tampered.s_g = "02".repeat(64);
+        assert!(!tampered.is_authentic(&key, 22));
+    }
+}
@@ -1,143 +1,380 @@
 use anyhow::{anyhow, Result};
 use blake2::{Blake2b512, Digest};
-use group::{cofactor::CofactorCurveAffine, ff::FromUniformBytes};
+use core::fmt::Debug;
+use group::{ff::FromUniformBytes, prime::PrimeCurveAffine, Group};
 use halo2_proofs::arithmetic::Field;
 use halo2_proofs::{
     arithmetic::parallelize,
-    halo2curves::{
-        bn256::{pairing, Bn256, Fr, G1Affine, G2Affine, G2},
-        msm::best_multiexp,
-        serde::SerdeObject,
-    },
+    halo2curves::{msm::best_multiexp, pairing::Engine, serde::SerdeObject},
 };
 use log::{error, info, warn};
-use std::path::PathBuf;
+use rand::rngs::OsRng;
+use std::path::{Path, PathBuf};
+use std::time::Instant;
 
-use crate::contribution::{KZGContribution, CONTRIBUTION_K, CONTRIBUTION_N};
-use crate::proof::verify_contribution_proof;
-use crate::utils::get_contributions_list;
+use crate::attestation::{Attestation, AttestationReport};
+use crate::cache::{CacheEntry, VerificationCache};
+use crate::config::CeremonyConfig;
+use crate::contribution::{g_chunks, ContributionView, SerdeCurveAffine, G1_CHUNK_LENGTH};
+use crate::header::CurveIdentifier;
+use crate::mapped::LoadedContribution;
+use crate::progress::Progress;
+use crate::proof::{recover_public_s, verify_contribution_proof, ProofContext};
+use crate::report::{ChainReport, ContributionReport, ProofStatus, SrsReport, Timings};
+use crate::secrets::{gen_secrets, SecretsGenConfig};
+use crate::utils::{decode_sha256, get_contributions_list, hash_file};
 
-pub fn check_contribution(contribution: &KZGContribution<Bn256>) -> Result<bool> {
-    info!("Checking contribution with id {:#?}", contribution.id());
+pub fn check_contribution<E: Engine + Debug, V: ContributionView<E>>(
+    contribution: &V,
+    config: &CeremonyConfig,
+) -> Result<bool>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64>,
+{
+    let (ceremony_size, srs_report) = verify_contribution(contribution, config)?;
+    Ok(ceremony_size && srs_report.is_valid())
+}
 
-    let mut result: bool = true;
+// Checks the contribution's size against the ceremony config and the validity of its SRS
+fn verify_contribution<E: Engine + Debug, V: ContributionView<E>>(
+    contribution: &V,
+    config: &CeremonyConfig,
+) -> Result<(bool, SrsReport)>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64>,
+{
+    info!("Checking contribution with id {:#?}", contribution.id());
 
-    let check_result = (contribution.k() == CONTRIBUTION_K) && (contribution.n() == CONTRIBUTION_N);
-    if check_result {
+    let ceremony_size = config.ensure_matches(contribution).is_ok();
+    if ceremony_size {
         info!("Contribution's k and n are correct");
     } else {
         error!("Contribution's k and n are NOT correct");
     }
-    result &= check_result;
 
-    let check_result = check_srs(contribution.g(), contribution.g2(), contribution.s_g2())?;
-    if check_result {
+    let srs_report = check_srs::<E, V>(contribution, config.n())?;
+    if srs_report.is_valid() {
         info!("SRS is valid");
     } else {
         error!("SRS is NOT valid");
     }
-    result &= check_result;
 
-    if result {
+    if ceremony_size && srs_report.is_valid() {
         info!("Contribution with id {:#?} is valid", contribution.id());
     } else {
         error!("Contribution with id {:#?} is NOT valid", contribution.id());
     }
 
-    Ok(result)
+    Ok((ceremony_size, srs_report))
 }
 
-// It verifies that e(a^(i-1)*P, a*P') = e(a^i*P, P') for all i in [1, n]
-fn check_srs(g: &Vec<G1Affine>, g2: &G2Affine, s_g2: &G2Affine) -> Result<bool> {
-    let mut result: bool = true;
+/// Checks that a SRS is well-formed, i.e. it verifies that e(a^(i-1)*P, a*P') = e(a^i*P, P') for all i in [1, n].
+/// The points in G1 are read in chunks, so that those of a memory-mapped contribution are never all decoded at once
+pub fn check_srs<E: Engine, V: ContributionView<E> + ?Sized>(
+    contribution: &V,
+    n: usize,
+) -> Result<SrsReport>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64>,
+{
+    // We hash all the inputs to get a random scalar rho
+    let mut hasher = Blake2b512::new();
+    let progress = Progress::start("Hashing the SRS", contribution.n());
+    for (_, g) in g_chunks(contribution, G1_CHUNK_LENGTH) {
+        for el in g.iter() {
+            hasher.update(el.to_raw_bytes());
+        }
+        progress.advance(g.len());
+    }
+    progress.finish();
+    let (g2, s_g2) = (contribution.g2(), contribution.s_g2());
+    hasher.update(g2.to_raw_bytes());
+    hasher.update(s_g2.to_raw_bytes());
+    let rho = E::Fr::from_uniform_bytes(hasher.finalize().as_ref());
 
-    // Check #1: We check that g[0] and g2 are the hardcoded generators for G1 and G2, respectively
-    let check_result = (g[0] == G1Affine::generator()) && (*g2 == G2Affine::generator());
-    if check_result {
-        info!("> SRS Check #1 Succeded: srs uses hardcoded group generators");
-    } else {
-        error!("> SRS Check #1 Failed: srs does not use hardcoded group generators");
-    }
-    result &= check_result;
-
-    // Check #2: We check that s_g2 has order r
-    // Note that [r] * g = O <=> [r-1] * g = -g
-    // Since r in Fr is encoded as 0, the latter equation allows us to actually check the order through a full-size scalar multiplication
-    // Indeed, -Fr::one() is a full bitsize unsigned scalar corresponding to r-1
-    let g2_proj: G2 = g2.into();
-    let s_g2_proj: G2 = s_g2.into();
-    let check_result =
-        (g2_proj * (-Fr::one()) == -g2_proj) && (s_g2_proj * (-Fr::one()) == -s_g2_proj);
-    if check_result {
-        info!("> SRS Check #2 Succeded: g2 and s_g2 have order r");
-    } else {
-        error!("> SRS Check #2 Failed: g2 or s_g2 does not have order r");
+    // We compute the msm \sum_{i=0}^{n-1} rho^i * g[i]
+    // The msm is computed in chunks to report its progress: the sum of the chunks' msms is the msm of g
+    let mut checker = SrsChecker::<E>::new(rho);
+    let progress = Progress::start("Computing the SRS msm", contribution.n());
+    for (_, g) in g_chunks(contribution, G1_CHUNK_LENGTH) {
+        checker.update(&g);
+        progress.advance(g.len());
     }
-    result &= check_result;
+    progress.finish();
 
-    // Check #3 We check that g has the expected length
-    let check_result = g.len() == CONTRIBUTION_N;
-    if check_result {
-        info!("> SRS Check #3 Succeded: the g vector has expected size");
-    } else {
-        error!("> SRS Check #3 Failed: the g vector has an unexpected size");
+    Ok(checker.finish(g2, s_g2, n))
+}
+
+/// Checks a SRS whose points in G1 are provided in consecutive chunks, so that they never have to be held in memory
+/// all at once. The points are combined with the powers of a scalar rho which must not be predictable from the points:
+/// `check_srs` derives rho from the hash of the SRS, while a SRS read only once can be checked with a random rho
+pub struct SrsChecker<E: Engine> {
+    rho: E::Fr,
+    // rho^i, where i is the number of points provided so far
+    rho_power: E::Fr,
+    count: usize,
+    // \sum_i rho^i * g[i] over the points provided so far
+    msm: E::G1,
+    first: Option<E::G1Affine>,
+    second: Option<E::G1Affine>,
+    last: Option<E::G1Affine>,
+}
+
+impl<E: Engine> SrsChecker<E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+{
+    pub fn new(rho: E::Fr) -> Self {
+        SrsChecker {
+            rho,
+            rho_power: E::Fr::ONE,
+            count: 0,
+            msm: <E::G1 as Group>::identity(),
+            first: None,
+            second: None,
+            last: None,
+        }
     }
-    result &= check_result;
 
-    // Check #4: We check that srs is well-formed
-    // We hash all the inputs to get a random scalar rho
-    let mut hasher = Blake2b512::new();
-    for el in g {
-        hasher.update(el.to_raw_bytes());
+    /// Creates a checker combining the points with the powers of a random scalar
+    pub fn random() -> Self {
+        Self::new(E::Fr::random(OsRng))
     }
-    hasher.update(g2.to_raw_bytes());
-    hasher.update(s_g2.to_raw_bytes());
-    let rho = Fr::from_uniform_bytes(hasher.finalize().as_ref());
 
-    // We create a vector rho_powers = [1, rho, rho^2, .., rho^n-1] with n = g.len()
-    let mut rho_powers = vec![Fr::zero(); g.len()];
-    parallelize(&mut rho_powers, |rho_powers, start| {
-        let mut curr_power = rho.pow_vartime([start as u64, 0, 0, 0]);
-        for rho_power in rho_powers.iter_mut() {
-            *rho_power = curr_power;
-            curr_power *= rho;
+    /// Adds the next points of the SRS to the msm
+    pub fn update(&mut self, g: &[E::G1Affine]) {
+        if g.is_empty() {
+            return;
         }
-    });
 
-    // We compute the msm \sum_{i=0}^{n-1} rho^i * g[i]
-    let g1_msm = best_multiexp(&rho_powers, g);
+        // We create a vector rho_powers = [rho^i, rho^(i+1), .., rho^(i+len-1)] with i the number of points provided so far
+        let (rho, rho_power) = (self.rho, self.rho_power);
+        let mut rho_powers = vec![E::Fr::ZERO; g.len()];
+        parallelize(&mut rho_powers, |rho_powers, start| {
+            let mut curr_power = rho_power * rho.pow_vartime([start as u64, 0, 0, 0]);
+            for rho_power in rho_powers.iter_mut() {
+                *rho_power = curr_power;
+                curr_power *= rho;
+            }
+        });
+        self.msm += best_multiexp(&rho_powers, g);
+        self.rho_power = *rho_powers.last().unwrap() * rho;
 
-    // We check that e( \sum_{i=1}^{n-1} rho^{i-1} * g[i], g2 ) = e( \sum_{i=0}^{n-2} rho^i * g[i], s_g2 )
-    // We compute the left pairing G1 element, i.e. \sum_{i=1}^{n-1} rho^{i-1} * g[i]
-    // Note that g1_msm = \sum_{i=0}^{n-1} rho^i * g[i]
-    let g1_left: G1Affine = ((g1_msm - G1Affine::generator()) * rho.invert().unwrap()).into();
+        if self.count == 0 {
+            self.first = Some(g[0]);
+        }
+        if self.count <= 1 && self.count + g.len() > 1 {
+            self.second = Some(g[1 - self.count]);
+        }
+        self.last = g.last().copied();
+        self.count += g.len();
+    }
 
-    // We compute the right pairing G1 element, i.e. \sum_{i=0}^{n-2} rho^i * g[i]
-    // Note that g1_msm = \sum_{i=0}^{n-1} rho^i * g[i]
-    let g1_right: G1Affine = (g1_msm - g.last().unwrap() * rho_powers.last().unwrap()).into();
+    /// Runs the SRS checks once all points in G1 were provided
+    pub fn finish(self, g2: &E::G2Affine, s_g2: &E::G2Affine, n: usize) -> SrsReport {
+        let g1_generator = <E::G1Affine as PrimeCurveAffine>::generator();
+        let g2_generator = <E::G2Affine as PrimeCurveAffine>::generator();
 
-    let check_result = pairing(&g1_left, &G2Affine::generator()) == pairing(&g1_right, s_g2);
-    if check_result {
-        info!("> SRS Check #4 Succeded: srs is well-formed");
-    } else {
-        error!("> SRS Check #4 Failed: srs is not well-formed");
+        // Check #1: We check that g[0] and g2 are the hardcoded generators for G1 and G2, respectively
+        let check_result = (self.first == Some(g1_generator)) && (*g2 == g2_generator);
+        if check_result {
+            info!("> SRS Check #1 Succeded: srs uses hardcoded group generators");
+        } else {
+            error!("> SRS Check #1 Failed: srs does not use hardcoded group generators");
+        }
+        let generators = check_result;
+
+        // Check #2: We check that s_g2 has order r
+        // Note that [r] * g = O <=> [r-1] * g = -g
+        // Since r in Fr is encoded as 0, the latter equation allows us to actually check the order through a full-size scalar multiplication
+        // Indeed, -Fr::one() is a full bitsize unsigned scalar corresponding to r-1
+        let g2_proj: E::G2 = (*g2).into();
+        let s_g2_proj: E::G2 = (*s_g2).into();
+        let check_result =
+            (g2_proj * (-E::Fr::ONE) == -g2_proj) && (s_g2_proj * (-E::Fr::ONE) == -s_g2_proj);
+        if check_result {
+            info!("> SRS Check #2 Succeded: g2 and s_g2 have order r");
+        } else {
+            error!("> SRS Check #2 Failed: g2 or s_g2 does not have order r");
+        }
+        let g2_order = check_result;
+
+        // Check #3 We check that g has the expected length
+        let check_result = self.count == n;
+        if check_result {
+            info!("> SRS Check #3 Succeded: the g vector has expected size");
+        } else {
+            error!("> SRS Check #3 Failed: the g vector has an unexpected size");
+        }
+        let size = check_result;
+
+        // Check #4: We check that srs is well-formed, i.e.
+        // e( \sum_{i=1}^{n-1} rho^{i-1} * g[i], g2 ) = e( \sum_{i=0}^{n-2} rho^i * g[i], s_g2 )
+        let check_result = match self.last {
+            Some(last) => {
+                let rho_inv = self.rho.invert().unwrap();
+
+                // We compute the left pairing G1 element, i.e. \sum_{i=1}^{n-1} rho^{i-1} * g[i]
+                // Note that msm = \sum_{i=0}^{n-1} rho^i * g[i]
+                let g1_left: E::G1Affine = ((self.msm - g1_generator) * rho_inv).into();
+
+                // We compute the right pairing G1 element, i.e. \sum_{i=0}^{n-2} rho^i * g[i]
+                // Note that rho_power = rho^n
+                let g1_right: E::G1Affine = (self.msm - last * (self.rho_power * rho_inv)).into();
+
+                E::pairing(&g1_left, &g2_generator) == E::pairing(&g1_right, s_g2)
+            }
+            None => false,
+        };
+        if check_result {
+            info!("> SRS Check #4 Succeded: srs is well-formed");
+        } else {
+            error!("> SRS Check #4 Failed: srs is not well-formed");
+        }
+        let well_formed = check_result;
+
+        // Check #5: srs is non-degenerative
+        let check_result = matches!(self.second, Some(second) if second != <E::G1Affine as PrimeCurveAffine>::identity());
+        if check_result {
+            info!("> SRS Check #5 Succeded: srs is non-degenerative");
+        } else {
+            error!("> SRS Check #5 Failed: srs is degenerative");
+        }
+        let non_degenerative = check_result;
+
+        SrsReport {
+            generators,
+            g2_order,
+            size,
+            well_formed,
+            non_degenerative,
+        }
     }
-    result &= check_result;
+}
+
+/// Checks that the contribution with the given id in a verified chain revealed the rescaling factor s through its contribution proof
+pub fn check_revealed_s<E: Engine>(report: &ChainReport, id: u32, s: &E::Fr) -> Result<bool> {
+    let contribution = report
+        .contributions
+        .iter()
+        .find(|contribution| contribution.id == id)
+        .ok_or_else(|| anyhow!("No contribution with id {} found in the chain", id))?;
 
-    // Check #5: srs is non-degenerative
-    let check_result = g[1] != G1Affine::identity();
-    if check_result {
-        info!("> SRS Check #5 Succeded: srs is non-degenerative");
+    let s = format!("{:?}", s);
+    let is_valid = match &contribution.public_s {
+        Some(public_s) if *public_s == s => {
+            info!(
+                "Contribution with id {} revealed the expected rescaling factor s {}",
+                id, s
+            );
+            true
+        }
+        Some(public_s) => {
+            error!(
+                "Contribution with id {} revealed the rescaling factor s {} but {} was expected",
+                id, public_s, s
+            );
+            false
+        }
+        None => {
+            error!(
+                "Contribution with id {} does NOT reveal its rescaling factor s",
+                id
+            );
+            false
+        }
+    };
+
+    Ok(is_valid)
+}
+
+/// Recomputes the rescaling factor s of a public contribution from its declared secrets generation inputs,
+/// and checks that it is the one revealed by its contribution proof. The result is recorded in the report.
+/// Random bytes cannot be reproduced, so they must not be part of the declared inputs
+pub fn check_recomputed_s<E: Engine>(
+    report: &mut ChainReport,
+    id: u32,
+    config: SecretsGenConfig,
+) -> Result<bool>
+where
+    E::Fr: FromUniformBytes<64>,
+{
+    if config.random_bytes_size.is_some() {
+        return Err(anyhow!(
+            "Secrets generated from random bytes cannot be recomputed"
+        ));
+    }
+
+    // gen_secrets skips paths which are not files, while all declared inputs are required here
+    for path in config.files_to_hash.iter().flatten() {
+        if !Path::new(path).is_file() {
+            return Err(anyhow!("The declared input {:#?} is not a file", path));
+        }
+    }
+
+    info!(
+        "Recomputing the rescaling factor s of contribution with id {}",
+        id
+    );
+    let (s, _z) = gen_secrets::<E>(SecretsGenConfig {
+        reveal_s: true,
+        ..config
+    })?;
+
+    let is_valid = check_revealed_s::<E>(report, id, s.get())?;
+    report.recomputed_s = Some(is_valid);
+    report.valid &= is_valid;
+
+    Ok(is_valid)
+}
+
+// Verifies the attestation of a contribution file, if any
+fn verify_attestation(
+    id: u32,
+    contribution_path: &Path,
+    sha256: &str,
+) -> Result<Option<AttestationReport>> {
+    let Some(attestation) = Attestation::read(contribution_path)? else {
+        info!("Contribution with id {} has no attestation", id);
+        return Ok(None);
+    };
+
+    let report = attestation.report(sha256);
+    if report.valid {
+        info!(
+            "Contribution with id {} is attested by {:#?} (public key {}, timestamp {}): {:#?}",
+            id, report.handle, report.public_key, report.timestamp, report.message
+        );
     } else {
-        error!("> SRS Check #5 Failed: srs is degenerative");
+        error!("Attestation for contribution with id {} is NOT valid", id);
     }
-    result &= check_result;
 
-    Ok(result)
+    Ok(Some(report))
 }
 
-pub fn check_contribution_chain(contributions_path: &str) -> Result<bool> {
+/// Verifies all contributions found in contributions_path.
+/// If a cache path is given, contributions already verified against the same previous contribution are not checked again,
+/// unless `full` is set. Newly verified contributions are recorded in the cache
+pub fn check_contribution_chain<E: Engine + Debug + CurveIdentifier>(
+    contributions_path: &str,
+    config: &CeremonyConfig,
+    cache_path: Option<&Path>,
+    full: bool,
+) -> Result<ChainReport>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
     let mut chain_is_valid = true;
+    let mut reports: Vec<ContributionReport> = Vec::new();
 
     let contributions_path = PathBuf::from(contributions_path);
     let contributions_list = get_contributions_list(&contributions_path)?;
@@ -152,37 +389,114 @@ pub fn check_contribution_chain(contributions_path: &str) -> Result<bool> {
         contributions_ids.sort_unstable();
     }
 
+    let mut cache = match cache_path {
+        Some(cache_path) => Some(VerificationCache::load(cache_path, config)?),
+        None => None,
+    };
+    if full {
+        info!("Full verification requested: cached results are ignored");
+    }
+
     // We iterate over all contributions found
-    let mut prev_s_g: Option<G1Affine> = None;
+    let mut prev_s_g: Option<E::G1Affine> = None;
+    let mut prev_sha256: Option<String> = None;
     for id in contributions_ids.iter() {
-        let curr_contribution: KZGContribution<Bn256> =
-            KZGContribution::from(contributions_list.get(id).unwrap())?;
+        let contribution_path = contributions_list.get(id).unwrap();
+        let mut timings = Timings::default();
+
+        let timer = Instant::now();
+        let sha256 = hash_file(contribution_path)?;
+        timings.hash = timer.elapsed().as_secs_f64();
+        info!("Contribution with id {} has SHA256 {}", id, sha256);
+
+        // Attestations are stored apart from contribution files, so they are always verified
+        let attestation = verify_attestation(*id, contribution_path, &sha256)?;
+
+        // A contribution is verified against its predecessor only, so the cached result can be reused
+        // if both the contribution and its predecessor are unchanged
+        let cached_entry = cache
+            .as_ref()
+            .filter(|_| !full)
+            .and_then(|cache| cache.get(&sha256, prev_sha256.as_deref()))
+            .filter(|entry| entry.report.id == *id);
+        if let Some(entry) = cached_entry {
+            info!(
+                "Contribution with id {} was already verified: using cached results",
+                id
+            );
+            let mut report = entry.report.clone();
+            report.path = contribution_path.to_owned();
+            report.timings = Timings {
+                hash: timings.hash,
+                ..Timings::default()
+            };
+            report.attestation = attestation;
+            report.cached = true;
+
+            if contributions_ids.contains(&(id + 1)) {
+                prev_s_g = Some(entry.s_g()?);
+                prev_sha256 = Some(sha256);
+            } else {
+                prev_s_g = None;
+                prev_sha256 = None;
+            }
+
+            chain_is_valid &= report.is_valid();
+            reports.push(report);
+            continue;
+        }
 
-        let srs_check = check_contribution(&curr_contribution)?;
+        let timer = Instant::now();
+        let curr_contribution: LoadedContribution<E> = LoadedContribution::open(contribution_path)?;
+        timings.load = timer.elapsed().as_secs_f64();
+
+        let timer = Instant::now();
+        let (ceremony_size, srs_report) = verify_contribution(&curr_contribution, config)?;
+        timings.srs_check = timer.elapsed().as_secs_f64();
 
         // We verify the contribution proof.
         // Note that contribution with id 0 cannot have a contribution proof, which is set to a default value
-        let proof_check = if curr_contribution.id() == 0 {
+        let timer = Instant::now();
+        let mut public_s = None;
+        let proof_status = if curr_contribution.id() == 0 {
             warn!(
                 "Skipping contribution proof check for contribution with id {}",
                 curr_contribution.id()
             );
-            true
-        } else if let Some(prev_s_g) = prev_s_g {
-            verify_contribution_proof(
+            ProofStatus::Skipped
+        } else if let (Some(prev_s_g), Some(prev_sha256)) = (prev_s_g, prev_sha256.as_deref()) {
+            // Version 2 proofs are bound to the previous contribution file and to the contribution id
+            let context = ProofContext {
+                prev_sha256: decode_sha256(prev_sha256)?,
+                id: curr_contribution.id(),
+            };
+            if verify_contribution_proof(
                 &prev_s_g,
                 curr_contribution.s_g(),
                 curr_contribution.proof(),
-            )
+                &context,
+            ) {
+                public_s = recover_public_s(
+                    &prev_s_g,
+                    curr_contribution.s_g(),
+                    curr_contribution.proof(),
+                    &context,
+                )
+                .map(|s| format!("{:?}", s));
+                ProofStatus::Valid
+            } else {
+                ProofStatus::Invalid
+            }
         } else {
             error!(
                 "Missing contribution with id {}: cannot verify proof for contribution with id {}",
                 curr_contribution.id() - 1,
                 curr_contribution.id()
             );
-            false
+            ProofStatus::MissingPrevious
         };
-        if proof_check {
+        timings.proof_check = timer.elapsed().as_secs_f64();
+        if proof_status.is_valid() {
             info!(
                 "Contribution proof for contribution with id {} is valid",
                 curr_contribution.id()
@@ -194,14 +508,42 @@ pub fn check_contribution_chain(contributions_path: &str) -> Result<bool> {
             );
         }
 
-        // We save g[1] and s_g2 for next contribution, if present
+        let report = ContributionReport {
+            id: curr_contribution.id(),
+            path: contribution_path.to_owned(),
+            sha256: sha256.clone(),
+            ceremony_size,
+            srs: srs_report,
+            proof: proof_status,
+            public_s,
+            timings,
+            attestation,
+            cached: false,
+        };
+
+        // We record valid contributions in the cache as soon as they are verified
+        if let Some(cache) = cache.as_mut() {
+            if report.is_valid() {
+                cache.insert(CacheEntry::new(
+                    prev_sha256.clone(),
+                    curr_contribution.s_g(),
+                    report.clone(),
+                ))?;
+                cache.save(cache_path.unwrap())?;
+            }
+        }
+
+        // We save g[1] and the file hash for next contribution, if present
         if contributions_ids.contains(&(curr_contribution.id() + 1)) {
             prev_s_g = Some(*curr_contribution.s_g());
+            prev_sha256 = Some(sha256);
         } else {
             prev_s_g = None;
+            prev_sha256 = None;
         }
 
-        chain_is_valid &= srs_check & proof_check;
+        chain_is_valid &= report.is_valid();
+        reports.push(report);
     }
 
     if chain_is_valid {
@@ -210,5 +552,81 @@ pub fn check_contribution_chain(contributions_path: &str) -> Result<bool> {
         error!("Contributions' chain is NOT valid");
     }
 
-    Ok(chain_is_valid)
+    Ok(ChainReport {
+        contributions: reports,
+        valid: chain_is_valid,
+        beacon: None,
+        recomputed_s: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribution::KZGContribution;
+    use crate::proof::ContributionProof;
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G2Affine};
+
+    const N: usize = 16;
+
+    fn srs(tau: Fr) -> (Vec<G1Affine>, G2Affine, G2Affine) {
+        let mut power = Fr::ONE;
+        let g = (0..N)
+            .map(|_| {
+                let point = (G1Affine::generator() * power).into();
+                power *= tau;
+                point
+            })
+            .collect();
+        (
+            g,
+            G2Affine::generator(),
+            (G2Affine::generator() * tau).into(),
+        )
+    }
+
+    // Checks the SRS with a random rho, providing its points in chunks of 3
+    fn check_chunked(g: &[G1Affine], g2: &G2Affine, s_g2: &G2Affine) -> SrsReport {
+        let mut checker = SrsChecker::<Bn256>::random();
+        for chunk in g.chunks(3) {
+            checker.update(chunk);
+        }
+        checker.finish(g2, s_g2, N)
+    }
+
+    // Checks the SRS through a contribution, with rho derived from its hash
+    fn check_hashed(g: &[G1Affine], g2: &G2Affine, s_g2: &G2Affine) -> SrsReport {
+        let contribution = KZGContribution::<Bn256>::from_parts(
+            N.trailing_zeros(),
+            g.to_vec(),
+            *g2,
+            *s_g2,
+            ContributionProof::default(),
+            1,
+        );
+        check_srs::<Bn256, _>(&contribution, N).unwrap()
+    }
+
+    #[test]
+    fn chunked_check_accepts_valid_srs() {
+        let (g, g2, s_g2) = srs(Fr::from(3));
+        assert!(check_chunked(&g, &g2, &s_g2).is_valid());
+        assert!(check_hashed(&g, &g2, &s_g2).is_valid());
+    }
+
+    #[test]
+    fn chunked_check_rejects_invalid_srs() {
+        let (mut g, g2, s_g2) = srs(Fr::from(3));
+        g[7] = (g[7] * Fr::from(2)).into();
+        let report = check_chunked(&g, &g2, &s_g2);
+        assert!(!report.well_formed);
+        assert!(!check_hashed(&g, &g2, &s_g2).is_valid());
+
+        let (g, _, s_g2) = srs(Fr::from(3));
+        let report = check_chunked(&g, &s_g2, &s_g2);
+        assert!(!report.generators);
+
+        let report = check_chunked(&g[..N - 1], &g2, &s_g2);
+        assert!(!report.size);
+    }
 }
@@ -0,0 +1,458 @@
+use anyhow::{anyhow, Result};
+use argon2::Argon2;
+use chacha20poly1305::{
+    aead::{Aead, KeyInit, Payload},
+    ChaCha20Poly1305, Key, Nonce,
+};
+use group::ff::Field;
+use halo2_proofs::{
+    halo2curves::{pairing::Engine, serde::SerdeObject},
+    SerdeFormat,
+};
+use log::info;
+use rand::{rngs::OsRng, Rng, RngCore};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::chunked::ContributionReader;
+use crate::config::PointEncoding;
+use crate::contribution::{SerdeCurveAffine, CONTRIBUTION_EXTENSION};
+use crate::header::{ContributionLayout, CurveIdentifier, CONTRIBUTION_HEADER_LENGTH};
+use crate::locked::Locked;
+use crate::secrets::Secret;
+
+/// Extension of the checkpoint written next to a partial contribution
+pub const CHECKPOINT_EXTENSION: &str = "checkpoint";
+
+// Number of randomly sampled points checked when resuming, in addition to the first and the last written points
+const RESUME_SAMPLED_POINTS: usize = 32;
+
+/// Settings of checkpointed contributions
+pub struct CheckpointConfig {
+    /// The passphrase from which the key encrypting the secrets is derived
+    pub passphrase: Zeroizing<String>,
+    /// Resume the contribution from an existing checkpoint
+    pub resume: bool,
+}
+
+/// The progress of a contribution computation, from which it can be resumed.
+/// The secrets are encrypted with ChaCha20-Poly1305 under a key derived from a passphrase with Argon2id,
+/// and are bound to the previous contribution and to the new contribution id
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    /// SHA256 of the previous contribution file, hex encoded
+    pub prev_sha256: String,
+    pub id: u32,
+    pub k: u32,
+    pub encoding: PointEncoding,
+    /// The number of powers of tau rescaled and written to the partial contribution
+    pub index: usize,
+    /// The byte length of the partial contribution covering those points
+    pub partial_length: u64,
+    salt: String,
+    nonce: String,
+    encrypted_secrets: String,
+}
+
+/// Prompts for the passphrase protecting checkpoints, asking for a confirmation when a new one is set
+pub fn prompt_passphrase(confirm: bool) -> Result<Zeroizing<String>> {
+    let passphrase = Zeroizing::new(rpassword::prompt_password("Checkpoint passphrase: ")?);
+    if passphrase.is_empty() {
+        return Err(anyhow!("The checkpoint passphrase cannot be empty"));
+    }
+    if confirm {
+        let confirmation = Zeroizing::new(rpassword::prompt_password(
+            "Confirm checkpoint passphrase: ",
+        )?);
+        if *confirmation != *passphrase {
+            return Err(anyhow!("The checkpoint passphrases do not match"));
+        }
+    }
+    Ok(passphrase)
+}
+
+/// Returns the path of the checkpoint of a contribution
+pub fn checkpoint_filepath(filepath: &Path) -> PathBuf {
+    let mut checkpoint_filepath = filepath.to_path_buf();
+    checkpoint_filepath.set_extension(format!(
+        "{}.{}",
+        CONTRIBUTION_EXTENSION, CHECKPOINT_EXTENSION
+    ));
+    checkpoint_filepath
+}
+
+// Derives the key encrypting the secrets from the passphrase
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<Locked<[u8; 32]>> {
+    let mut key = Locked::new([0u8; 32]);
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut *key)
+        .map_err(|e| anyhow!("Cannot derive the checkpoint key: {}", e))?;
+    Ok(key)
+}
+
+// The encrypted secrets are bound to the previous contribution and to the new contribution id
+fn associated_data(prev_sha256: &str, id: u32) -> Vec<u8> {
+    [prev_sha256.as_bytes(), &id.to_le_bytes()].concat()
+}
+
+impl Checkpoint {
+    /// Creates the checkpoint of a contribution which has not been rescaled yet, encrypting its secrets
+    pub fn new<E: Engine>(
+        prev_sha256: String,
+        id: u32,
+        k: u32,
+        encoding: PointEncoding,
+        passphrase: &str,
+        s: &Secret<E>,
+        z: &Secret<E>,
+    ) -> Result<Self>
+    where
+        E::Fr: SerdeObject,
+    {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let mut secrets = Vec::new();
+        s.get().write_raw(&mut secrets)?;
+        z.get().write_raw(&mut secrets)?;
+
+        let key = derive_key(passphrase, &salt)?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&*key));
+        let encrypted_secrets = cipher.encrypt(
+            Nonce::from_slice(&nonce),
+            Payload {
+                msg: &secrets,
+                aad: &associated_data(&prev_sha256, id),
+            },
+        );
+        secrets.zeroize();
+        let encrypted_secrets =
+            encrypted_secrets.map_err(|_| anyhow!("Cannot encrypt the checkpoint secrets"))?;
+
+        Ok(Checkpoint {
+            prev_sha256,
+            id,
+            k,
+            encoding,
+            index: 0,
+            partial_length: CONTRIBUTION_HEADER_LENGTH as u64,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            encrypted_secrets: hex::encode(encrypted_secrets),
+        })
+    }
+
+    /// Decrypts the secrets stored in the checkpoint
+    pub fn secrets<E: Engine>(&self, passphrase: &str) -> Result<(Secret<E>, Secret<E>)>
+    where
+        E::Fr: SerdeObject,
+    {
+        let key = derive_key(passphrase, &hex::decode(&self.salt)?)?;
+        let cipher = ChaCha20Poly1305::new(Key::from_slice(&*key));
+        let nonce = hex::decode(&self.nonce)?;
+        if nonce.len() != 12 {
+            return Err(anyhow!("The checkpoint nonce is not valid"));
+        }
+
+        let mut secrets = cipher
+            .decrypt(
+                Nonce::from_slice(&nonce),
+                Payload {
+                    msg: &hex::decode(&self.encrypted_secrets)?,
+                    aad: &associated_data(&self.prev_sha256, self.id),
+                },
+            )
+            .map_err(|_| {
+                anyhow!("Cannot decrypt the checkpoint secrets: wrong passphrase or corrupted checkpoint")
+            })?;
+
+        let mut reader = secrets.as_slice();
+        let s = <E::Fr as SerdeObject>::read_raw(&mut reader).map(Secret::new);
+        let z = <E::Fr as SerdeObject>::read_raw(&mut reader).map(Secret::new);
+        secrets.zeroize();
+
+        Ok((s?, z?))
+    }
+
+    /// Reads a checkpoint
+    pub fn read(filepath: &Path) -> Result<Self> {
+        let reader = BufReader::new(File::open(filepath).map_err(|e| {
+            anyhow!(
+                "Cannot open the checkpoint {:#?}: {}",
+                filepath.display(),
+                e
+            )
+        })?);
+        serde_json::from_reader(reader).map_err(|e| anyhow!("Cannot parse the checkpoint: {}", e))
+    }
+
+    /// Writes the checkpoint, replacing the previous one only once it is completely written
+    pub fn write(&self, filepath: &Path) -> Result<()> {
+        let mut tmp_filepath = filepath.to_path_buf();
+        tmp_filepath.set_extension(format!("{}.tmp", CHECKPOINT_EXTENSION));
+
+        // On Unix the checkpoint is readable by its owner only
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let mut writer = BufWriter::new(options.open(&tmp_filepath)?);
+        serde_json::to_writer(&mut writer, self)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        fs::rename(&tmp_filepath, filepath)?;
+        Ok(())
+    }
+
+    /// Overwrites the checkpoint with zeros and removes it
+    pub fn wipe(filepath: &Path) -> Result<()> {
+        let length = fs::metadata(filepath)?.len();
+        let mut file = OpenOptions::new().write(true).open(filepath)?;
+        file.write_all(&vec![0u8; length as usize])?;
+        file.sync_all()?;
+        drop(file);
+
+        fs::remove_file(filepath)?;
+        info!("Checkpoint {:#?} wiped", filepath.display());
+        Ok(())
+    }
+}
+
+/// Checks that a partial contribution is consistent with its checkpoint: the partial contribution is truncated to the
+/// checkpointed length, its header must match the checkpoint, and the first, the last and randomly sampled written points
+/// must be the corresponding points of the previous contribution rescaled by s
+pub fn verify_partial<E: Engine + CurveIdentifier>(
+    partial_filepath: &Path,
+    prev_filepath: &Path,
+    checkpoint: &Checkpoint,
+    s: &Secret<E>,
+) -> Result<()>
+where
+    E::G1Affine: SerdeCurveAffine,
+{
+    let partial_length = fs::metadata(partial_filepath)?.len();
+    if partial_length < checkpoint.partial_length {
+        return Err(anyhow!(
+            "The partial contribution has {} bytes but the checkpoint covers {} bytes",
+            partial_length,
+            checkpoint.partial_length
+        ));
+    }
+
+    // Points written after the last checkpoint are discarded
+    OpenOptions::new()
+        .write(true)
+        .open(partial_filepath)?
+        .set_len(checkpoint.partial_length)?;
+
+    let mut partial_reader = BufReader::new(File::open(partial_filepath)?);
+    let header = match ContributionLayout::read(&mut partial_reader)? {
+        ContributionLayout::Versioned(header) => header,
+        ContributionLayout::Legacy { .. } => {
+            return Err(anyhow!("The partial contribution has no versioned header"))
+        }
+    };
+    if header.curve != E::CURVE_ID
+        || header.k != checkpoint.k
+        || header.id != checkpoint.id
+        || PointEncoding::from_format(header.format) != checkpoint.encoding
+    {
+        return Err(anyhow!(
+            "The partial contribution header does not match the checkpoint"
+        ));
+    }
+    let write_format = checkpoint.encoding.format();
+    let write_length = E::G1Affine::byte_length(write_format) as u64;
+    if checkpoint.partial_length
+        != CONTRIBUTION_HEADER_LENGTH as u64 + checkpoint.index as u64 * write_length
+    {
+        return Err(anyhow!(
+            "The checkpoint length is not consistent with its number of points"
+        ));
+    }
+
+    let mut prev_reader = BufReader::new(ContributionReader::open(prev_filepath)?);
+    let (prev_format, prev_header_length) = match ContributionLayout::read(&mut prev_reader)? {
+        ContributionLayout::Versioned(header) => (
+            header.read_format(SerdeFormat::RawBytes),
+            CONTRIBUTION_HEADER_LENGTH as u64,
+        ),
+        ContributionLayout::Legacy { .. } => (SerdeFormat::RawBytes, 4),
+    };
+    let prev_length = E::G1Affine::byte_length(prev_format) as u64;
+
+    if checkpoint.index == 0 {
+        return Ok(());
+    }
+
+    let mut indices = vec![0, checkpoint.index - 1];
+    indices.extend((0..RESUME_SAMPLED_POINTS).map(|_| OsRng.gen_range(0..checkpoint.index)));
+
+    for i in indices {
+        prev_reader.seek(SeekFrom::Start(prev_header_length + i as u64 * prev_length))?;
+        let prev_point = <E::G1Affine as SerdeCurveAffine>::read(&mut prev_reader, prev_format)?;
+        partial_reader.seek(SeekFrom::Start(
+            CONTRIBUTION_HEADER_LENGTH as u64 + i as u64 * write_length,
+        ))?;
+        let point = <E::G1Affine as SerdeCurveAffine>::read(&mut partial_reader, write_format)?;
+
+        let s_pow = Locked::new(s.get().pow_vartime([i as u64, 0, 0, 0]));
+        let expected: E::G1Affine = (prev_point * *s_pow).into();
+        if point != expected {
+            return Err(anyhow!(
+                "The point {} of the partial contribution is not consistent with the checkpoint",
+                i
+            ));
+        }
+    }
+
+    info!(
+        "The partial contribution is consistent with the checkpoint: resuming from point {}",
+        checkpoint.index
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribution::KZGContribution;
+    use crate::header::ContributionHeader;
+    use crate::proof::ContributionProof;
+    use crate::utils::ScratchDir;
+    use group::prime::PrimeCurveAffine;
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G2Affine};
+
+    const K: u32 = 4;
+    const PASSPHRASE: &str = "correct horse battery staple";
+
+    fn powers(tau: Fr, n: usize) -> Vec<G1Affine> {
+        let mut power = Fr::ONE;
+        (0..n)
+            .map(|_| {
+                let point = (G1Affine::generator() * power).into();
+                power *= tau;
+                point
+            })
+            .collect()
+    }
+
+    fn checkpoint(index: usize, s: &Secret<Bn256>, z: &Secret<Bn256>) -> Checkpoint {
+        let mut checkpoint = Checkpoint::new::<Bn256>(
+            "ab".repeat(32),
+            2,
+            K,
+            PointEncoding::Uncompressed,
+            PASSPHRASE,
+            s,
+            z,
+        )
+        .unwrap();
+        checkpoint.index = index;
+        checkpoint.partial_length = CONTRIBUTION_HEADER_LENGTH as u64
+            + (index * G1Affine::byte_length(SerdeFormat::RawBytes)) as u64;
+        checkpoint
+    }
+
+    // Writes a previous contribution with tau = 2, and a partial contribution holding its first `written` points
+    // rescaled by s, followed by a truncated point as left by an interrupted write
+    fn write_contributions(dir: &Path, s: Fr, written: usize) -> (PathBuf, PathBuf) {
+        let n = 1 << K;
+        let tau = Fr::from(2);
+        let prev_filepath = dir.join("prev.csrs");
+        KZGContribution::<Bn256>::from_parts(
+            K,
+            powers(tau, n),
+            G2Affine::generator(),
+            (G2Affine::generator() * tau).into(),
+            ContributionProof::default(),
+            1,
+        )
+        .write(&prev_filepath)
+        .unwrap();
+
+        let partial_filepath = dir.join("partial.csrs.partial");
+        let mut partial = Vec::new();
+        ContributionHeader::new(Bn256::CURVE_ID, SerdeFormat::RawBytes, K, 2)
+            .write(&mut partial)
+            .unwrap();
+        for point in powers(tau * s, n).iter().take(written) {
+            point.write(&mut partial, SerdeFormat::RawBytes).unwrap();
+        }
+        partial.extend_from_slice(&[0xff; 17]);
+        fs::write(&partial_filepath, partial).unwrap();
+
+        (prev_filepath, partial_filepath)
+    }
+
+    #[test]
+    fn secrets_roundtrip() {
+        let (s, z) = (Secret::new(Fr::from(3)), Secret::new(Fr::from(5)));
+        let (decrypted_s, decrypted_z) =
+            checkpoint(0, &s, &z).secrets::<Bn256>(PASSPHRASE).unwrap();
+        assert_eq!(decrypted_s.get(), s.get());
+        assert_eq!(decrypted_z.get(), z.get());
+    }
+
+    #[test]
+    fn secrets_reject_wrong_passphrase() {
+        let (s, z) = (Secret::new(Fr::from(3)), Secret::new(Fr::from(5)));
+        assert!(checkpoint(0, &s, &z)
+            .secrets::<Bn256>("wrong passphrase")
+            .is_err());
+    }
+
+    #[test]
+    fn secrets_are_bound_to_the_contribution() {
+        let (s, z) = (Secret::new(Fr::from(3)), Secret::new(Fr::from(5)));
+        let mut checkpoint = checkpoint(0, &s, &z);
+        checkpoint.id += 1;
+        assert!(checkpoint.secrets::<Bn256>(PASSPHRASE).is_err());
+    }
+
+    #[test]
+    fn verify_partial_resumes_after_truncation() {
+        let dir = ScratchDir::new("checkpoint_test");
+        let (s, z) = (Secret::new(Fr::from(3)), Secret::new(Fr::from(5)));
+        // The checkpoint covers 6 points, but 9 points and a truncated one were written before the interruption
+        let (prev_filepath, partial_filepath) = write_contributions(&dir.0, *s.get(), 9);
+        let checkpoint = checkpoint(6, &s, &z);
+
+        verify_partial(&partial_filepath, &prev_filepath, &checkpoint, &s).unwrap();
+        assert_eq!(
+            fs::metadata(&partial_filepath).unwrap().len(),
+            checkpoint.partial_length
+        );
+    }
+
+    #[test]
+    fn verify_partial_rejects_short_partial() {
+        let dir = ScratchDir::new("checkpoint_test");
+        let (s, z) = (Secret::new(Fr::from(3)), Secret::new(Fr::from(5)));
+        let (prev_filepath, partial_filepath) = write_contributions(&dir.0, *s.get(), 4);
+        // The 4 written points and the truncated one do not cover the 6 checkpointed points
+        let checkpoint = checkpoint(6, &s, &z);
+
+        assert!(verify_partial(&partial_filepath, &prev_filepath, &checkpoint, &s).is_err());
+    }
+
+    #[test]
+    fn verify_partial_rejects_wrong_secret() {
+        let dir = ScratchDir::new("checkpoint_test");
+        let (s, z) = (Secret::new(Fr::from(3)), Secret::new(Fr::from(5)));
+        let (prev_filepath, partial_filepath) = write_contributions(&dir.0, Fr::from(7), 9);
+        let checkpoint = checkpoint(6, &s, &z);
+
+        assert!(verify_partial(&partial_filepath, &prev_filepath, &checkpoint, &s).is_err());
+    }
+}
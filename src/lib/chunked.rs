@@ -0,0 +1,846 @@
+use anyhow::{anyhow, Result};
+use core::fmt::Debug;
+use halo2_proofs::{halo2curves::pairing::Engine, SerdeFormat};
+use log::info;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Error, ErrorKind, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::contribution::{points_length, SerdeCurveAffine};
+use crate::header::{
+    ContributionHeader, ContributionLayout, CurveIdentifier, CONTRIBUTION_HEADER_LENGTH,
+};
+
+/// Filename of the index of a chunked contribution
+pub const CHUNKED_INDEX_FILENAME: &str = "index.json";
+/// Current version of the chunked contribution index
+pub const CHUNKED_INDEX_VERSION: u16 = 1;
+/// Smallest chunk size accepted, which bounds the number of chunk files of a contribution
+pub const MIN_CHUNK_SIZE: u64 = 1 << 20;
+
+// Returns the filename of the i-th chunk of a chunked contribution
+fn chunk_filename(i: usize) -> String {
+    format!("chunk_{:05}.bin", i)
+}
+
+/// A chunk file of a chunked contribution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkEntry {
+    pub filename: String,
+    pub length: u64,
+    /// SHA256 of the chunk file, hex encoded
+    pub sha256: String,
+}
+
+/// The index of a chunked contribution.
+///
+/// A chunked contribution is a directory storing the points of a contribution in fixed-size chunk files,
+/// while the index holds the bytes preceding and following the points. Concatenating the header, the chunks
+/// and the proof gives back the single-file contribution, so both layouts have the same SHA256
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChunkedIndex {
+    pub version: u16,
+    /// The contribution header, or k for legacy contributions, hex encoded
+    pub header: String,
+    /// The byte length of all chunks but the last one
+    pub chunk_size: u64,
+    /// The chunks storing the points in G1 and G2, in order
+    pub chunks: Vec<ChunkEntry>,
+    /// The contribution proof, followed by the id for legacy contributions, hex encoded
+    pub proof: String,
+}
+
+impl ChunkedIndex {
+    /// Reads the index of the chunked contribution stored in dirpath
+    pub fn read(dirpath: &Path) -> io::Result<Self> {
+        let index_filepath = dirpath.join(CHUNKED_INDEX_FILENAME);
+        let reader = BufReader::new(File::open(&index_filepath).map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!(
+                    "Cannot open the index of chunked contribution {:#?}: {}",
+                    dirpath.display(),
+                    e
+                ),
+            )
+        })?);
+        let index: ChunkedIndex = serde_json::from_reader(reader)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e.to_string()))?;
+
+        if index.version == 0 || index.version > CHUNKED_INDEX_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unsupported chunked contribution index version {} (latest supported is {})",
+                    index.version, CHUNKED_INDEX_VERSION
+                ),
+            ));
+        }
+
+        Ok(index)
+    }
+
+    // Writes the index, replacing a previous one only once it is completely written
+    fn write(&self, dirpath: &Path) -> io::Result<()> {
+        let index_filepath = dirpath.join(CHUNKED_INDEX_FILENAME);
+        let tmp_filepath = index_filepath.with_extension("json.tmp");
+        let mut writer = BufWriter::new(File::create(&tmp_filepath)?);
+        serde_json::to_writer_pretty(&mut writer, self)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+        fs::rename(&tmp_filepath, &index_filepath)
+    }
+}
+
+/// Returns true if path is the directory of a chunked contribution, i.e. it contains an index
+pub fn is_chunked_contribution(path: &Path) -> bool {
+    path.is_dir() && path.join(CHUNKED_INDEX_FILENAME).is_file()
+}
+
+/// Reads the logical content of a chunked contribution, i.e. the bytes of the equivalent single-file contribution.
+/// Chunks read sequentially from their start are checked against the hashes in the index once their end is reached,
+/// as hash_file does. A chunk accessed at any other offset, e.g. after a seek, is entirely checked before any of its
+/// bytes is returned. Each chunk is checked at most once per reader
+pub struct ChunkedReader {
+    dirpath: PathBuf,
+    header: Vec<u8>,
+    proof: Vec<u8>,
+    chunks: Vec<ChunkEntry>,
+    // The logical offsets at which the header, each chunk and the proof end
+    segment_ends: Vec<u64>,
+    position: u64,
+    // The chunk being read, the file position within it and, if read from its start, its running hash
+    current: Option<(usize, File, u64, Option<Sha256>)>,
+    // Whether each chunk was checked against its hash
+    verified: Vec<bool>,
+}
+
+impl ChunkedReader {
+    /// Opens a chunked contribution, checking that all chunks are present with the length recorded in the index
+    pub fn open(dirpath: &Path) -> io::Result<Self> {
+        let index = ChunkedIndex::read(dirpath)?;
+        let invalid = |e: hex::FromHexError| Error::new(ErrorKind::InvalidData, e.to_string());
+        let header = hex::decode(&index.header).map_err(invalid)?;
+        let proof = hex::decode(&index.proof).map_err(invalid)?;
+
+        let mut segment_ends = vec![header.len() as u64];
+        for (i, chunk) in index.chunks.iter().enumerate() {
+            // Chunks are named after their position, which also keeps them inside the directory
+            if chunk.filename != chunk_filename(i) {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Chunk {} of {:#?} should be named {:#?} but is named {:#?}",
+                        i,
+                        dirpath.display(),
+                        chunk_filename(i),
+                        chunk.filename
+                    ),
+                ));
+            }
+            let chunk_filepath = dirpath.join(&chunk.filename);
+            let length = fs::metadata(&chunk_filepath)
+                .map_err(|e| {
+                    Error::new(
+                        e.kind(),
+                        format!("Cannot find chunk {:#?}: {}", chunk_filepath.display(), e),
+                    )
+                })?
+                .len();
+            if length != chunk.length {
+                return Err(Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Chunk {:#?} has {} bytes but the index records {} bytes",
+                        chunk_filepath.display(),
+                        length,
+                        chunk.length
+                    ),
+                ));
+            }
+            segment_ends.push(segment_ends.last().unwrap() + chunk.length);
+        }
+        segment_ends.push(segment_ends.last().unwrap() + proof.len() as u64);
+
+        Ok(ChunkedReader {
+            dirpath: dirpath.to_path_buf(),
+            header,
+            proof,
+            verified: vec![false; index.chunks.len()],
+            chunks: index.chunks,
+            segment_ends,
+            position: 0,
+            current: None,
+        })
+    }
+
+    /// Returns the length of the logical content
+    pub fn length(&self) -> u64 {
+        *self.segment_ends.last().unwrap()
+    }
+
+    /// Returns the number of chunks
+    pub fn chunk_count(&self) -> usize {
+        self.chunks.len()
+    }
+
+    // Returns the error reported when the i-th chunk does not match its hash
+    fn chunk_mismatch(&self, i: usize) -> Error {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Chunk {:#?} does not match the SHA256 recorded in the index",
+                self.dirpath.join(&self.chunks[i].filename).display()
+            ),
+        )
+    }
+
+    // Checks the whole i-th chunk against its hash
+    fn verify_chunk(&mut self, i: usize) -> io::Result<()> {
+        let mut hasher = Sha256::new();
+        io::copy(
+            &mut File::open(self.dirpath.join(&self.chunks[i].filename))?,
+            &mut hasher,
+        )?;
+        if hex::encode(hasher.finalize()) != self.chunks[i].sha256 {
+            return Err(self.chunk_mismatch(i));
+        }
+        self.verified[i] = true;
+        Ok(())
+    }
+
+    // Reads from the i-th chunk, starting at offset bytes from its start
+    fn read_chunk(&mut self, i: usize, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        let is_current =
+            matches!(&self.current, Some((j, _, position, _)) if *j == i && *position == offset);
+        if !is_current {
+            // A chunk which is not read from its start is checked before being read
+            if offset != 0 && !self.verified[i] {
+                self.verify_chunk(i)?;
+            }
+            let mut file = File::open(self.dirpath.join(&self.chunks[i].filename))?;
+            file.seek(SeekFrom::Start(offset))?;
+            let hasher = (!self.verified[i]).then(Sha256::new);
+            self.current = Some((i, file, offset, hasher));
+        }
+        let (_, file, position, hasher) = self.current.as_mut().unwrap();
+
+        let remaining = (self.chunks[i].length - offset).min(buf.len() as u64) as usize;
+        let bytes_read = file.read(&mut buf[..remaining])?;
+        if bytes_read == 0 {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                format!("Chunk {:#?} is truncated", self.chunks[i].filename),
+            ));
+        }
+        *position += bytes_read as u64;
+
+        if let Some(hasher) = hasher {
+            hasher.update(&buf[..bytes_read]);
+            if *position == self.chunks[i].length {
+                if hex::encode(hasher.clone().finalize()) != self.chunks[i].sha256 {
+                    return Err(self.chunk_mismatch(i));
+                }
+                self.verified[i] = true;
+            }
+        }
+
+        Ok(bytes_read)
+    }
+}
+
+impl Read for ChunkedReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.length() {
+            return Ok(0);
+        }
+
+        let segment = self
+            .segment_ends
+            .partition_point(|&end| end <= self.position);
+        let segment_start = if segment == 0 {
+            0
+        } else {
+            self.segment_ends[segment - 1]
+        };
+        let offset = self.position - segment_start;
+
+        let bytes_read = if segment == 0 || segment == self.segment_ends.len() - 1 {
+            let bytes = if segment == 0 {
+                &self.header
+            } else {
+                &self.proof
+            };
+            let mut remaining = &bytes[offset as usize..];
+            remaining.read(buf)?
+        } else {
+            self.read_chunk(segment - 1, offset, buf)?
+        };
+
+        self.position += bytes_read as u64;
+        Ok(bytes_read)
+    }
+}
+
+impl Seek for ChunkedReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = match pos {
+            SeekFrom::Start(offset) => Some(offset),
+            SeekFrom::End(offset) => self.length().checked_add_signed(offset),
+            SeekFrom::Current(offset) => self.position.checked_add_signed(offset),
+        };
+        self.position = position.ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidInput,
+                "Invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
+
+/// Reads a contribution stored either in a single file or as a chunked contribution directory
+pub enum ContributionReader {
+    File(File),
+    Chunked(Box<ChunkedReader>),
+}
+
+impl ContributionReader {
+    /// Opens the contribution stored at path, detecting its layout
+    pub fn open(path: &Path) -> io::Result<Self> {
+        if path.is_dir() {
+            Ok(ContributionReader::Chunked(Box::new(ChunkedReader::open(
+                path,
+            )?)))
+        } else {
+            Ok(ContributionReader::File(File::open(path)?))
+        }
+    }
+
+    /// Returns the byte length of the contribution, i.e. of its single-file layout
+    pub fn length(&self) -> io::Result<u64> {
+        match self {
+            ContributionReader::File(file) => Ok(file.metadata()?.len()),
+            ContributionReader::Chunked(reader) => Ok(reader.length()),
+        }
+    }
+}
+
+impl Read for ContributionReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            ContributionReader::File(file) => file.read(buf),
+            ContributionReader::Chunked(reader) => reader.read(buf),
+        }
+    }
+}
+
+impl Seek for ContributionReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        match self {
+            ContributionReader::File(file) => file.seek(pos),
+            ContributionReader::Chunked(reader) => reader.seek(pos),
+        }
+    }
+}
+
+/// Writes a contribution as a chunked contribution directory: the bytes of the single-file contribution are
+/// split into the header, fixed-size chunks of points and the proof. The index is written by `finish`
+pub struct ChunkedWriter {
+    dirpath: PathBuf,
+    chunk_size: u64,
+    header_length: u64,
+    points_length: u64,
+    position: u64,
+    header: Vec<u8>,
+    proof: Vec<u8>,
+    chunks: Vec<ChunkEntry>,
+    // The chunk being written and its running hash
+    current: Option<(BufWriter<File>, Sha256)>,
+}
+
+impl ChunkedWriter {
+    /// Creates the directory dirpath, which must not exist, for a contribution whose points are preceded by
+    /// header_length bytes and span points_length bytes
+    pub fn create(
+        dirpath: &Path,
+        header_length: u64,
+        points_length: u64,
+        chunk_size: u64,
+    ) -> io::Result<Self> {
+        if chunk_size < MIN_CHUNK_SIZE {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "The chunk size is {} bytes but should be at least {} bytes",
+                    chunk_size, MIN_CHUNK_SIZE
+                ),
+            ));
+        }
+        fs::create_dir(dirpath)?;
+
+        Ok(ChunkedWriter {
+            dirpath: dirpath.to_path_buf(),
+            chunk_size,
+            header_length,
+            points_length,
+            position: 0,
+            header: Vec::with_capacity(header_length as usize),
+            proof: Vec::new(),
+            chunks: Vec::new(),
+            current: None,
+        })
+    }
+
+    // Flushes the chunk being written and records it in the index
+    fn close_chunk(&mut self) -> io::Result<()> {
+        if let Some((mut writer, hasher)) = self.current.take() {
+            writer.flush()?;
+            writer.get_ref().sync_data()?;
+            let i = self.chunks.len();
+            self.chunks.push(ChunkEntry {
+                filename: chunk_filename(i),
+                length: writer.get_ref().metadata()?.len(),
+                sha256: hex::encode(hasher.finalize()),
+            });
+        }
+        Ok(())
+    }
+
+    /// Completes the contribution, writing its index
+    pub fn finish(mut self) -> io::Result<()> {
+        self.close_chunk()?;
+        if self.position < self.header_length + self.points_length {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "The chunked contribution is missing some points",
+            ));
+        }
+
+        let index = ChunkedIndex {
+            version: CHUNKED_INDEX_VERSION,
+            header: hex::encode(&self.header),
+            chunk_size: self.chunk_size,
+            chunks: self.chunks,
+            proof: hex::encode(&self.proof),
+        };
+        index.write(&self.dirpath)?;
+        info!(
+            "Chunked contribution written to {:#?} in {} chunks",
+            self.dirpath.display(),
+            index.chunks.len()
+        );
+        Ok(())
+    }
+
+    /// Flushes the chunk being written and makes sure it is on disk
+    pub fn sync_data(&mut self) -> io::Result<()> {
+        if let Some((writer, _)) = self.current.as_mut() {
+            writer.flush()?;
+            writer.get_ref().sync_data()?;
+        }
+        Ok(())
+    }
+}
+
+impl Write for ChunkedWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        let points_end = self.header_length + self.points_length;
+
+        let written = if self.position < self.header_length {
+            let length = ((self.header_length - self.position) as usize).min(buf.len());
+            self.header.extend_from_slice(&buf[..length]);
+            length
+        } else if self.position < points_end {
+            let chunk_offset = (self.position - self.header_length) % self.chunk_size;
+            if chunk_offset == 0 {
+                self.close_chunk()?;
+                let filepath = self.dirpath.join(chunk_filename(self.chunks.len()));
+                self.current = Some((BufWriter::new(File::create(filepath)?), Sha256::new()));
+            }
+            let length = (self.chunk_size - chunk_offset)
+                .min(points_end - self.position)
+                .min(buf.len() as u64) as usize;
+            let (writer, hasher) = self.current.as_mut().unwrap();
+            writer.write_all(&buf[..length])?;
+            hasher.update(&buf[..length]);
+            length
+        } else {
+            self.proof.extend_from_slice(buf);
+            buf.len()
+        };
+
+        self.position += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self.current.as_mut() {
+            Some((writer, _)) => writer.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes a contribution either to a single file or, if a chunk size is set, as a chunked contribution directory
+pub enum ContributionWriter {
+    File(BufWriter<File>),
+    Chunked(Box<ChunkedWriter>),
+}
+
+impl ContributionWriter {
+    /// Creates the contribution with the given header at path
+    pub fn create<E: Engine + Debug>(
+        path: &Path,
+        header: &ContributionHeader,
+        chunk_size: Option<u64>,
+    ) -> io::Result<Self>
+    where
+        E::G1Affine: SerdeCurveAffine,
+        E::G2Affine: SerdeCurveAffine,
+    {
+        match chunk_size {
+            Some(chunk_size) => Ok(ContributionWriter::Chunked(Box::new(
+                ChunkedWriter::create(
+                    path,
+                    CONTRIBUTION_HEADER_LENGTH as u64,
+                    points_length::<E>(header.k, header.format),
+                    chunk_size,
+                )?,
+            ))),
+            None => Ok(ContributionWriter::File(BufWriter::new(File::create(
+                path,
+            )?))),
+        }
+    }
+
+    /// Flushes the contribution and makes sure the bytes written so far are on disk
+    pub fn sync_data(&mut self) -> io::Result<()> {
+        match self {
+            ContributionWriter::File(writer) => {
+                writer.flush()?;
+                writer.get_ref().sync_data()
+            }
+            ContributionWriter::Chunked(writer) => writer.sync_data(),
+        }
+    }
+
+    /// Completes the contribution
+    pub fn finish(self) -> io::Result<()> {
+        match self {
+            ContributionWriter::File(mut writer) => writer.flush(),
+            ContributionWriter::Chunked(writer) => writer.finish(),
+        }
+    }
+}
+
+impl Write for ContributionWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            ContributionWriter::File(writer) => writer.write(buf),
+            ContributionWriter::Chunked(writer) => writer.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            ContributionWriter::File(writer) => writer.flush(),
+            ContributionWriter::Chunked(writer) => writer.flush(),
+        }
+    }
+}
+
+/// Rewrites a contribution in the other layout: if a chunk size is given, a single-file contribution is split into chunks,
+/// otherwise a chunked contribution is joined into a single file. The contribution is written next to the original one,
+/// checked to have the same SHA256 and then replaces it. Returns the SHA256 of the contribution, hex encoded
+pub fn convert_contribution<E: Engine + Debug + CurveIdentifier>(
+    path: &Path,
+    chunk_size: Option<u64>,
+) -> Result<String>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+{
+    let mut reader = BufReader::new(ContributionReader::open(path)?);
+    let is_chunked = matches!(reader.get_ref(), ContributionReader::Chunked(_));
+    let length = reader.get_ref().length()?;
+
+    let (header_length, points_length) = match ContributionLayout::read(&mut reader)? {
+        ContributionLayout::Versioned(header) => {
+            if header.curve != E::CURVE_ID {
+                return Err(anyhow!(
+                    "The contribution {:#?} is defined over {:?} but {:?} was expected",
+                    path.display(),
+                    header.curve,
+                    E::CURVE_ID
+                ));
+            }
+            (
+                CONTRIBUTION_HEADER_LENGTH as u64,
+                points_length::<E>(header.k, header.format),
+            )
+        }
+        ContributionLayout::Legacy { k } => (4, points_length::<E>(k, SerdeFormat::RawBytes)),
+    };
+    if length < header_length + points_length {
+        return Err(anyhow!(
+            "The contribution {:#?} is shorter than declared in its header",
+            path.display()
+        ));
+    }
+    reader.seek(SeekFrom::Start(0))?;
+
+    let mut converted_path = path.to_path_buf();
+    converted_path.set_extension(format!(
+        "{}.converted",
+        path.extension()
+            .and_then(|e| e.to_str())
+            .unwrap_or_default()
+    ));
+
+    let mut writer = match (is_chunked, chunk_size) {
+        (true, None) => ContributionWriter::File(BufWriter::new(File::create(&converted_path)?)),
+        (false, Some(chunk_size)) => ContributionWriter::Chunked(Box::new(ChunkedWriter::create(
+            &converted_path,
+            header_length,
+            points_length,
+            chunk_size,
+        )?)),
+        (true, Some(_)) => {
+            return Err(anyhow!(
+                "The contribution {:#?} is already chunked",
+                path.display()
+            ))
+        }
+        (false, None) => {
+            return Err(anyhow!(
+                "The contribution {:#?} is already stored in a single file",
+                path.display()
+            ))
+        }
+    };
+
+    // We hash the contribution while converting it, and hash back the converted contribution
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 16 * 1024 * 1024];
+    loop {
+        let bytes_read = reader.read(&mut buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        writer.write_all(&buffer[..bytes_read])?;
+    }
+    writer.sync_data()?;
+    writer.finish()?;
+    drop(reader);
+
+    let sha256 = hex::encode(hasher.finalize());
+    let mut converted_reader = ContributionReader::open(&converted_path)?;
+    let mut converted_hasher = Sha256::new();
+    io::copy(&mut converted_reader, &mut converted_hasher)?;
+    if hex::encode(converted_hasher.finalize()) != sha256 {
+        return Err(anyhow!(
+            "The converted contribution {:#?} does not match the original one",
+            converted_path.display()
+        ));
+    }
+
+    if is_chunked {
+        fs::remove_dir_all(path)?;
+    } else {
+        fs::remove_file(path)?;
+    }
+    fs::rename(&converted_path, path)?;
+    info!(
+        "Contribution {:#?} converted to the {} layout",
+        path.display(),
+        if is_chunked { "single-file" } else { "chunked" }
+    );
+
+    Ok(sha256)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribution::KZGContribution;
+    use crate::proof::ContributionProof;
+    use crate::utils::{hash_file, ScratchDir};
+    use group::{prime::PrimeCurveAffine, Curve, Group};
+    use halo2_proofs::halo2curves::bn256::{Bn256, G1Affine, G2Affine, G1};
+    use rand::{rngs::OsRng, RngCore};
+
+    const HEADER_LENGTH: u64 = CONTRIBUTION_HEADER_LENGTH as u64;
+    const POINTS_LENGTH: u64 = 2 * MIN_CHUNK_SIZE + 12345;
+    const PROOF_LENGTH: usize = 100;
+
+    // Writes random bytes as a chunked contribution with 3 chunks, returning the bytes
+    fn write_chunked(dirpath: &Path) -> Vec<u8> {
+        let mut bytes = vec![0u8; (HEADER_LENGTH + POINTS_LENGTH) as usize + PROOF_LENGTH];
+        OsRng.fill_bytes(&mut bytes);
+        let mut writer =
+            ChunkedWriter::create(dirpath, HEADER_LENGTH, POINTS_LENGTH, MIN_CHUNK_SIZE).unwrap();
+        // Writes of uneven sizes cross the header, chunk and proof boundaries
+        for part in bytes.chunks(300_007) {
+            writer.write_all(part).unwrap();
+        }
+        writer.finish().unwrap();
+        bytes
+    }
+
+    fn read_all(reader: &mut impl Read) -> io::Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    #[test]
+    fn chunked_contribution_matches_single_file() {
+        let dir = ScratchDir::new("chunked_test");
+        // The 2^15 powers are 1 G, 2 G, 3 G.. so that no two chunks are equal
+        let k = 15;
+        let g_projective: Vec<G1> = (0..1 << k)
+            .scan(G1::identity(), |power, _| {
+                *power += G1::generator();
+                Some(*power)
+            })
+            .collect();
+        let mut g = vec![G1Affine::identity(); 1 << k];
+        G1::batch_normalize(&g_projective, &mut g);
+        let contribution = KZGContribution::<Bn256>::from_parts(
+            k,
+            g,
+            G2Affine::generator(),
+            G2Affine::generator(),
+            ContributionProof::default(),
+            1,
+        );
+
+        let single_filepath = dir.0.join("single.csrs");
+        let chunked_filepath = dir.0.join("chunked.csrs");
+        contribution.write(&single_filepath).unwrap();
+        contribution.write(&chunked_filepath).unwrap();
+        let sha256 =
+            convert_contribution::<Bn256>(&chunked_filepath, Some(MIN_CHUNK_SIZE)).unwrap();
+        assert!(is_chunked_contribution(&chunked_filepath));
+        assert!(
+            ChunkedReader::open(&chunked_filepath)
+                .unwrap()
+                .chunk_count()
+                > 1
+        );
+
+        let single_bytes = fs::read(&single_filepath).unwrap();
+        assert_eq!(
+            read_all(&mut ContributionReader::open(&chunked_filepath).unwrap()).unwrap(),
+            single_bytes
+        );
+        assert_eq!(hash_file(&single_filepath).unwrap(), sha256);
+        assert_eq!(hash_file(&chunked_filepath).unwrap(), sha256);
+        assert_eq!(
+            KZGContribution::<Bn256>::from(&chunked_filepath)
+                .unwrap()
+                .g(),
+            contribution.g()
+        );
+
+        // Joining the chunks gives back the single file
+        convert_contribution::<Bn256>(&chunked_filepath, None).unwrap();
+        assert_eq!(fs::read(&chunked_filepath).unwrap(), single_bytes);
+    }
+
+    #[test]
+    fn seeks_across_chunk_boundaries() {
+        let dir = ScratchDir::new("chunked_test");
+        let dirpath = dir.0.join("contribution.csrs");
+        let bytes = write_chunked(&dirpath);
+        let mut reader = ChunkedReader::open(&dirpath).unwrap();
+        assert_eq!(reader.chunk_count(), 3);
+        assert_eq!(reader.length(), bytes.len() as u64);
+
+        let boundaries = [
+            HEADER_LENGTH,
+            HEADER_LENGTH + MIN_CHUNK_SIZE,
+            HEADER_LENGTH + 2 * MIN_CHUNK_SIZE,
+            HEADER_LENGTH + POINTS_LENGTH,
+        ];
+        for boundary in boundaries.iter().rev() {
+            let start = (boundary - 7) as usize;
+            let mut buffer = [0u8; 16];
+            reader.seek(SeekFrom::Start(start as u64)).unwrap();
+            reader.read_exact(&mut buffer).unwrap();
+            assert_eq!(buffer, bytes[start..start + 16]);
+        }
+
+        reader.seek(SeekFrom::End(-20)).unwrap();
+        assert_eq!(read_all(&mut reader).unwrap(), bytes[bytes.len() - 20..]);
+        reader
+            .seek(SeekFrom::Start(HEADER_LENGTH + MIN_CHUNK_SIZE + 5))
+            .unwrap();
+        reader.seek(SeekFrom::Current(-10)).unwrap();
+        let start = (HEADER_LENGTH + MIN_CHUNK_SIZE - 5) as usize;
+        assert_eq!(read_all(&mut reader).unwrap(), bytes[start..]);
+        assert!(reader
+            .seek(SeekFrom::Current(-(bytes.len() as i64) - 1))
+            .is_err());
+    }
+
+    #[test]
+    fn truncated_chunk_is_rejected() {
+        let dir = ScratchDir::new("chunked_test");
+        let dirpath = dir.0.join("contribution.csrs");
+        write_chunked(&dirpath);
+        fs::OpenOptions::new()
+            .write(true)
+            .open(dirpath.join(chunk_filename(1)))
+            .unwrap()
+            .set_len(MIN_CHUNK_SIZE - 1)
+            .unwrap();
+
+        assert_eq!(
+            ChunkedReader::open(&dirpath).err().unwrap().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+
+    #[test]
+    fn tampered_chunk_is_rejected() {
+        let dir = ScratchDir::new("chunked_test");
+        let dirpath = dir.0.join("contribution.csrs");
+        let bytes = write_chunked(&dirpath);
+        let chunk_filepath = dirpath.join(chunk_filename(1));
+        let mut chunk = fs::read(&chunk_filepath).unwrap();
+        chunk[1000] ^= 1;
+        fs::write(&chunk_filepath, chunk).unwrap();
+
+        // Sequential reads fail once the end of the tampered chunk is reached
+        let error = read_all(&mut ChunkedReader::open(&dirpath).unwrap()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::InvalidData);
+        assert!(hash_file(&dirpath).is_err());
+
+        // Reads after a seek fail before returning any byte of the tampered chunk,
+        // while the other chunks can still be read
+        let mut reader = ChunkedReader::open(&dirpath).unwrap();
+        let mut buffer = [0u8; 16];
+        reader
+            .seek(SeekFrom::Start(HEADER_LENGTH + MIN_CHUNK_SIZE + 1000))
+            .unwrap();
+        assert_eq!(
+            reader.read(&mut buffer).unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+        let start = (HEADER_LENGTH + 2 * MIN_CHUNK_SIZE + 1000) as usize;
+        reader.seek(SeekFrom::Start(start as u64)).unwrap();
+        reader.read_exact(&mut buffer).unwrap();
+        assert_eq!(buffer, bytes[start..start + 16]);
+    }
+}
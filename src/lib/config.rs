@@ -0,0 +1,196 @@
+use anyhow::{anyhow, Result};
+use core::fmt::Debug;
+use halo2_proofs::halo2curves::pairing::Engine;
+use halo2_proofs::SerdeFormat;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::chunked::MIN_CHUNK_SIZE;
+use crate::contribution::{
+    is_supported_k, ContributionView, DEFAULT_CONTRIBUTION_K, MAX_CONTRIBUTION_K,
+    MIN_CONTRIBUTION_K,
+};
+
+/// Default filename of the ceremony config file
+pub const DEFAULT_CONFIG_FILENAME: &str = "ceremony.toml";
+
+/// Encodings of the points written to contribution files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointEncoding {
+    /// Compressed points
+    Compressed,
+    /// Uncompressed points with coordinates in Montgomery form
+    Uncompressed,
+}
+
+impl PointEncoding {
+    /// Names of the point encodings, as accepted by `from_str`
+    pub const NAMES: [&'static str; 2] = ["compressed", "uncompressed"];
+
+    pub fn from_format(format: SerdeFormat) -> Self {
+        match format {
+            SerdeFormat::Processed => PointEncoding::Compressed,
+            SerdeFormat::RawBytes | SerdeFormat::RawBytesUnchecked => PointEncoding::Uncompressed,
+        }
+    }
+
+    pub fn format(self) -> SerdeFormat {
+        match self {
+            PointEncoding::Compressed => SerdeFormat::Processed,
+            PointEncoding::Uncompressed => SerdeFormat::RawBytes,
+        }
+    }
+}
+
+impl fmt::Display for PointEncoding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PointEncoding::Compressed => write!(f, "compressed"),
+            PointEncoding::Uncompressed => write!(f, "uncompressed"),
+        }
+    }
+}
+
+impl FromStr for PointEncoding {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "compressed" => Ok(PointEncoding::Compressed),
+            "uncompressed" => Ok(PointEncoding::Uncompressed),
+            _ => Err(anyhow!(
+                "Unknown point encoding {:?}, the available encodings are: {}",
+                s,
+                PointEncoding::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Parameters shared by all participants of a ceremony
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CeremonyConfig {
+    k: u32,
+    encoding: PointEncoding,
+    chunk_size: Option<u64>,
+}
+
+impl Default for CeremonyConfig {
+    fn default() -> Self {
+        CeremonyConfig {
+            k: DEFAULT_CONTRIBUTION_K,
+            encoding: PointEncoding::Uncompressed,
+            chunk_size: None,
+        }
+    }
+}
+
+impl CeremonyConfig {
+    pub fn new(k: u32) -> Result<Self> {
+        if !is_supported_k(k) {
+            return Err(anyhow!(
+                "Ceremony k is {} but should be between {} and {}",
+                k,
+                MIN_CONTRIBUTION_K,
+                MAX_CONTRIBUTION_K
+            ));
+        }
+        Ok(CeremonyConfig {
+            k,
+            ..CeremonyConfig::default()
+        })
+    }
+
+    /// Sets the encoding of the points in new contributions
+    pub fn with_encoding(mut self, encoding: PointEncoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    /// Sets the size of the chunk files of new contributions. If unset, new contributions are written to a single file
+    pub fn with_chunk_size(mut self, chunk_size: Option<u64>) -> Result<Self> {
+        if let Some(chunk_size) = chunk_size.filter(|&size| size < MIN_CHUNK_SIZE) {
+            return Err(anyhow!(
+                "The chunk size is {} bytes but should be at least {} bytes",
+                chunk_size,
+                MIN_CHUNK_SIZE
+            ));
+        }
+        self.chunk_size = chunk_size;
+        Ok(self)
+    }
+
+    /// Returns the ceremony's k
+    pub fn k(&self) -> u32 {
+        self.k
+    }
+
+    /// Returns the encoding of the points in new contributions
+    pub fn encoding(&self) -> PointEncoding {
+        self.encoding
+    }
+
+    /// Returns the format used to write new contributions
+    pub fn format(&self) -> SerdeFormat {
+        self.encoding.format()
+    }
+
+    /// Returns the size of the chunk files of new contributions, if they are chunked
+    pub fn chunk_size(&self) -> Option<u64> {
+        self.chunk_size
+    }
+
+    /// Returns n = 1 << k
+    pub fn n(&self) -> usize {
+        1 << self.k
+    }
+
+    /// Ensures that a contribution has the size set for the ceremony
+    pub fn ensure_matches<E: Engine + Debug, V: ContributionView<E>>(
+        &self,
+        contribution: &V,
+    ) -> Result<()> {
+        if contribution.k() != self.k || contribution.n() != self.n() {
+            return Err(anyhow!(
+                "Contribution with id {} has k {} but the ceremony k is {}",
+                contribution.id(),
+                contribution.k(),
+                self.k
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// A ceremony config file, in TOML. All fields are optional and are overridden by command line options
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CeremonyFile {
+    /// The directory containing the contributions
+    pub contributions: Option<PathBuf>,
+    pub k: Option<u32>,
+    pub format: Option<PointEncoding>,
+    /// The size in bytes of the chunk files of new contributions
+    pub chunk_size: Option<u64>,
+}
+
+impl CeremonyFile {
+    /// Reads a ceremony config file. A relative contributions directory is resolved against the config file directory
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Cannot read config file {:#?}: {}", path.display(), e))?;
+        let mut file: CeremonyFile = toml::from_str(&content)
+            .map_err(|e| anyhow!("Config file {:#?} is malformed: {}", path.display(), e))?;
+
+        if let (Some(contributions), Some(base)) = (&file.contributions, path.parent()) {
+            if contributions.is_relative() {
+                file.contributions = Some(base.join(contributions));
+            }
+        }
+
+        Ok(file)
+    }
+}
@@ -1,21 +1,46 @@
-use std::path::{Path, PathBuf};
+use std::{
+    fs::{self, OpenOptions},
+    io::{BufReader, BufWriter, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
 
 use anyhow::{anyhow, Result};
-use group::{ff::Field, prime::PrimeCurveAffine};
+use core::fmt::Debug;
+use group::{
+    ff::{Field, FromUniformBytes},
+    prime::PrimeCurveAffine,
+};
 use halo2_proofs::{
     arithmetic::parallelize,
-    halo2curves::bn256::{Bn256, Fr, G1Affine, G2Affine},
+    halo2curves::{pairing::Engine, serde::SerdeObject},
+    SerdeFormat,
 };
 use log::info;
 
 use crate::{
-    check::check_contribution,
-    contribution::{KZGContribution, CONTRIBUTION_EXTENSION, CONTRIBUTION_K},
-    proof::create_contribution_proof,
+    attestation::{Attestation, AttestationConfig},
+    beacon::{beacon_secrets, BeaconConfig},
+    check::{check_contribution, SrsChecker},
+    checkpoint::{checkpoint_filepath, verify_partial, Checkpoint, CheckpointConfig},
+    chunked::{ContributionReader, ContributionWriter},
+    config::{CeremonyConfig, PointEncoding},
+    contribution::{
+        g_chunks, read_points, ContributionView, KZGContribution, SerdeCurveAffine,
+        CONTRIBUTION_EXTENSION, G1_CHUNK_LENGTH,
+    },
+    header::{ContributionHeader, ContributionLayout, CurveIdentifier, CONTRIBUTION_HEADER_LENGTH},
+    locked::Locked,
+    mapped::LoadedContribution,
+    progress::Progress,
+    proof::{create_contribution_proof, ProofContext},
     secrets::{gen_secrets, Secret, SecretsGenConfig},
-    utils::get_last_contribution,
+    shares::combine_shares,
+    utils::{decode_sha256, get_last_contribution_path, hash_file, read_id, HashingReader},
 };
 
+// Contributions are computed in a file with this suffix and renamed once completed
+const PARTIAL_CONTRIBUTION_EXTENSION: &str = "partial";
+
 // This utility is used to generate a default filename for storing new contributions
 pub fn default_contribution_filepath(
     contributions_path: &Path,
@@ -45,66 +70,460 @@ pub fn default_contribution_filepath(
     }
 }
 
-pub fn contribute(contributions_path_str: &str, config: SecretsGenConfig) -> Result<()> {
-    let contributions_path = PathBuf::from(contributions_path_str);
-
-    let last_contribution = get_last_contribution(&contributions_path)?;
-
-    let is_valid = check_contribution(&last_contribution)?;
-    if !is_valid {
-        return Err(anyhow!(
-            "The retrieved last contribution with id {:#?} is not valid",
-            last_contribution.id()
-        ));
-    }
+pub fn contribute<E: Engine + Debug + CurveIdentifier>(
+    contributions_path_str: &str,
+    ceremony_config: &CeremonyConfig,
+    config: SecretsGenConfig,
+    metadata: Vec<u8>,
+    attestation_config: Option<AttestationConfig>,
+) -> Result<PathBuf>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
+    let (last_contribution, last_sha256) =
+        load_last_contribution::<E>(contributions_path_str, ceremony_config)?;
 
     // We generate 2 secrets (s for rescaling the srs, z for the contribution proof)
     // according to the config
-    let (s, z) = gen_secrets(config)?;
+    let (s, z) = gen_secrets::<E>(config)?;
 
-    let contribution = rescale(last_contribution, &s, &z);
+    let contribution = rescale(&last_contribution, last_sha256, &s, &z, metadata);
 
     // We explicitly drop the secrets
     drop(s);
     drop(z);
 
     // We store the contribution in contributions_path with a default filename
-    contribution.write_default(contributions_path_str)?;
+    let filepath = contribution.write_default_custom(
+        contributions_path_str,
+        ceremony_config.format(),
+        ceremony_config.chunk_size(),
+    )?;
+
+    if let Some(attestation_config) = attestation_config {
+        attest_contribution(&filepath, &attestation_config)?;
+    }
+
+    Ok(filepath)
+}
+
+/// Computes the final contribution from a public random beacon. The rescaling factor s is derived from the beacon,
+/// while the blinding factor z is set to 0 so that s can be recomputed from the contribution proof by `check`
+pub fn contribute_beacon<E: Engine + Debug + CurveIdentifier>(
+    contributions_path_str: &str,
+    ceremony_config: &CeremonyConfig,
+    beacon: &BeaconConfig,
+    attestation_config: Option<AttestationConfig>,
+) -> Result<PathBuf>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
+    let (last_contribution, last_sha256) =
+        load_last_contribution::<E>(contributions_path_str, ceremony_config)?;
+
+    // The beacon and the number of iterations are bound to the contribution proof
+    let metadata = beacon.metadata()?;
+    let (s, z) = beacon_secrets::<E>(beacon)?;
+
+    let contribution = rescale(&last_contribution, last_sha256, &s, &z, metadata);
+
+    drop(s);
+    drop(z);
+
+    let filepath = contribution.write_default_custom(
+        contributions_path_str,
+        ceremony_config.format(),
+        ceremony_config.chunk_size(),
+    )?;
 
-    Ok(())
+    if let Some(attestation_config) = attestation_config {
+        attest_contribution(&filepath, &attestation_config)?;
+    }
+
+    Ok(filepath)
 }
 
-pub fn rescale(
-    prev_contribution: KZGContribution<Bn256>,
-    s: &Secret<Bn256>,
-    z: &Secret<Bn256>,
-) -> KZGContribution<Bn256> {
+/// Computes a contribution from secret shares generated offline with `write_share`.
+/// The last contribution is rescaled by the product of the shares' rescaling factors, so only the product is used to compute it
+pub fn contribute_shares<E: Engine + Debug + CurveIdentifier>(
+    contributions_path_str: &str,
+    ceremony_config: &CeremonyConfig,
+    share_paths: &[PathBuf],
+    metadata: Vec<u8>,
+    attestation_config: Option<AttestationConfig>,
+) -> Result<PathBuf>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
+    let (last_contribution, last_sha256) =
+        load_last_contribution::<E>(contributions_path_str, ceremony_config)?;
+
+    let (s, z) = combine_shares::<E>(share_paths)?;
+
+    let contribution = rescale(&last_contribution, last_sha256, &s, &z, metadata);
+
+    // We explicitly drop the combined secrets
+    drop(s);
+    drop(z);
+
+    let filepath = contribution.write_default_custom(
+        contributions_path_str,
+        ceremony_config.format(),
+        ceremony_config.chunk_size(),
+    )?;
+
+    if let Some(attestation_config) = attestation_config {
+        attest_contribution(&filepath, &attestation_config)?;
+    }
+
+    Ok(filepath)
+}
+
+// Loads and checks the last contribution found in contributions_path, returning it along with the SHA256 of its file
+fn load_last_contribution<E: Engine + Debug + CurveIdentifier>(
+    contributions_path_str: &str,
+    ceremony_config: &CeremonyConfig,
+) -> Result<(LoadedContribution<E>, [u8; 32])>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
+    let contributions_path = PathBuf::from(contributions_path_str);
+
+    // The contribution proof is bound to the SHA256 of the last contribution file
+    let last_contribution_path = get_last_contribution_path(&contributions_path)?;
+    let last_sha256 = decode_sha256(&hash_file(&last_contribution_path)?)?;
+    let last_contribution = LoadedContribution::<E>::open(&last_contribution_path)?;
+    ceremony_config.ensure_matches(&last_contribution)?;
+
+    let is_valid = check_contribution(&last_contribution, ceremony_config)?;
+    if !is_valid {
+        return Err(anyhow!(
+            "The retrieved last contribution with id {:#?} is not valid",
+            last_contribution.id()
+        ));
+    }
+
+    Ok((last_contribution, last_sha256))
+}
+
+// Signs an attestation for a contribution file and stores it next to the contribution
+fn attest_contribution(filepath: &PathBuf, attestation_config: &AttestationConfig) -> Result<()> {
+    let sha256 = hash_file(filepath)?;
+    Attestation::new(attestation_config, &sha256)?.write(filepath)
+}
+
+pub fn rescale<E: Engine + Debug, V: ContributionView<E>>(
+    prev_contribution: &V,
+    prev_sha256: [u8; 32],
+    s: &Secret<E>,
+    z: &Secret<E>,
+    metadata: Vec<u8>,
+) -> KZGContribution<E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64>,
+{
     info!("Rescaling powers of tau..");
 
-    // Compared to setup + batch_normalize, this approach seems to use less memory but has comparable performances
-    let mut g = vec![G1Affine::identity(); 1 << CONTRIBUTION_K];
-    parallelize(&mut g, |chunk_g, start| {
-        let mut _s_pow = s.get().pow_vartime([start as u64, 0, 0, 0]);
-        for (idx, g_el) in chunk_g.iter_mut().enumerate() {
-            *g_el = (prev_contribution.g()[start + idx] * _s_pow).into();
-            _s_pow *= s.get();
-        }
-        // We zero the _s_pow value before being dropped
-        _s_pow = Fr::zero();
-    });
+    // Compared to setup + batch_normalize, this approach seems to use less memory but has comparable performances.
+    // The previous points are read in chunks, so that those of a memory-mapped contribution are never all decoded at once
+    let mut g = vec![<E::G1Affine as PrimeCurveAffine>::identity(); prev_contribution.n()];
+    let progress = Progress::start("Rescaling powers of tau", g.len());
+    for ((offset, prev_g), g) in
+        g_chunks(prev_contribution, G1_CHUNK_LENGTH).zip(g.chunks_mut(G1_CHUNK_LENGTH))
+    {
+        g.copy_from_slice(&prev_g);
+        rescale_points(g, offset, s);
+        progress.advance(g.len());
+    }
+    progress.finish();
 
-    let s_g2: G2Affine = (prev_contribution.s_g2() * s.get()).into();
+    let s_g2: E::G2Affine = (*prev_contribution.s_g2() * s.get()).into();
 
     // We compute a contribution proof
     info!("Computing contribution proof..");
-    let contribution_proof = create_contribution_proof(prev_contribution.s_g(), s, z);
+    let context = ProofContext {
+        prev_sha256,
+        id: prev_contribution.id() + 1,
+    };
+    let contribution_proof =
+        create_contribution_proof(prev_contribution.s_g(), s, z, &context, metadata);
 
     KZGContribution::from_parts(
-        CONTRIBUTION_K,
+        prev_contribution.k(),
         g,
-        G2Affine::generator(),
+        <E::G2Affine as PrimeCurveAffine>::generator(),
         s_g2,
         contribution_proof,
         prev_contribution.id() + 1,
     )
 }
+
+// Rescales in place a slice of consecutive powers of tau, where points[0] = tau^offset * P
+fn rescale_points<E: Engine>(points: &mut [E::G1Affine], offset: usize, s: &Secret<E>) {
+    parallelize(points, |chunk_g, start| {
+        // The power accumulator is stored in locked memory and zeroed when dropped
+        let mut s_pow = Locked::new(s.get().pow_vartime([(offset + start) as u64, 0, 0, 0]));
+        for g_el in chunk_g.iter_mut() {
+            *g_el = (*g_el * *s_pow).into();
+            *s_pow *= s.get();
+        }
+    });
+}
+
+/// Computes a new contribution like `contribute`, but without loading the last contribution in memory.
+/// Points are read, rescaled and written in chunks whose size is bounded by `chunk_budget` bytes.
+/// The SRS of the last contribution is checked chunk by chunk while it is read, and no contribution is written if it is invalid.
+/// If a checkpoint config is given, a checkpoint is written after each chunk, so that the computation can be resumed
+pub fn contribute_streaming<E: Engine + Debug + CurveIdentifier>(
+    contributions_path_str: &str,
+    ceremony_config: &CeremonyConfig,
+    config: SecretsGenConfig,
+    chunk_budget: usize,
+    metadata: Vec<u8>,
+    attestation_config: Option<AttestationConfig>,
+    checkpoint_config: Option<CheckpointConfig>,
+) -> Result<PathBuf>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
+    // Checkpoints record the length of a single-file partial contribution
+    if checkpoint_config.is_some() && ceremony_config.chunk_size().is_some() {
+        return Err(anyhow!(
+            "Checkpointed contributions cannot be written as chunked contributions"
+        ));
+    }
+
+    let contributions_path = PathBuf::from(contributions_path_str);
+
+    let last_contribution_path = get_last_contribution_path(&contributions_path)?;
+    let last_id = read_id(&last_contribution_path)?;
+
+    info!(
+        "Streaming contribution with id {} from {:#?}",
+        last_id,
+        last_contribution_path.display()
+    );
+
+    // We hash the last contribution file while reading it, since the contribution proof is bound to its SHA256
+    let mut reader = HashingReader::new(BufReader::new(ContributionReader::open(
+        &last_contribution_path,
+    )?));
+    let layout = ContributionLayout::read(&mut reader)?;
+    let format = match layout {
+        ContributionLayout::Versioned(header) => {
+            if header.curve != E::CURVE_ID {
+                return Err(anyhow!(
+                    "The last contribution is defined over {:?} but {:?} was expected",
+                    header.curve,
+                    E::CURVE_ID
+                ));
+            }
+            header.read_format(SerdeFormat::RawBytes)
+        }
+        ContributionLayout::Legacy { .. } => SerdeFormat::RawBytes,
+    };
+
+    if layout.k() != ceremony_config.k() {
+        return Err(anyhow!(
+            "The last contribution with id {} has k {} but the ceremony k is {}",
+            last_id,
+            layout.k(),
+            ceremony_config.k()
+        ));
+    }
+    let n = ceremony_config.n();
+
+    // Each point is held both encoded and decoded in memory, along with the power of rho it is combined with
+    // when checking the SRS: read_points decodes in place, without any intermediate buffer.
+    // We need at least 2 points in the first chunk to retrieve s_g
+    let point_memory = E::G1Affine::byte_length(format)
+        + std::mem::size_of::<E::G1Affine>()
+        + std::mem::size_of::<E::Fr>();
+    let chunk_len = (chunk_budget / point_memory).clamp(2, n);
+    info!("Processing powers of tau in chunks of {} points", chunk_len);
+
+    let filepath = default_contribution_filepath(&contributions_path, last_id + 1)?;
+    let partial_filepath = partial_contribution_filepath(&filepath);
+    let checkpoint_filepath = checkpoint_filepath(&filepath);
+    let write_format = ceremony_config.format();
+
+    // We generate 2 secrets (s for rescaling the srs, z for the contribution proof) according to the config,
+    // or recover them from the checkpoint when resuming
+    let (s, z, mut checkpoint) = match &checkpoint_config {
+        Some(checkpoint_config) if checkpoint_config.resume => {
+            let checkpoint = Checkpoint::read(&checkpoint_filepath)?;
+            if checkpoint.prev_sha256 != hash_file(&last_contribution_path)?
+                || checkpoint.id != last_id + 1
+                || checkpoint.k != ceremony_config.k()
+                || checkpoint.encoding != PointEncoding::from_format(write_format)
+            {
+                return Err(anyhow!(
+                    "The checkpoint {:#?} does not match the last contribution and the ceremony config",
+                    checkpoint_filepath.display()
+                ));
+            }
+            let (s, z) = checkpoint.secrets::<E>(&checkpoint_config.passphrase)?;
+            verify_partial(&partial_filepath, &last_contribution_path, &checkpoint, &s)?;
+            (s, z, Some(checkpoint))
+        }
+        Some(checkpoint_config) => {
+            let (s, z) = gen_secrets::<E>(config)?;
+            let checkpoint = Checkpoint::new(
+                hash_file(&last_contribution_path)?,
+                last_id + 1,
+                ceremony_config.k(),
+                PointEncoding::from_format(write_format),
+                &checkpoint_config.passphrase,
+                &s,
+                &z,
+            )?;
+            (s, z, Some(checkpoint))
+        }
+        None => {
+            let (s, z) = gen_secrets::<E>(config)?;
+            (s, z, None)
+        }
+    };
+
+    info!("Rescaling powers of tau..");
+    let resume_index = checkpoint.as_ref().map_or(0, |checkpoint| checkpoint.index);
+    let mut writer = if resume_index > 0 {
+        let mut file = OpenOptions::new().write(true).open(&partial_filepath)?;
+        file.seek(SeekFrom::End(0))?;
+        ContributionWriter::File(BufWriter::new(file))
+    } else {
+        // A chunked partial contribution left by an interrupted run is discarded
+        if partial_filepath.is_dir() {
+            fs::remove_dir_all(&partial_filepath)?;
+        }
+        let header =
+            ContributionHeader::new(E::CURVE_ID, write_format, ceremony_config.k(), last_id + 1);
+        let mut writer = ContributionWriter::create::<E>(
+            &partial_filepath,
+            &header,
+            ceremony_config.chunk_size(),
+        )?;
+        header.write(&mut writer)?;
+        writer
+    };
+    if let Some(checkpoint) = checkpoint.as_ref().filter(|_| resume_index == 0) {
+        writer.flush()?;
+        checkpoint.write(&checkpoint_filepath)?;
+    }
+
+    // The SRS of the last contribution is checked chunk by chunk while it is streamed, whatever its layout.
+    // Since its points are read only once, they are combined with the powers of a random rho instead of one
+    // derived from their hash
+    let mut srs_checker = SrsChecker::<E>::random();
+    let mut prev_s_g: Option<E::G1Affine> = None;
+    let progress = Progress::start("Rescaling powers of tau", n);
+    let mut start = 0;
+    while start < n {
+        // Points before the resume index were already rescaled: they are read to hash the last contribution only
+        let len = if start < resume_index {
+            chunk_len.min(resume_index - start)
+        } else {
+            chunk_len.min(n - start)
+        };
+        let mut points: Vec<E::G1Affine> = read_points(&mut reader, format, len)?;
+
+        if start == 0 {
+            prev_s_g = Some(points[1]);
+        }
+        srs_checker.update(&points);
+
+        if start >= resume_index {
+            rescale_points(&mut points, start, &s);
+            for point in points.iter() {
+                point.write(&mut writer, write_format)?;
+            }
+
+            // We make sure the rescaled points are on disk before recording them in the checkpoint
+            if let Some(checkpoint) = checkpoint.as_mut() {
+                writer.sync_data()?;
+                checkpoint.index = start + len;
+                checkpoint.partial_length = CONTRIBUTION_HEADER_LENGTH as u64
+                    + (checkpoint.index * E::G1Affine::byte_length(write_format)) as u64;
+                checkpoint.write(&checkpoint_filepath)?;
+            }
+
+            info!("Rescaled {}/{} powers of tau", start + len, n);
+        }
+
+        progress.advance(len);
+        start += len;
+    }
+    progress.finish();
+    let prev_s_g = prev_s_g.unwrap();
+
+    let prev_g2 = <E::G2Affine as SerdeCurveAffine>::read(&mut reader, format)?;
+    if prev_g2 != <E::G2Affine as PrimeCurveAffine>::generator() {
+        return Err(anyhow!(
+            "The last contribution with id {} has a point g2 which is not the generator of G2",
+            last_id
+        ));
+    }
+    let prev_s_g2 = <E::G2Affine as SerdeCurveAffine>::read(&mut reader, format)?;
+
+    // The new contribution is completed only if the last contribution is valid
+    if !srs_checker.finish(&prev_g2, &prev_s_g2, n).is_valid() {
+        return Err(anyhow!(
+            "The retrieved last contribution with id {:#?} is not valid",
+            last_id
+        ));
+    }
+    let s_g2: E::G2Affine = (prev_s_g2 * s.get()).into();
+    let last_sha256 = reader.finalize()?;
+
+    // We compute a contribution proof
+    info!("Computing contribution proof..");
+    let context = ProofContext {
+        prev_sha256: last_sha256,
+        id: last_id + 1,
+    };
+    let contribution_proof = create_contribution_proof(&prev_s_g, &s, &z, &context, metadata);
+
+    // We explicitly drop the secrets
+    drop(s);
+    drop(z);
+
+    <E::G2Affine as PrimeCurveAffine>::generator().write(&mut writer, write_format)?;
+    s_g2.write(&mut writer, write_format)?;
+    contribution_proof.write_versioned(&mut writer)?;
+    writer.finish()?;
+
+    fs::rename(&partial_filepath, &filepath)?;
+    info!("Contribution written to {:#?}", &filepath.display());
+
+    // The checkpoint holds the encrypted secrets, so it is wiped once the contribution is completed
+    if checkpoint.is_some() {
+        Checkpoint::wipe(&checkpoint_filepath)?;
+    }
+
+    if let Some(attestation_config) = attestation_config {
+        attest_contribution(&filepath, &attestation_config)?;
+    }
+
+    Ok(filepath)
+}
+
+// Returns the path where a contribution is written while being computed
+pub(crate) fn partial_contribution_filepath(filepath: &Path) -> PathBuf {
+    let mut partial_filepath = filepath.to_path_buf();
+    partial_filepath.set_extension(format!(
+        "{}.{}",
+        CONTRIBUTION_EXTENSION, PARTIAL_CONTRIBUTION_EXTENSION
+    ));
+    partial_filepath
+}
@@ -1,5 +1,5 @@
 use core::fmt::Debug;
-use group::prime::PrimeCurveAffine;
+use group::{prime::PrimeCurveAffine, Group};
 use halo2_proofs::arithmetic::{g_to_lagrange, CurveExt};
 use halo2_proofs::halo2curves::{pairing::Engine, serde::SerdeObject};
 use halo2_proofs::poly::commitment::ParamsProver;
@@ -9,15 +9,33 @@ use halo2_proofs::{
     SerdeFormat,
 };
 use log::info;
+use std::borrow::Cow;
 use std::io::{self, BufReader, BufWriter, Error, ErrorKind, Write};
 use std::path::PathBuf;
 
-use crate::contribute::default_contribution_filepath;
+use crate::chunked::{ContributionReader, ContributionWriter};
+use crate::contribute::{default_contribution_filepath, partial_contribution_filepath};
+use crate::header::{
+    ContributionHeader, ContributionLayout, CurveIdentifier, CONTRIBUTION_G2_COUNT,
+};
+use crate::progress::{Progress, PROGRESS_STEP};
 use crate::proof::ContributionProof;
 
-pub const CONTRIBUTION_K: u32 = 28;
-pub const CONTRIBUTION_N: usize = 1 << CONTRIBUTION_K;
+pub const DEFAULT_CONTRIBUTION_K: u32 = 28;
+pub const MIN_CONTRIBUTION_K: u32 = 1;
+// Lagrange basis computation requires 2^k to divide the multiplicative group order of the scalar field
+pub const MAX_CONTRIBUTION_K: u32 = 28;
 pub const CONTRIBUTION_EXTENSION: &str = "csrs";
+// Maximum number of butterflies computed by each task of a FFT layer
+/// Number of G1Affine elements processed at once when the points of a contribution are read in chunks.
+/// Chunks are large enough to keep most of the benefit of bucket-based msm, which is already split across threads,
+/// while bounding the memory used to decode the points of a memory-mapped contribution
+pub const G1_CHUNK_LENGTH: usize = 1 << 22;
+
+/// Returns true if k is a supported ceremony size
+pub fn is_supported_k(k: u32) -> bool {
+    (MIN_CONTRIBUTION_K..=MAX_CONTRIBUTION_K).contains(&k)
+}
 
 /// KZG public parameters for ceremony contributors
 #[derive(Debug, Clone)]
@@ -33,10 +51,18 @@ pub struct KZGContribution<E: Engine> {
 
 impl<E: Engine + Debug> Default for KZGContribution<E> {
     fn default() -> Self {
+        Self::trivial(DEFAULT_CONTRIBUTION_K)
+    }
+}
+
+impl<E: Engine + Debug> KZGContribution<E> {
+    /// Returns a trivial contribution of size 2^k, i.e. a SRS with tau = 1
+    pub fn trivial(k: u32) -> Self {
+        let n = 1 << k;
         Self {
-            k: CONTRIBUTION_K,
-            n: CONTRIBUTION_N,
-            g: vec![<E::G1Affine as PrimeCurveAffine>::generator(); CONTRIBUTION_N],
+            k,
+            n,
+            g: vec![<E::G1Affine as PrimeCurveAffine>::generator(); n],
             g2: <E::G2Affine as PrimeCurveAffine>::generator(),
             s_g2: <E::G2Affine as PrimeCurveAffine>::generator(),
             proof: ContributionProof::<E>::default(),
@@ -114,27 +140,13 @@ where
         E::G1: CurveExt<AffineExt = E::G1Affine>,
         E::G2Affine: SerdeCurveAffine,
     {
-        info!("Converting contribution with id {} to ParamsKZG", self.id());
-
-        info!(
-            "Computing Lagrange basis for contribution with id {}",
-            self.id()
-        );
-        let g_lagrange: Vec<E::G1Affine> = g_to_lagrange(
-            self.g.iter().map(PrimeCurveAffine::to_curve).collect(),
-            self.k,
-        );
-        info!("Lagrange basis computed..");
-
-        // ParamsKZG::from_parts takes &self as input. We create an empty struct (k=0) and populate it with the contribution field
-        let empty_params = ParamsKZG::new(0);
-
-        empty_params.from_parts(self.k, self.g, Some(g_lagrange), self.g2, self.s_g2)
+        params_from_parts(self.id, self.k, self.g, self.g2, self.s_g2)
     }
 
     /// Writes a contribution to disk
     pub fn write(&self, filepath: &PathBuf) -> io::Result<()>
     where
+        E: CurveIdentifier,
         E::G2Affine: SerdeCurveAffine,
         <E as Engine>::Fr: SerdeObject,
     {
@@ -144,9 +156,28 @@ where
         buffered_writer.flush()
     }
 
-    /// Writes a contribution to disk to a default path generated from the contribution's id
-    pub fn write_default(&self, contributions_path_str: &str) -> io::Result<()>
+    /// Writes a contribution to disk to a default path generated from the contribution's id.
+    /// Returns the path the contribution was written to
+    pub fn write_default(&self, contributions_path_str: &str) -> io::Result<PathBuf>
+    where
+        E: CurveIdentifier,
+        E::G2Affine: SerdeCurveAffine,
+        <E as Engine>::Fr: SerdeObject,
+    {
+        self.write_default_custom(contributions_path_str, SerdeFormat::RawBytes, None)
+    }
+
+    /// Writes a contribution to disk to a default path generated from the contribution's id, encoding points according to `format`.
+    /// If a chunk size is given, the contribution is written as a chunked contribution directory.
+    /// Returns the path the contribution was written to
+    pub fn write_default_custom(
+        &self,
+        contributions_path_str: &str,
+        format: SerdeFormat,
+        chunk_size: Option<u64>,
+    ) -> io::Result<PathBuf>
     where
+        E: CurveIdentifier,
         E::G2Affine: SerdeCurveAffine,
         <E as Engine>::Fr: SerdeObject,
     {
@@ -156,24 +187,37 @@ where
             Err(e) => Err(io::Error::new(ErrorKind::Other, e.to_string())),
         }?;
 
-        let fd_write = std::fs::File::create(&filepath)?;
-        let mut buffered_writer = BufWriter::new(fd_write);
-        self.write_custom(&mut buffered_writer, SerdeFormat::RawBytes)?;
-        buffered_writer.flush()?;
+        // Chunked contributions are written to a partial directory, renamed once completed
+        let write_filepath = match chunk_size {
+            Some(_) => partial_contribution_filepath(&filepath),
+            None => filepath.clone(),
+        };
+        if write_filepath.is_dir() {
+            std::fs::remove_dir_all(&write_filepath)?;
+        }
+
+        let header = ContributionHeader::new(E::CURVE_ID, format, self.k, self.id);
+        let mut writer = ContributionWriter::create::<E>(&write_filepath, &header, chunk_size)?;
+        self.write_custom(&mut writer, format)?;
+        writer.finish()?;
+        if write_filepath != filepath {
+            std::fs::rename(&write_filepath, &filepath)?;
+        }
 
         info!("Contribution written to {:#?}", &filepath.display());
 
-        Ok(())
+        Ok(filepath)
     }
 
-    /// Reads a contribution from disk
+    /// Reads a contribution from disk, stored in a single file or chunked
     pub fn from(filepath: &PathBuf) -> io::Result<Self>
     where
+        E: CurveIdentifier,
         E::G2Affine: SerdeCurveAffine,
         <E as Engine>::Fr: SerdeObject,
     {
         info!("Reading contribution from {:#?}", &filepath.display());
-        let fd_read = std::fs::File::open(filepath)?;
+        let fd_read = ContributionReader::open(filepath)?;
         let mut buffered_reader = BufReader::new(fd_read);
         KZGContribution::read_custom(&mut buffered_reader, SerdeFormat::RawBytes)
     }
@@ -181,35 +225,54 @@ where
     /// Writes parameters to buffer
     pub fn write_custom<W: io::Write>(&self, writer: &mut W, format: SerdeFormat) -> io::Result<()>
     where
+        E: CurveIdentifier,
         E::G2Affine: SerdeCurveAffine,
         <E as Engine>::Fr: SerdeObject,
     {
-        writer.write_all(&self.k.to_le_bytes())?;
+        ContributionHeader::new(E::CURVE_ID, format, self.k, self.id).write(writer)?;
         for el in self.g.iter() {
             el.write(writer, format)?;
         }
         self.g2.write(writer, format)?;
         self.s_g2.write(writer, format)?;
-        self.proof.write(writer)?;
-        writer.write_all(&self.id.to_le_bytes())?;
+        self.proof.write_versioned(writer)?;
         Ok(())
     }
 
     /// Reads params from a buffer.
+    /// Both versioned contributions and legacy contributions (written without a header) are supported.
+    /// For versioned contributions, the point encoding is the one declared in the header
     pub fn read_custom<R: io::Read>(reader: &mut R, format: SerdeFormat) -> io::Result<Self>
     where
+        E: CurveIdentifier,
         E::G2Affine: SerdeCurveAffine,
         <E as Engine>::Fr: SerdeObject,
     {
-        let mut k = [0u8; 4];
-        reader.read_exact(&mut k[..])?;
-        let k = u32::from_le_bytes(k);
-        if k != CONTRIBUTION_K {
+        let layout = ContributionLayout::read(reader)?;
+        let format = match &layout {
+            ContributionLayout::Versioned(header) => {
+                if header.curve != E::CURVE_ID {
+                    return Err(Error::new(
+                        ErrorKind::InvalidInput,
+                        format!(
+                            "Contribution is defined over {:?} but {:?} was expected",
+                            header.curve,
+                            E::CURVE_ID
+                        ),
+                    ));
+                }
+                header.read_format(format)
+            }
+            ContributionLayout::Legacy { .. } => format,
+        };
+
+        let k = layout.k();
+        if !is_supported_k(k) {
             return Err(Error::new(
                 ErrorKind::InvalidInput,
                 format!(
-                    "Contribution read has k {} but should be {}",
-                    k, CONTRIBUTION_K
+                    "Contribution read has k {} but should be between {} and {}",
+                    k, MIN_CONTRIBUTION_K, MAX_CONTRIBUTION_K
                 ),
             ));
         }
@@ -262,11 +325,21 @@ where
         let g2 = <E::G2Affine as SerdeCurveAffine>::read(reader, format)?;
         let s_g2 = <E::G2Affine as SerdeCurveAffine>::read(reader, format)?;
 
-        let proof = ContributionProof::read(reader)?;
+        let proof = if layout.has_versioned_proof() {
+            ContributionProof::read_versioned(reader)?
+        } else {
+            ContributionProof::read(reader)?
+        };
 
-        let mut id = [0u8; 4];
-        reader.read_exact(&mut id[..])?;
-        let id = u32::from_le_bytes(id);
+        // Legacy contributions store their id in the last 4 bytes
+        let id = match layout {
+            ContributionLayout::Versioned(header) => header.id,
+            ContributionLayout::Legacy { .. } => {
+                let mut id = [0u8; 4];
+                reader.read_exact(&mut id[..])?;
+                u32::from_le_bytes(id)
+            }
+        };
 
         // We ensure all points read are on the curve
         if !g.iter().all(|&point| point.is_on_curve().into()) {
@@ -309,6 +382,149 @@ where
     }
 }
 
+/// Converts the parts of a contribution to halo2 ParamsKZG, computing the Lagrange basis
+pub(crate) fn params_from_parts<E: Engine + Debug>(
+    id: u32,
+    k: u32,
+    g: Vec<E::G1Affine>,
+    g2: E::G2Affine,
+    s_g2: E::G2Affine,
+) -> ParamsKZG<E>
+where
+    E::G1Affine: SerdeCurveAffine<ScalarExt = <E as Engine>::Fr, CurveExt = <E as Engine>::G1>,
+    E::G1: CurveExt<AffineExt = E::G1Affine>,
+    E::G2Affine: SerdeCurveAffine,
+{
+    info!("Converting contribution with id {} to ParamsKZG", id);
+
+    info!("Computing Lagrange basis for contribution with id {}", id);
+    let g_lagrange: Vec<E::G1Affine> = powers_to_lagrange::<E>(&g, k);
+    info!("Lagrange basis computed..");
+
+    // ParamsKZG::from_parts takes &self as input. We create an empty struct (k=0) and populate it with the contribution field
+    let empty_params = ParamsKZG::new(0);
+
+    empty_params.from_parts(k, g, Some(g_lagrange), g2, s_g2)
+}
+
+// Computes the Lagrange basis of the powers of tau g with halo2_proofs' g_to_lagrange.
+// Its inverse FFT does not report any progress: we report the conversion of the powers of tau to projective points,
+// and the inverse FFT as a single phase, so that at least its start and its end are known
+fn powers_to_lagrange<E: Engine>(g: &[E::G1Affine], k: u32) -> Vec<E::G1Affine>
+where
+    E::G1Affine: CurveAffine<CurveExt = E::G1>,
+    E::G1: CurveExt<AffineExt = E::G1Affine>,
+{
+    let progress = Progress::start("Converting powers of tau to projective", g.len());
+    let mut g_projective = vec![<E::G1 as Group>::identity(); g.len()];
+    parallelize(&mut g_projective, |chunk_g_projective, start| {
+        for (offset, chunk_g_projective) in (start..)
+            .step_by(PROGRESS_STEP)
+            .zip(chunk_g_projective.chunks_mut(PROGRESS_STEP))
+        {
+            for (g_projective_el, g_el) in chunk_g_projective.iter_mut().zip(&g[offset..]) {
+                *g_projective_el = g_el.to_curve();
+            }
+            progress.advance(chunk_g_projective.len());
+        }
+    });
+    progress.finish();
+
+    let progress = Progress::start("Computing the Lagrange basis", 1);
+    let g_lagrange = g_to_lagrange(g_projective, k);
+    progress.advance(1);
+    progress.finish();
+
+    g_lagrange
+}
+
+// Returns the byte length of the points stored in a contribution
+pub(crate) fn points_length<E: Engine>(k: u32, format: SerdeFormat) -> u64
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+{
+    (1u64 << k) * E::G1Affine::byte_length(format) as u64
+        + CONTRIBUTION_G2_COUNT * E::G2Affine::byte_length(format) as u64
+}
+
+/// Read access to the elements of a contribution, independently of where they are stored
+pub trait ContributionView<E: Engine> {
+    /// Returns the contribution's k
+    fn k(&self) -> u32;
+
+    /// Returns n = 1 << k
+    fn n(&self) -> usize;
+
+    /// Returns the len G1Affine elements of the contribution starting from index start.
+    /// Elements held in memory are borrowed, while elements stored elsewhere are decoded on each call
+    fn g_chunk(&self, start: usize, len: usize) -> Cow<'_, [E::G1Affine]>;
+
+    /// Returns the generator of G2
+    fn g2(&self) -> &E::G2Affine;
+
+    /// Returns first power of secret on G2
+    fn s_g2(&self) -> &E::G2Affine;
+
+    /// Returns the Contribution proof
+    fn proof(&self) -> &ContributionProof<E>;
+
+    /// Returns the Contribution id
+    fn id(&self) -> u32;
+
+    /// Returns first power of secret on G1
+    fn s_g(&self) -> &E::G1Affine;
+}
+
+/// Iterates over the G1Affine elements of a contribution in consecutive chunks of at most chunk_length elements,
+/// yielding each chunk along with the index of its first element
+pub fn g_chunks<'a, E: Engine + 'a, V: ContributionView<E> + ?Sized + 'a>(
+    contribution: &'a V,
+    chunk_length: usize,
+) -> impl Iterator<Item = (usize, Cow<'a, [E::G1Affine]>)> + 'a {
+    let n = contribution.n();
+    (0..n).step_by(chunk_length).map(move |start| {
+        (
+            start,
+            contribution.g_chunk(start, chunk_length.min(n - start)),
+        )
+    })
+}
+
+impl<E: Engine + Debug> ContributionView<E> for KZGContribution<E> {
+    fn k(&self) -> u32 {
+        self.k
+    }
+
+    fn n(&self) -> usize {
+        self.n
+    }
+
+    fn g_chunk(&self, start: usize, len: usize) -> Cow<'_, [E::G1Affine]> {
+        Cow::Borrowed(&self.g[start..start + len])
+    }
+
+    fn g2(&self) -> &E::G2Affine {
+        &self.g2
+    }
+
+    fn s_g2(&self) -> &E::G2Affine {
+        &self.s_g2
+    }
+
+    fn proof(&self) -> &ContributionProof<E> {
+        &self.proof
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn s_g(&self) -> &E::G1Affine {
+        &self.g[1]
+    }
+}
+
 pub trait SerdeCurveAffine: CurveAffine + SerdeObject {
     /// Reads an element from the buffer and parses it according to the `format`:
     /// - `Processed`: Reads a compressed curve element and decompress it
@@ -343,6 +559,52 @@ pub trait SerdeCurveAffine: CurveAffine + SerdeObject {
 }
 impl<C: CurveAffine + SerdeObject> SerdeCurveAffine for C {}
 
+/// Reads len consecutive curve elements encoded according to `format`.
+/// Bytes are read sequentially, while points are decoded in parallel directly into the returned vector,
+/// so that at most the encoded bytes and the decoded points are held in memory
+pub fn read_points<C: SerdeCurveAffine, R: io::Read>(
+    reader: &mut R,
+    format: SerdeFormat,
+    len: usize,
+) -> io::Result<Vec<C>> {
+    use group::GroupEncoding;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    let point_length = C::byte_length(format);
+    let mut bytes = vec![0u8; len * point_length];
+    reader.read_exact(&mut bytes)?;
+
+    let mut points = vec![C::default(); len];
+    let invalid = AtomicBool::new(false);
+    parallelize(&mut points, |points, start| {
+        for (i, point) in points.iter_mut().enumerate() {
+            let offset = (start + i) * point_length;
+            let point_bytes = &bytes[offset..offset + point_length];
+            let decoded = match format {
+                SerdeFormat::Processed => {
+                    let mut compressed = C::Repr::default();
+                    compressed.as_mut().copy_from_slice(point_bytes);
+                    Option::from(C::from_bytes(&compressed))
+                }
+                SerdeFormat::RawBytes => C::from_raw_bytes(point_bytes),
+                SerdeFormat::RawBytesUnchecked => Some(C::from_raw_bytes_unchecked(point_bytes)),
+            };
+            match decoded {
+                Some(decoded) => *point = decoded,
+                None => invalid.store(true, Ordering::Relaxed),
+            }
+        }
+    });
+
+    if invalid.load(Ordering::Relaxed) {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            "invalid point encoding",
+        ));
+    }
+    Ok(points)
+}
+
 // Keep this trait for compatibility with IPA serialization
 pub(crate) trait CurveRead: CurveAffine {
     /// Reads a compressed element from the buffer and attempts to parse it
@@ -368,8 +630,68 @@ where
             && (self.g == other.g)
             && (self.g2 == other.g2)
             && (self.s_g2 == other.s_g2)
+            && (self.proof.version() == other.proof.version())
             && (self.proof.p() == other.proof.p())
             && (self.proof.r() == other.proof.r())
+            && (self.proof.metadata() == other.proof.metadata())
             && (self.id == other.id)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::{hash_file, read_id, ScratchDir};
+    use group::ff::Field;
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G2Affine};
+    use sha2::{Digest, Sha256};
+
+    #[test]
+    fn legacy_layout_is_read() {
+        let scratch = ScratchDir::new("legacy_layout");
+        let k: u32 = 3;
+        let id: u32 = 7;
+        let tau = Fr::from(5);
+        let mut power = Fr::ONE;
+        let g: Vec<G1Affine> = (0..1 << k)
+            .map(|_| {
+                let point = (G1Affine::generator() * power).into();
+                power *= tau;
+                point
+            })
+            .collect();
+        let g2 = G2Affine::generator();
+        let s_g2: G2Affine = (g2 * tau).into();
+        let proof =
+            ContributionProof::<Bn256>::new((G1Affine::generator() * tau).into(), Fr::from(11));
+
+        // Legacy files start with a bare k and end with the id, with unversioned proofs and RawBytes points
+        let mut bytes = k.to_le_bytes().to_vec();
+        for point in g.iter() {
+            point.write(&mut bytes, SerdeFormat::RawBytes).unwrap();
+        }
+        g2.write(&mut bytes, SerdeFormat::RawBytes).unwrap();
+        s_g2.write(&mut bytes, SerdeFormat::RawBytes).unwrap();
+        proof.write(&mut bytes).unwrap();
+        bytes.extend_from_slice(&id.to_le_bytes());
+        let filepath = scratch.0.join("legacy.csrs");
+        std::fs::write(&filepath, &bytes).unwrap();
+
+        let contribution =
+            KZGContribution::<Bn256>::read_custom(&mut bytes.as_slice(), SerdeFormat::RawBytes)
+                .unwrap();
+        assert_eq!(
+            contribution,
+            KZGContribution::from_parts(k, g, g2, s_g2, proof, id)
+        );
+        assert_eq!(
+            KZGContribution::<Bn256>::from(&filepath).unwrap(),
+            contribution
+        );
+        assert_eq!(read_id(&filepath).unwrap(), id);
+        assert_eq!(
+            hash_file(&filepath).unwrap(),
+            hex::encode(Sha256::digest(&bytes))
+        );
+    }
+}
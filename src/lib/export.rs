@@ -0,0 +1,604 @@
+use anyhow::{anyhow, Result};
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+use group::ff::{Field, PrimeField};
+use group::prime::PrimeCurveAffine;
+use halo2_proofs::arithmetic::CurveAffine;
+use halo2_proofs::halo2curves::bn256::{Bn256, Fq, Fq2, G1Affine, G2Affine};
+use log::info;
+use std::{
+    fmt,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+use crate::contribution::{g_chunks, ContributionView, G1_CHUNK_LENGTH};
+
+/// Formats the finalized parameters can be written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParamsFormat {
+    /// halo2 ParamsKZG
+    Halo2,
+    /// arkworks' ark-poly-commit KZG10 UniversalParams, uncompressed
+    Arkworks,
+    /// gnark-crypto kzg.SRS, with raw points
+    Gnark,
+}
+
+impl ParamsFormat {
+    /// Names of the parameters formats, as accepted by `from_str`
+    pub const NAMES: [&'static str; 3] = ["halo2", "arkworks", "gnark"];
+
+    /// Returns the extension of files written in this format
+    pub fn extension(self) -> &'static str {
+        match self {
+            ParamsFormat::Halo2 => "params",
+            ParamsFormat::Arkworks => "ark",
+            ParamsFormat::Gnark => "gnark",
+        }
+    }
+}
+
+impl fmt::Display for ParamsFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParamsFormat::Halo2 => write!(f, "halo2"),
+            ParamsFormat::Arkworks => write!(f, "arkworks"),
+            ParamsFormat::Gnark => write!(f, "gnark"),
+        }
+    }
+}
+
+impl FromStr for ParamsFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "halo2" => Ok(ParamsFormat::Halo2),
+            "arkworks" => Ok(ParamsFormat::Arkworks),
+            "gnark" => Ok(ParamsFormat::Gnark),
+            _ => Err(anyhow!(
+                "Unknown parameters format {:?}, the available formats are: {}",
+                s,
+                ParamsFormat::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// The BN256 KZG SRS elements read back from an exported file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedSrs {
+    /// Powers of tau in G1
+    pub g: Vec<G1Affine>,
+    /// The generator of G2
+    pub g2: G2Affine,
+    /// First power of tau in G2
+    pub s_g2: G2Affine,
+}
+
+// Byte length of a BN256 base field element
+pub(crate) const FQ_LENGTH: usize = 32;
+
+// arkworks flags, stored in the two most significant bits of the last byte of a serialized point
+const ARKWORKS_Y_IS_NEGATIVE: u8 = 1 << 7;
+const ARKWORKS_POINT_AT_INFINITY: u8 = 1 << 6;
+
+// gnark flags, stored in the two most significant bits of the first byte of a serialized point
+const GNARK_MASK: u8 = 0b11 << 6;
+const GNARK_UNCOMPRESSED: u8 = 0b00 << 6;
+const GNARK_COMPRESSED_SMALLEST: u8 = 0b10 << 6;
+const GNARK_COMPRESSED_LARGEST: u8 = 0b11 << 6;
+const GNARK_COMPRESSED_INFINITY: u8 = 0b01 << 6;
+
+// Returns the canonical little-endian encoding of a base field element
+fn fq_to_le(fq: &Fq) -> [u8; FQ_LENGTH] {
+    let mut bytes = [0u8; FQ_LENGTH];
+    bytes.copy_from_slice(fq.to_repr().as_ref());
+    bytes
+}
+
+// Returns the canonical big-endian encoding of a base field element
+fn fq_to_be(fq: &Fq) -> [u8; FQ_LENGTH] {
+    let mut bytes = fq_to_le(fq);
+    bytes.reverse();
+    bytes
+}
+
+// Parses a canonical little-endian base field element, failing if it is not less than the modulus
+fn fq_from_le(bytes: &[u8]) -> Result<Fq> {
+    let mut repr = <Fq as PrimeField>::Repr::default();
+    repr.as_mut().copy_from_slice(bytes);
+    Option::from(Fq::from_repr(repr)).ok_or_else(|| anyhow!("Invalid base field element"))
+}
+
+// Parses a canonical big-endian base field element, failing if it is not less than the modulus
+pub(crate) fn fq_from_be(bytes: &[u8]) -> Result<Fq> {
+    let mut le_bytes = bytes.to_vec();
+    le_bytes.reverse();
+    fq_from_le(&le_bytes)
+}
+
+// Returns true if fq > -fq, that is fq > (q-1)/2.
+// Both arkworks and gnark use this ordering to select the sign of y
+pub(crate) fn fq_is_largest(fq: &Fq) -> bool {
+    fq_to_be(fq) > fq_to_be(&-fq)
+}
+
+// Returns true if fq2 > -fq2, comparing c1 first and then c0
+pub(crate) fn fq2_is_largest(fq2: &Fq2) -> bool {
+    if bool::from(fq2.c1.is_zero()) {
+        fq_is_largest(&fq2.c0)
+    } else {
+        fq_is_largest(&fq2.c1)
+    }
+}
+
+// Returns a point from its coordinates, failing if it is not on the curve
+pub(crate) fn point_from_xy<C: CurveAffine>(x: C::Base, y: C::Base) -> Result<C> {
+    Option::from(C::from_xy(x, y)).ok_or_else(|| anyhow!("Point is not on the curve"))
+}
+
+// Returns the point with the given x and sign of y, failing if x is not the abscissa of a point on the curve
+pub(crate) fn point_from_x<C: CurveAffine>(
+    x: C::Base,
+    largest: bool,
+    is_largest: impl Fn(&C::Base) -> bool,
+) -> Result<C> {
+    let y2 = x.square() * x + C::b();
+    let y: C::Base = Option::from(y2.sqrt()).ok_or_else(|| anyhow!("Point is not on the curve"))?;
+    let y = if is_largest(&y) == largest { y } else { -y };
+    point_from_xy(x, y)
+}
+
+fn write_arkworks_g1<W: Write>(writer: &mut W, point: &G1Affine) -> Result<()> {
+    if bool::from(point.is_identity()) {
+        let mut bytes = [0u8; 2 * FQ_LENGTH];
+        bytes[2 * FQ_LENGTH - 1] = ARKWORKS_POINT_AT_INFINITY;
+        writer.write_all(&bytes)?;
+        return Ok(());
+    }
+
+    let mut y = fq_to_le(&point.y);
+    if fq_is_largest(&point.y) {
+        y[FQ_LENGTH - 1] |= ARKWORKS_Y_IS_NEGATIVE;
+    }
+    writer.write_all(&fq_to_le(&point.x))?;
+    writer.write_all(&y)?;
+    Ok(())
+}
+
+fn write_arkworks_g2<W: Write>(writer: &mut W, point: &G2Affine) -> Result<()> {
+    if bool::from(point.is_identity()) {
+        let mut bytes = [0u8; 4 * FQ_LENGTH];
+        bytes[4 * FQ_LENGTH - 1] = ARKWORKS_POINT_AT_INFINITY;
+        writer.write_all(&bytes)?;
+        return Ok(());
+    }
+
+    let mut y_c1 = fq_to_le(&point.y.c1);
+    if fq2_is_largest(&point.y) {
+        y_c1[FQ_LENGTH - 1] |= ARKWORKS_Y_IS_NEGATIVE;
+    }
+    writer.write_all(&fq_to_le(&point.x.c0))?;
+    writer.write_all(&fq_to_le(&point.x.c1))?;
+    writer.write_all(&fq_to_le(&point.y.c0))?;
+    writer.write_all(&y_c1)?;
+    Ok(())
+}
+
+fn read_arkworks_g1<R: Read>(reader: &mut R) -> Result<G1Affine> {
+    let mut bytes = [0u8; 2 * FQ_LENGTH];
+    reader.read_exact(&mut bytes)?;
+    let flags = bytes[2 * FQ_LENGTH - 1] & (ARKWORKS_Y_IS_NEGATIVE | ARKWORKS_POINT_AT_INFINITY);
+    if flags & ARKWORKS_POINT_AT_INFINITY != 0 {
+        return Ok(G1Affine::identity());
+    }
+
+    bytes[2 * FQ_LENGTH - 1] &= !flags;
+    let x = fq_from_le(&bytes[..FQ_LENGTH])?;
+    let y = fq_from_le(&bytes[FQ_LENGTH..])?;
+    point_from_xy(x, y)
+}
+
+fn read_arkworks_g2<R: Read>(reader: &mut R) -> Result<G2Affine> {
+    let mut bytes = [0u8; 4 * FQ_LENGTH];
+    reader.read_exact(&mut bytes)?;
+    let flags = bytes[4 * FQ_LENGTH - 1] & (ARKWORKS_Y_IS_NEGATIVE | ARKWORKS_POINT_AT_INFINITY);
+    if flags & ARKWORKS_POINT_AT_INFINITY != 0 {
+        return Ok(G2Affine::identity());
+    }
+
+    bytes[4 * FQ_LENGTH - 1] &= !flags;
+    let x = Fq2 {
+        c0: fq_from_le(&bytes[..FQ_LENGTH])?,
+        c1: fq_from_le(&bytes[FQ_LENGTH..2 * FQ_LENGTH])?,
+    };
+    let y = Fq2 {
+        c0: fq_from_le(&bytes[2 * FQ_LENGTH..3 * FQ_LENGTH])?,
+        c1: fq_from_le(&bytes[3 * FQ_LENGTH..])?,
+    };
+    point_from_xy(x, y)
+}
+
+/// Writes a contribution as an uncompressed ark-poly-commit KZG10 UniversalParams, i.e. in order:
+/// - powers_of_g: a u64 little-endian length followed by the powers of tau in G1;
+/// - powers_of_gamma_g: an empty map, as gamma is not part of the ceremony;
+/// - h and beta_h: the generator of G2 and the first power of tau in G2;
+/// - neg_powers_of_h: an empty map, as negative powers of tau are not part of the ceremony.
+///
+/// Field elements are canonical and little-endian, with arkworks flags in the most significant bits of y
+pub fn write_arkworks<V: ContributionView<Bn256>>(contribution: &V, filepath: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(filepath)?);
+    write_arkworks_to(contribution, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_arkworks_to<V: ContributionView<Bn256>, W: Write>(
+    contribution: &V,
+    writer: &mut W,
+) -> Result<()> {
+    writer.write_u64::<LittleEndian>(contribution.n() as u64)?;
+    for (_, g) in g_chunks(contribution, G1_CHUNK_LENGTH) {
+        for point in g.iter() {
+            write_arkworks_g1(writer, point)?;
+        }
+    }
+    writer.write_u64::<LittleEndian>(0)?;
+    write_arkworks_g2(writer, contribution.g2())?;
+    write_arkworks_g2(writer, contribution.s_g2())?;
+    writer.write_u64::<LittleEndian>(0)?;
+    Ok(())
+}
+
+/// Reads an uncompressed ark-poly-commit KZG10 UniversalParams, as specified in write_arkworks
+pub fn read_arkworks(filepath: &Path) -> Result<ExportedSrs> {
+    read_arkworks_from(&mut BufReader::new(File::open(filepath)?))
+}
+
+fn read_arkworks_from<R: Read>(reader: &mut R) -> Result<ExportedSrs> {
+    let n = reader.read_u64::<LittleEndian>()?;
+    let g = (0..n)
+        .map(|_| read_arkworks_g1(reader))
+        .collect::<Result<Vec<G1Affine>>>()?;
+
+    // We skip powers_of_gamma_g, whose entries are a u64 key and a G1 point
+    let gamma_length = reader.read_u64::<LittleEndian>()?;
+    for _ in 0..gamma_length {
+        reader.read_u64::<LittleEndian>()?;
+        read_arkworks_g1(reader)?;
+    }
+
+    let g2 = read_arkworks_g2(reader)?;
+    let s_g2 = read_arkworks_g2(reader)?;
+
+    Ok(ExportedSrs { g, g2, s_g2 })
+}
+
+fn write_gnark_g1_raw<W: Write>(writer: &mut W, point: &G1Affine) -> Result<()> {
+    if bool::from(point.is_identity()) {
+        writer.write_all(&[0u8; 2 * FQ_LENGTH])?;
+        return Ok(());
+    }
+
+    writer.write_all(&fq_to_be(&point.x))?;
+    writer.write_all(&fq_to_be(&point.y))?;
+    Ok(())
+}
+
+fn write_gnark_g2_raw<W: Write>(writer: &mut W, point: &G2Affine) -> Result<()> {
+    if bool::from(point.is_identity()) {
+        writer.write_all(&[0u8; 4 * FQ_LENGTH])?;
+        return Ok(());
+    }
+
+    writer.write_all(&fq_to_be(&point.x.c1))?;
+    writer.write_all(&fq_to_be(&point.x.c0))?;
+    writer.write_all(&fq_to_be(&point.y.c1))?;
+    writer.write_all(&fq_to_be(&point.y.c0))?;
+    Ok(())
+}
+
+// Reads a gnark G1 point, either raw or compressed according to its flags
+fn read_gnark_g1<R: Read>(reader: &mut R) -> Result<G1Affine> {
+    let mut bytes = vec![0u8; FQ_LENGTH];
+    reader.read_exact(&mut bytes)?;
+    let flags = bytes[0] & GNARK_MASK;
+    bytes[0] &= !GNARK_MASK;
+
+    match flags {
+        GNARK_UNCOMPRESSED => {
+            let mut y = [0u8; FQ_LENGTH];
+            reader.read_exact(&mut y)?;
+            if bytes.iter().chain(y.iter()).all(|byte| *byte == 0) {
+                return Ok(G1Affine::identity());
+            }
+            point_from_xy(fq_from_be(&bytes)?, fq_from_be(&y)?)
+        }
+        GNARK_COMPRESSED_INFINITY => Ok(G1Affine::identity()),
+        _ => point_from_x(
+            fq_from_be(&bytes)?,
+            flags == GNARK_COMPRESSED_LARGEST,
+            fq_is_largest,
+        ),
+    }
+}
+
+// Reads a gnark G2 point, either raw or compressed according to its flags
+fn read_gnark_g2<R: Read>(reader: &mut R) -> Result<G2Affine> {
+    let mut bytes = vec![0u8; 2 * FQ_LENGTH];
+    reader.read_exact(&mut bytes)?;
+    let flags = bytes[0] & GNARK_MASK;
+    bytes[0] &= !GNARK_MASK;
+
+    let x = Fq2 {
+        c1: fq_from_be(&bytes[..FQ_LENGTH])?,
+        c0: fq_from_be(&bytes[FQ_LENGTH..])?,
+    };
+
+    match flags {
+        GNARK_UNCOMPRESSED => {
+            let mut y_bytes = [0u8; 2 * FQ_LENGTH];
+            reader.read_exact(&mut y_bytes)?;
+            if bytes.iter().chain(y_bytes.iter()).all(|byte| *byte == 0) {
+                return Ok(G2Affine::identity());
+            }
+            let y = Fq2 {
+                c1: fq_from_be(&y_bytes[..FQ_LENGTH])?,
+                c0: fq_from_be(&y_bytes[FQ_LENGTH..])?,
+            };
+            point_from_xy(x, y)
+        }
+        GNARK_COMPRESSED_INFINITY => Ok(G2Affine::identity()),
+        _ => point_from_x(x, flags == GNARK_COMPRESSED_LARGEST, fq2_is_largest),
+    }
+}
+
+/// Writes a contribution as a gnark-crypto v0.11.2 BN254 kzg.SRS, as done by SRS.WriteRawTo, i.e. in order:
+/// - the verifying key: the generator of G2, the first power of tau in G2 and the generator of G1;
+/// - the proving key: a u32 big-endian length followed by the powers of tau in G1.
+///
+/// As with WriteRawTo, all points are uncompressed. Field elements are canonical and big-endian,
+/// with gnark flags in the most significant bits of x. G2 coordinates are written as c1 followed by c0
+pub fn write_gnark<V: ContributionView<Bn256>>(contribution: &V, filepath: &Path) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(filepath)?);
+    write_gnark_to(contribution, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_gnark_to<V: ContributionView<Bn256>, W: Write>(
+    contribution: &V,
+    writer: &mut W,
+) -> Result<()> {
+    let n = u32::try_from(contribution.n())?;
+
+    write_gnark_g2_raw(writer, contribution.g2())?;
+    write_gnark_g2_raw(writer, contribution.s_g2())?;
+    write_gnark_g1_raw(writer, &contribution.g_chunk(0, 1)[0])?;
+
+    writer.write_u32::<BigEndian>(n)?;
+    for (_, g) in g_chunks(contribution, G1_CHUNK_LENGTH) {
+        for point in g.iter() {
+            write_gnark_g1_raw(writer, point)?;
+        }
+    }
+    Ok(())
+}
+
+/// Reads a gnark-crypto v0.11.2 BN254 kzg.SRS, as specified in write_gnark.
+/// Points may be either raw or compressed, so files written by both SRS.WriteRawTo and SRS.WriteTo are accepted
+pub fn read_gnark(filepath: &Path) -> Result<ExportedSrs> {
+    read_gnark_from(&mut BufReader::new(File::open(filepath)?))
+}
+
+fn read_gnark_from<R: Read>(reader: &mut R) -> Result<ExportedSrs> {
+    let g2 = read_gnark_g2(reader)?;
+    let s_g2 = read_gnark_g2(reader)?;
+    let g1 = read_gnark_g1(reader)?;
+
+    let n = reader.read_u32::<BigEndian>()?;
+    let g = (0..n)
+        .map(|_| read_gnark_g1(reader))
+        .collect::<Result<Vec<G1Affine>>>()?;
+
+    if g.first() != Some(&g1) {
+        return Err(anyhow!(
+            "The verifying key G1 element does not match the first power of tau"
+        ));
+    }
+
+    Ok(ExportedSrs { g, g2, s_g2 })
+}
+
+/// Writes a contribution to filepath in the arkworks or gnark format
+pub fn write_params<V: ContributionView<Bn256>>(
+    contribution: &V,
+    filepath: &Path,
+    format: ParamsFormat,
+) -> Result<()> {
+    match format {
+        ParamsFormat::Arkworks => write_arkworks(contribution, filepath),
+        ParamsFormat::Gnark => write_gnark(contribution, filepath),
+        ParamsFormat::Halo2 => Err(anyhow!(
+            "halo2 parameters are written by finalize::finalize"
+        )),
+    }
+}
+
+/// Reads back the parameters written by write_params and checks that they match the contribution
+pub fn verify_params<V: ContributionView<Bn256>>(
+    contribution: &V,
+    filepath: &Path,
+    format: ParamsFormat,
+) -> Result<()> {
+    let srs = match format {
+        ParamsFormat::Arkworks => read_arkworks(filepath)?,
+        ParamsFormat::Gnark => read_gnark(filepath)?,
+        ParamsFormat::Halo2 => {
+            return Err(anyhow!(
+                "halo2 parameters are written by finalize::finalize"
+            ))
+        }
+    };
+
+    let g_matches = srs.g.len() == contribution.n()
+        && g_chunks(contribution, G1_CHUNK_LENGTH)
+            .all(|(start, g)| srs.g[start..start + g.len()] == *g);
+    if !g_matches || &srs.g2 != contribution.g2() || &srs.s_g2 != contribution.s_g2() {
+        return Err(anyhow!(
+            "The parameters read back from {:#?} do not match the contribution with id {}",
+            &filepath.display(),
+            contribution.id()
+        ));
+    }
+
+    info!(
+        "Parameters read back from {:#?} match the contribution with id {}",
+        &filepath.display(),
+        contribution.id()
+    );
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribution::KZGContribution;
+    use crate::proof::ContributionProof;
+    use halo2_proofs::halo2curves::bn256::Fr;
+    use rand::rngs::OsRng;
+
+    // Fixtures are computed from the format specifications, independently of this module, for the SRS
+    // with tau = 2 and k = 1, i.e. g = [G1, 2 G1], g2 = G2 and s_g2 = 2 G2
+
+    // gnark-crypto v0.11.2 SRS.WriteRawTo
+    const GNARK_RAW: &[&str] = &[
+        "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+        "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed",
+        "090689d0585ff075ec9e99ad690c3395bc4b313370b38ef355acdadcd122975b",
+        "12c85ea5db8c6deb4aab71808dcb408fe3d1e7690c43d37b4ce6cc0166fa7daa",
+        "203e205db4f19b37b60121b83a7333706db86431c6d835849957ed8c3928ad79",
+        "27dc7234fd11d3e8c36c59277c3e6f149d5cd3cfa9a62aee49f8130962b4b3b9",
+        "195e8aa5b7827463722b8c153931579d3505566b4edf48d498e185f0509de152",
+        "04bb53b8977e5f92a0bc372742c4830944a59b4fe6b1c0466e2a6dad122b5d2e",
+        "0000000000000000000000000000000000000000000000000000000000000001",
+        "0000000000000000000000000000000000000000000000000000000000000002",
+        "0000000200000000000000000000000000000000000000000000000000000000",
+        "0000000100000000000000000000000000000000000000000000000000000000",
+        "00000002030644e72e131a029b85045b68181585d97816a916871ca8d3c208c1",
+        "6d87cfd315ed738c0e0a7c92e7845f96b2ae9c0a68a6a449e3538fc7ff3ebf7a",
+        "5a18a2c4",
+    ];
+
+    // gnark-crypto v0.11.2 SRS.WriteTo
+    const GNARK_COMPRESSED: &[&str] = &[
+        "998e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+        "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed",
+        "e03e205db4f19b37b60121b83a7333706db86431c6d835849957ed8c3928ad79",
+        "27dc7234fd11d3e8c36c59277c3e6f149d5cd3cfa9a62aee49f8130962b4b3b9",
+        "8000000000000000000000000000000000000000000000000000000000000001",
+        "0000000280000000000000000000000000000000000000000000000000000000",
+        "00000001830644e72e131a029b85045b68181585d97816a916871ca8d3c208c1",
+        "6d87cfd3",
+    ];
+
+    // ark-poly-commit KZG10 UniversalParams, serialize_uncompressed
+    const ARKWORKS: &[&str] = &[
+        "0200000000000000010000000000000000000000000000000000000000000000",
+        "0000000000000000020000000000000000000000000000000000000000000000",
+        "0000000000000000d3cf876dc108c2d3a81c8716a91678d9851518685b04859b",
+        "021a132ee7440603c4a2185a7abf3effc78f53e349a4a6680a9caeb2965f84e7",
+        "927c0a0e8c73ed150000000000000000edf692d95cbdde46ddda5ef7d4224367",
+        "79445c5e66006a42761e1f12efde0018c212f3aeb785e49712e7a9353349aaf1",
+        "255dfb31b7bf60723a480d9293938e19aa7dfa6601cce64c7bd3430c69e7d1e3",
+        "8f40cb8d8071ab4aeb6d8cdba55ec8125b9722d1dcdaac55f38eb37033314bbc",
+        "95330c69ad999eec75f05f58d0890609b9b3b4620913f849ee2aa6a9cfd35c9d",
+        "146f3e7c27596cc3e8d311fd3472dc2779ad28398ced57998435d8c63164b86d",
+        "7033733ab82101b6379bf1b45d203e202e5d2b12ad6d2a6e46c0b1e64f9ba544",
+        "0983c4422737bca0925f7e97b853bb0452e19d50f085e198d448df4e6b560535",
+        "9d573139158c2b72637482b7a58a5e990000000000000000",
+    ];
+
+    fn fixture(hex_lines: &[&str]) -> Vec<u8> {
+        hex::decode(hex_lines.concat()).unwrap()
+    }
+
+    fn contribution(tau: Fr, k: u32) -> KZGContribution<Bn256> {
+        let mut g = Vec::with_capacity(1 << k);
+        let mut power = Fr::ONE;
+        for _ in 0..1 << k {
+            g.push((G1Affine::generator() * power).into());
+            power *= tau;
+        }
+        KZGContribution::from_parts(
+            k,
+            g,
+            G2Affine::generator(),
+            (G2Affine::generator() * tau).into(),
+            ContributionProof::default(),
+            1,
+        )
+    }
+
+    fn assert_matches(srs: &ExportedSrs, contribution: &KZGContribution<Bn256>) {
+        assert_eq!(&srs.g, contribution.g());
+        assert_eq!(&srs.g2, contribution.g2());
+        assert_eq!(&srs.s_g2, contribution.s_g2());
+    }
+
+    #[test]
+    fn gnark_writer_matches_fixture() {
+        let mut bytes = Vec::new();
+        write_gnark_to(&contribution(Fr::from(2), 1), &mut bytes).unwrap();
+        assert_eq!(bytes, fixture(GNARK_RAW));
+    }
+
+    #[test]
+    fn gnark_reader_accepts_raw_and_compressed_fixtures() {
+        let expected = contribution(Fr::from(2), 1);
+        for hex_lines in [GNARK_RAW, GNARK_COMPRESSED] {
+            let srs = read_gnark_from(&mut fixture(hex_lines).as_slice()).unwrap();
+            assert_matches(&srs, &expected);
+        }
+    }
+
+    #[test]
+    fn gnark_reader_rejects_mismatching_verifying_key() {
+        // We replace Vk.G1 with the second power of tau
+        let mut bytes = fixture(GNARK_RAW);
+        let (vk_g1, powers) = (4 * 2 * FQ_LENGTH, 4 * 2 * FQ_LENGTH + 2 * FQ_LENGTH + 4);
+        let second_power = bytes[powers + 2 * FQ_LENGTH..powers + 4 * FQ_LENGTH].to_vec();
+        bytes[vk_g1..vk_g1 + 2 * FQ_LENGTH].copy_from_slice(&second_power);
+        assert!(read_gnark_from(&mut bytes.as_slice()).is_err());
+    }
+
+    #[test]
+    fn arkworks_writer_matches_fixture() {
+        let mut bytes = Vec::new();
+        write_arkworks_to(&contribution(Fr::from(2), 1), &mut bytes).unwrap();
+        assert_eq!(bytes, fixture(ARKWORKS));
+
+        let srs = read_arkworks_from(&mut bytes.as_slice()).unwrap();
+        assert_matches(&srs, &contribution(Fr::from(2), 1));
+    }
+
+    #[test]
+    fn random_srs_round_trips() {
+        let expected = contribution(Fr::random(OsRng), 4);
+
+        let mut bytes = Vec::new();
+        write_gnark_to(&expected, &mut bytes).unwrap();
+        assert_matches(&read_gnark_from(&mut bytes.as_slice()).unwrap(), &expected);
+
+        let mut bytes = Vec::new();
+        write_arkworks_to(&expected, &mut bytes).unwrap();
+        assert_matches(
+            &read_arkworks_from(&mut bytes.as_slice()).unwrap(),
+            &expected,
+        );
+    }
+}
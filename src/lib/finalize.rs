@@ -1,16 +1,68 @@
-use crate::utils::get_last_contribution;
+use crate::config::CeremonyConfig;
+use crate::contribution::{SerdeCurveAffine, MAX_CONTRIBUTION_K, MIN_CONTRIBUTION_K};
+use crate::export::{verify_params, write_params, ParamsFormat};
+use crate::header::CurveIdentifier;
+use crate::ptau::write_ptau;
+use crate::utils::{get_last_contribution, HashingWriter};
 use anyhow::{anyhow, Result};
+use core::fmt::Debug;
+use halo2_proofs::arithmetic::CurveExt;
+use halo2_proofs::halo2curves::{bn256::Bn256, pairing::Engine, serde::SerdeObject};
 use halo2_proofs::poly::commitment::Params;
 use log::{info, warn};
 use std::{
     io::{BufWriter, Write},
+    ops::RangeInclusive,
     path::PathBuf,
 };
 
 const KZG_PARAMS_FILENAME: &str = "final";
 const KZG_PARAMS_EXTENSION: &str = "params";
+const DOWNSIZED_PARAMS_PREFIX: &str = "k_";
+const DOWNSIZED_PARAMS_EXTENSION: &str = "srs";
+const DOWNSIZED_PARAMS_MANIFEST: &str = "SHA256SUMS";
 
-pub fn finalize(contributions_path: String, params_filepath: Option<String>) -> Result<()> {
+/// Returns the filename of the parameters downsized to k
+pub fn downsized_params_filename(k: u32) -> String {
+    format!(
+        "{}{}.{}",
+        DOWNSIZED_PARAMS_PREFIX, k, DOWNSIZED_PARAMS_EXTENSION
+    )
+}
+
+/// Parses a range of k values, given either as a single k or as MIN..MAX (both included)
+pub fn parse_k_range(range: &str) -> Result<RangeInclusive<u32>> {
+    let (min_k, max_k) = match range.split_once("..") {
+        Some((min_k, max_k)) => (min_k.trim().parse::<u32>()?, max_k.trim().parse::<u32>()?),
+        None => {
+            let k = range.trim().parse::<u32>()?;
+            (k, k)
+        }
+    };
+
+    if min_k > max_k || min_k < MIN_CONTRIBUTION_K || max_k > MAX_CONTRIBUTION_K {
+        return Err(anyhow!(
+            "Invalid k range {}: expected MIN..MAX with {} <= MIN <= MAX <= {}",
+            range,
+            MIN_CONTRIBUTION_K,
+            MAX_CONTRIBUTION_K
+        ));
+    }
+
+    Ok(min_k..=max_k)
+}
+
+pub fn finalize<E: Engine + Debug + CurveIdentifier>(
+    contributions_path: String,
+    params_filepath: Option<String>,
+    config: &CeremonyConfig,
+) -> Result<PathBuf>
+where
+    E::G1Affine: SerdeCurveAffine<ScalarExt = <E as Engine>::Fr, CurveExt = <E as Engine>::G1>,
+    E::G1: CurveExt<AffineExt = E::G1Affine>,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: SerdeObject,
+{
     let contributions_path = PathBuf::from(&contributions_path);
 
     // We get the filepath where ParamsKZG will be written
@@ -32,7 +84,8 @@ pub fn finalize(contributions_path: String, params_filepath: Option<String>) ->
     }
 
     // We retrieve the last contribution from contributions_path
-    let last_contribution = get_last_contribution(&contributions_path)?;
+    let last_contribution = get_last_contribution::<E>(&contributions_path)?;
+    config.ensure_matches(&last_contribution)?;
 
     // We convert the last contribution to halo2 ParamsKZG
     let params = last_contribution.to_params();
@@ -49,5 +102,165 @@ pub fn finalize(contributions_path: String, params_filepath: Option<String>) ->
         &params_filepath.display()
     );
 
-    Ok(())
+    Ok(params_filepath)
+}
+
+/// Converts the last contribution to halo2 ParamsKZG downsized to each k in k_range, written as k_{k}.srs to params_path.
+/// The Lagrange basis of each parameters file is computed for its own size.
+/// A manifest listing the SHA256 of each file, in the sha256sum format, is written alongside.
+/// Returns the paths of the parameters files written
+pub fn finalize_k_range<E: Engine + Debug + CurveIdentifier>(
+    contributions_path: String,
+    params_path: Option<String>,
+    k_range: RangeInclusive<u32>,
+    config: &CeremonyConfig,
+) -> Result<Vec<PathBuf>>
+where
+    E::G1Affine: SerdeCurveAffine<ScalarExt = <E as Engine>::Fr, CurveExt = <E as Engine>::G1>,
+    E::G1: CurveExt<AffineExt = E::G1Affine>,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: SerdeObject,
+{
+    let contributions_path = PathBuf::from(&contributions_path);
+    let params_path = params_path.map_or_else(|| contributions_path.clone(), PathBuf::from);
+
+    if *k_range.end() > config.k() {
+        return Err(anyhow!(
+            "Cannot downsize parameters to k = {} since the ceremony k is {}",
+            k_range.end(),
+            config.k()
+        ));
+    }
+
+    if !params_path.is_dir() {
+        return Err(anyhow!(
+            "The output path {:#?} is not a directory",
+            &params_path.display()
+        ));
+    }
+
+    // If any output file already exists we return without any further expensive computation
+    let manifest_filepath = params_path.join(DOWNSIZED_PARAMS_MANIFEST);
+    let params_filepaths: Vec<(u32, PathBuf)> = k_range
+        .rev()
+        .map(|k| (k, params_path.join(downsized_params_filename(k))))
+        .collect();
+    for filepath in params_filepaths
+        .iter()
+        .map(|(_, filepath)| filepath)
+        .chain(std::iter::once(&manifest_filepath))
+    {
+        if filepath.is_file() {
+            return Err(anyhow!(
+                "Parameters will be written to {}, but this file already exists.",
+                &filepath.display()
+            ));
+        }
+    }
+
+    // We retrieve the last contribution from contributions_path
+    let last_contribution = get_last_contribution::<E>(&contributions_path)?;
+    config.ensure_matches(&last_contribution)?;
+
+    let mut manifest = String::new();
+    for (k, filepath) in params_filepaths.iter() {
+        // We convert the last contribution to halo2 ParamsKZG with 2^k points
+        let params = last_contribution.to_downsized_params(*k)?;
+
+        // We write the ParamsKZG to disk, hashing it on the fly
+        let fd_write = std::fs::File::create(filepath)?;
+        let mut hashing_writer = HashingWriter::new(BufWriter::new(fd_write));
+        params.write(&mut hashing_writer)?;
+        let sha256 = hashing_writer.finalize()?;
+
+        info!(
+            "ParamsKZG with k = {} written to {:#?}",
+            params.k(),
+            &filepath.display()
+        );
+
+        manifest.push_str(&format!(
+            "{}  {}\n",
+            hex::encode(sha256),
+            downsized_params_filename(*k)
+        ));
+    }
+
+    std::fs::write(&manifest_filepath, manifest)?;
+    info!(
+        "Parameters manifest written to {:#?}",
+        &manifest_filepath.display()
+    );
+
+    Ok(params_filepaths
+        .into_iter()
+        .map(|(_, filepath)| filepath)
+        .collect())
+}
+
+/// Exports the last contribution to a snarkjs .ptau file. See write_ptau for the sections written
+pub fn export_ptau(
+    contributions_path: String,
+    ptau_filepath: String,
+    config: &CeremonyConfig,
+) -> Result<PathBuf> {
+    let contributions_path = PathBuf::from(&contributions_path);
+    let ptau_filepath = PathBuf::from(ptau_filepath);
+
+    // We retrieve the last contribution from contributions_path
+    let last_contribution = get_last_contribution::<Bn256>(&contributions_path)?;
+    config.ensure_matches(&last_contribution)?;
+
+    write_ptau(&last_contribution, &ptau_filepath)?;
+
+    Ok(ptau_filepath)
+}
+
+/// Writes the last contribution in the arkworks or gnark format. See export::write_params for the layouts written.
+/// If verify is set, the written file is read back and checked against the last contribution
+pub fn finalize_export(
+    contributions_path: String,
+    params_filepath: Option<String>,
+    format: ParamsFormat,
+    verify: bool,
+    config: &CeremonyConfig,
+) -> Result<PathBuf> {
+    let contributions_path = PathBuf::from(&contributions_path);
+
+    // We get the filepath where parameters will be written
+    let params_filepath = if let Some(path) = params_filepath {
+        PathBuf::from(path)
+    } else {
+        let mut default_path = contributions_path.clone();
+        default_path.push(format!("{}.{}", KZG_PARAMS_FILENAME, format.extension()));
+        warn!("No custom filepath set for output parameters.");
+        default_path
+    };
+
+    // If the file already exists we return without any further expensive computation
+    if params_filepath.is_file() {
+        return Err(anyhow!(
+            "Parameters will be written to {}, but this file already exists.",
+            &params_filepath.display()
+        ));
+    }
+
+    // We retrieve the last contribution from contributions_path
+    let last_contribution = get_last_contribution::<Bn256>(&contributions_path)?;
+    config.ensure_matches(&last_contribution)?;
+
+    write_params(&last_contribution, &params_filepath, format)?;
+
+    info!(
+        "Parameters in the {:?} format with k = {} written to {:#?}",
+        format,
+        config.k(),
+        &params_filepath.display()
+    );
+
+    if verify {
+        verify_params(&last_contribution, &params_filepath, format)?;
+    }
+
+    Ok(params_filepath)
 }
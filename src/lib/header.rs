@@ -0,0 +1,215 @@
+use halo2_proofs::halo2curves::bn256::Bn256;
+use halo2_proofs::SerdeFormat;
+use serde::{Deserialize, Serialize};
+use std::io::{self, Error, ErrorKind};
+
+/// Magic bytes identifying a versioned contribution file
+pub const CONTRIBUTION_MAGIC: [u8; 4] = *b"CSRS";
+/// Current version of the contribution file format.
+/// Version 2 stores the contribution proof together with its version and the contributor metadata
+pub const CONTRIBUTION_FORMAT_VERSION: u16 = 2;
+/// Byte length of a serialized ContributionHeader
+pub const CONTRIBUTION_HEADER_LENGTH: usize = 32;
+/// Number of G2 points stored in a contribution (g2 and s_g2)
+pub const CONTRIBUTION_G2_COUNT: u64 = 2;
+
+/// Identifiers of the curves a contribution can be defined over, as written in contribution headers.
+/// All ceremony operations are generic over the pairing engine: a curve is added here once its engine
+/// implements the halo2curves traits used by halo2_proofs and CurveIdentifier
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CurveId {
+    Bn256,
+}
+
+impl CurveId {
+    pub fn to_byte(self) -> u8 {
+        match self {
+            CurveId::Bn256 => 1,
+        }
+    }
+
+    pub fn from_byte(byte: u8) -> io::Result<Self> {
+        match byte {
+            1 => Ok(CurveId::Bn256),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Unknown curve identifier {}", byte),
+            )),
+        }
+    }
+}
+
+/// Associates a pairing engine to the identifier written in contribution headers
+pub trait CurveIdentifier {
+    const CURVE_ID: CurveId;
+}
+
+impl CurveIdentifier for Bn256 {
+    const CURVE_ID: CurveId = CurveId::Bn256;
+}
+
+// Points are stored either compressed or uncompressed with coordinates in Montgomery form.
+// RawBytesUnchecked produces the same bytes as RawBytes, so both are encoded with the same tag
+fn format_to_byte(format: SerdeFormat) -> u8 {
+    match format {
+        SerdeFormat::Processed => 0,
+        SerdeFormat::RawBytes | SerdeFormat::RawBytesUnchecked => 1,
+    }
+}
+
+fn format_from_byte(byte: u8) -> io::Result<SerdeFormat> {
+    match byte {
+        0 => Ok(SerdeFormat::Processed),
+        1 => Ok(SerdeFormat::RawBytes),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unknown point encoding {}", byte),
+        )),
+    }
+}
+
+/// Self-describing header written at the beginning of a contribution file.
+///
+/// All integers are encoded little-endian:
+/// magic (4) | version (2) | curve (1) | point encoding (1) | k (4) | id (4) | g1 count (8) | g2 count (8)
+#[derive(Debug, Clone, Copy)]
+pub struct ContributionHeader {
+    pub version: u16,
+    pub curve: CurveId,
+    pub format: SerdeFormat,
+    pub k: u32,
+    pub id: u32,
+    pub g1_count: u64,
+    pub g2_count: u64,
+}
+
+impl ContributionHeader {
+    pub fn new(curve: CurveId, format: SerdeFormat, k: u32, id: u32) -> Self {
+        ContributionHeader {
+            version: CONTRIBUTION_FORMAT_VERSION,
+            curve,
+            format,
+            k,
+            id,
+            g1_count: 1 << k,
+            g2_count: CONTRIBUTION_G2_COUNT,
+        }
+    }
+
+    /// Returns the format to be used for reading points, given the format requested by the caller.
+    /// The point encoding is fixed by the header, while the caller can only opt out of checks
+    pub fn read_format(&self, requested: SerdeFormat) -> SerdeFormat {
+        match (self.format, requested) {
+            (SerdeFormat::Processed, _) => SerdeFormat::Processed,
+            (_, SerdeFormat::RawBytesUnchecked) => SerdeFormat::RawBytesUnchecked,
+            _ => SerdeFormat::RawBytes,
+        }
+    }
+
+    /// Writes the header to buffer
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&CONTRIBUTION_MAGIC)?;
+        writer.write_all(&self.version.to_le_bytes())?;
+        writer.write_all(&[self.curve.to_byte(), format_to_byte(self.format)])?;
+        writer.write_all(&self.k.to_le_bytes())?;
+        writer.write_all(&self.id.to_le_bytes())?;
+        writer.write_all(&self.g1_count.to_le_bytes())?;
+        writer.write_all(&self.g2_count.to_le_bytes())?;
+        Ok(())
+    }
+
+    /// Reads the header fields following the magic bytes
+    fn read_after_magic<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut bytes = [0u8; CONTRIBUTION_HEADER_LENGTH - CONTRIBUTION_MAGIC.len()];
+        reader.read_exact(&mut bytes)?;
+
+        let version = u16::from_le_bytes(bytes[0..2].try_into().unwrap());
+        if version == 0 || version > CONTRIBUTION_FORMAT_VERSION {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unsupported contribution format version {} (latest supported is {})",
+                    version, CONTRIBUTION_FORMAT_VERSION
+                ),
+            ));
+        }
+
+        let header = ContributionHeader {
+            version,
+            curve: CurveId::from_byte(bytes[2])?,
+            format: format_from_byte(bytes[3])?,
+            k: u32::from_le_bytes(bytes[4..8].try_into().unwrap()),
+            id: u32::from_le_bytes(bytes[8..12].try_into().unwrap()),
+            g1_count: u64::from_le_bytes(bytes[12..20].try_into().unwrap()),
+            g2_count: u64::from_le_bytes(bytes[20..28].try_into().unwrap()),
+        };
+
+        if header.k >= u64::BITS || header.g1_count != 1 << header.k {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Contribution header declares {} points in G1 but k is {}",
+                    header.g1_count, header.k
+                ),
+            ));
+        }
+
+        if header.g2_count != CONTRIBUTION_G2_COUNT {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Contribution header declares {} points in G2 but should be {}",
+                    header.g2_count, CONTRIBUTION_G2_COUNT
+                ),
+            ));
+        }
+
+        Ok(header)
+    }
+}
+
+/// The on-disk layout of a contribution, as detected from its first bytes
+#[derive(Debug, Clone, Copy)]
+pub enum ContributionLayout {
+    /// A file starting with a ContributionHeader
+    Versioned(ContributionHeader),
+    /// A file written before headers were introduced: it starts with k,
+    /// stores points as RawBytes and ends with the contribution id
+    Legacy { k: u32 },
+}
+
+impl ContributionLayout {
+    /// Reads the beginning of a contribution and detects its layout.
+    /// On return, the reader is positioned at the first point in G1
+    pub fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut first_bytes = [0u8; 4];
+        reader.read_exact(&mut first_bytes)?;
+
+        if first_bytes == CONTRIBUTION_MAGIC {
+            Ok(ContributionLayout::Versioned(
+                ContributionHeader::read_after_magic(reader)?,
+            ))
+        } else {
+            Ok(ContributionLayout::Legacy {
+                k: u32::from_le_bytes(first_bytes),
+            })
+        }
+    }
+
+    /// Returns the k declared by the contribution
+    pub fn k(&self) -> u32 {
+        match self {
+            ContributionLayout::Versioned(header) => header.k,
+            ContributionLayout::Legacy { k } => *k,
+        }
+    }
+
+    /// Returns true if the contribution proof is stored together with its version
+    pub fn has_versioned_proof(&self) -> bool {
+        match self {
+            ContributionLayout::Versioned(header) => header.version >= 2,
+            ContributionLayout::Legacy { .. } => false,
+        }
+    }
+}
@@ -0,0 +1,120 @@
+use anyhow::{anyhow, Result};
+use core::fmt::Debug;
+use halo2_proofs::halo2curves::{bn256::Bn256, pairing::Engine, serde::SerdeObject};
+use halo2_proofs::SerdeFormat;
+use serde::{Deserialize, Serialize};
+use std::{
+    io::{BufReader, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+use crate::attestation::Attestation;
+use crate::chunked::ContributionReader;
+use crate::config::PointEncoding;
+use crate::contribution::{points_length, SerdeCurveAffine};
+use crate::header::{ContributionLayout, CurveId, CONTRIBUTION_HEADER_LENGTH};
+use crate::proof::{ContributionProof, ProofVersion};
+use crate::utils::{hash_file, read_id};
+
+/// Layouts of contribution files
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LayoutKind {
+    Versioned,
+    Legacy,
+}
+
+/// Metadata of a contribution file, read without loading its points
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContributionInfo {
+    pub path: PathBuf,
+    /// SHA256 of the contribution file, hex encoded
+    pub sha256: String,
+    pub layout: LayoutKind,
+    /// The file format version, for versioned contributions
+    pub version: Option<u16>,
+    pub curve: CurveId,
+    pub encoding: PointEncoding,
+    pub k: u32,
+    pub id: u32,
+    pub proof_version: ProofVersion,
+    /// The contributor metadata bound to the contribution proof, lossily decoded as UTF-8
+    pub metadata: String,
+    pub attestation: Option<Attestation>,
+    /// The number of chunk files, for chunked contributions
+    #[serde(default)]
+    pub chunks: Option<usize>,
+}
+
+/// Reads the metadata of a contribution, stored in a single file or chunked. Points are skipped and not checked
+pub fn inspect_contribution(filepath: &Path) -> Result<ContributionInfo> {
+    let mut reader = BufReader::new(ContributionReader::open(filepath)?);
+    let chunks = match reader.get_ref() {
+        ContributionReader::Chunked(chunked) => Some(chunked.chunk_count()),
+        ContributionReader::File(_) => None,
+    };
+    let layout = ContributionLayout::read(&mut reader)?;
+
+    let (layout_kind, version, curve, format, header_length) = match layout {
+        ContributionLayout::Versioned(header) => (
+            LayoutKind::Versioned,
+            Some(header.version),
+            header.curve,
+            header.format,
+            CONTRIBUTION_HEADER_LENGTH,
+        ),
+        // Legacy contributions are defined over BN256 and start with k
+        ContributionLayout::Legacy { .. } => (
+            LayoutKind::Legacy,
+            None,
+            CurveId::Bn256,
+            SerdeFormat::RawBytes,
+            4,
+        ),
+    };
+
+    let proof_offset = header_length as u64
+        + match curve {
+            CurveId::Bn256 => points_length::<Bn256>(layout.k(), format),
+        };
+    reader.seek(SeekFrom::Start(proof_offset))?;
+
+    let (proof_version, metadata) = match curve {
+        CurveId::Bn256 => read_proof_info::<Bn256, _>(&mut reader, &layout)?,
+    };
+
+    let filepath = filepath.to_path_buf();
+    Ok(ContributionInfo {
+        sha256: hash_file(&filepath)?,
+        layout: layout_kind,
+        version,
+        curve,
+        encoding: PointEncoding::from_format(format),
+        k: layout.k(),
+        id: read_id(&filepath)?,
+        proof_version,
+        metadata: String::from_utf8_lossy(&metadata).into_owned(),
+        attestation: Attestation::read(&filepath)?,
+        chunks,
+        path: filepath,
+    })
+}
+
+// Reads the version and the metadata of a contribution proof
+fn read_proof_info<E: Engine + Debug, R: std::io::Read>(
+    reader: &mut R,
+    layout: &ContributionLayout,
+) -> Result<(ProofVersion, Vec<u8>)>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::Fr: SerdeObject,
+{
+    let proof = if layout.has_versioned_proof() {
+        ContributionProof::<E>::read_versioned(reader)
+    } else {
+        ContributionProof::<E>::read(reader)
+    }
+    .map_err(|e| anyhow!("Cannot read contribution proof: {}", e))?;
+
+    Ok((proof.version(), proof.metadata().to_vec()))
+}
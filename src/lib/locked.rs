@@ -0,0 +1,172 @@
+use log::{debug, warn};
+#[cfg(target_os = "linux")]
+use memmap2::Advice;
+use memmap2::MmapMut;
+use std::{
+    mem,
+    ops::{Deref, DerefMut},
+    ptr::{self, NonNull},
+    sync::{
+        atomic::{compiler_fence, AtomicBool, Ordering},
+        Mutex, OnceLock,
+    },
+};
+
+// The arena holds 128 slots of 512 bytes: enough for the secrets, the hasher states and one power accumulator
+// per thread, while fitting the default locked memory limit of most systems
+const ARENA_SIZE: usize = 64 * 1024;
+const SLOT_SIZE: usize = 512;
+// The arena is page aligned, so slots are aligned to their size
+const SLOT_ALIGN: usize = SLOT_SIZE;
+
+static ARENA: OnceLock<Option<SecretArena>> = OnceLock::new();
+static FALLBACK_WARNED: AtomicBool = AtomicBool::new(false);
+
+// An anonymous memory region, locked in RAM and excluded from core dumps where supported, split in fixed-size slots
+struct SecretArena {
+    _region: MmapMut,
+    base: usize,
+    free_slots: Mutex<Vec<usize>>,
+}
+
+impl SecretArena {
+    fn new() -> Option<Self> {
+        let mut region = match MmapMut::map_anon(ARENA_SIZE) {
+            Ok(region) => region,
+            Err(e) => {
+                warn!("Cannot allocate memory for secrets ({}): secrets are stored in regular memory and may be written to swap", e);
+                return None;
+            }
+        };
+        lock_region(&region);
+
+        let base = region.as_mut_ptr() as usize;
+        Some(SecretArena {
+            _region: region,
+            base,
+            free_slots: Mutex::new((0..ARENA_SIZE / SLOT_SIZE).rev().collect()),
+        })
+    }
+
+    fn allocate(&self) -> Option<usize> {
+        self.free_slots.lock().unwrap().pop()
+    }
+
+    fn release(&self, slot: usize) {
+        self.free_slots.lock().unwrap().push(slot);
+    }
+
+    fn slot_ptr(&self, slot: usize) -> *mut u8 {
+        (self.base + slot * SLOT_SIZE) as *mut u8
+    }
+}
+
+// Locks the region in RAM, so it is never written to swap, and excludes it from core dumps
+#[cfg(unix)]
+fn lock_region(region: &MmapMut) {
+    match region.lock() {
+        Ok(()) => debug!("Secrets are stored in {} bytes of locked memory", ARENA_SIZE),
+        Err(e) => warn!("Cannot lock the memory storing secrets ({}): secrets may be written to swap. Consider raising the locked memory limit (e.g. ulimit -l) or disabling swap while contributing", e),
+    }
+
+    #[cfg(target_os = "linux")]
+    if let Err(e) = region.advise(Advice::DontDump) {
+        warn!(
+            "Cannot exclude the memory storing secrets from core dumps ({})",
+            e
+        );
+    }
+}
+
+#[cfg(not(unix))]
+fn lock_region(_region: &MmapMut) {
+    warn!("Locking memory is not supported on this platform: secrets may be written to swap. Consider disabling swap while contributing");
+}
+
+fn arena() -> Option<&'static SecretArena> {
+    ARENA.get_or_init(SecretArena::new).as_ref()
+}
+
+/// A value stored in the locked secret arena and zeroed when dropped.
+/// If the value does not fit a slot or the arena is exhausted, it is stored on the heap and a warning is logged
+pub struct Locked<T> {
+    ptr: NonNull<T>,
+    slot: Option<usize>,
+}
+
+// Safety: Locked<T> uniquely owns its value, as a Box<T> does
+unsafe impl<T: Send> Send for Locked<T> {}
+unsafe impl<T: Sync> Sync for Locked<T> {}
+
+impl<T> Locked<T> {
+    pub fn new(value: T) -> Self {
+        let fits = mem::size_of::<T>() <= SLOT_SIZE && mem::align_of::<T>() <= SLOT_ALIGN;
+        let arena_slot = arena()
+            .filter(|_| fits)
+            .and_then(|arena| arena.allocate().map(|slot| (arena, slot)));
+
+        match arena_slot {
+            Some((arena, slot)) => {
+                let ptr = arena.slot_ptr(slot) as *mut T;
+                // Safety: the slot is free, and large and aligned enough to hold a T
+                unsafe { ptr.write(value) };
+                Locked {
+                    ptr: NonNull::new(ptr).unwrap(),
+                    slot: Some(slot),
+                }
+            }
+            None => {
+                if !FALLBACK_WARNED.swap(true, Ordering::Relaxed) {
+                    warn!("Secrets do not fit in locked memory: some of them are stored in regular memory and may be written to swap");
+                }
+                Locked {
+                    ptr: NonNull::new(Box::into_raw(Box::new(value))).unwrap(),
+                    slot: None,
+                }
+            }
+        }
+    }
+
+    /// Returns true if the value is stored in the secret arena
+    pub fn is_in_arena(&self) -> bool {
+        self.slot.is_some()
+    }
+}
+
+impl<T> Deref for Locked<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        // Safety: ptr points to an initialized T owned by self
+        unsafe { self.ptr.as_ref() }
+    }
+}
+
+impl<T> DerefMut for Locked<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        // Safety: ptr points to an initialized T owned by self
+        unsafe { self.ptr.as_mut() }
+    }
+}
+
+impl<T> Drop for Locked<T> {
+    fn drop(&mut self) {
+        let ptr = self.ptr.as_ptr();
+        // Safety: ptr points to an initialized T owned by self, which is dropped and then zeroed.
+        // Volatile writes prevent zeroing from being optimized away
+        unsafe {
+            ptr::drop_in_place(ptr);
+            let bytes = ptr as *mut u8;
+            for i in 0..mem::size_of::<T>() {
+                ptr::write_volatile(bytes.add(i), 0u8);
+            }
+        }
+        compiler_fence(Ordering::SeqCst);
+
+        match self.slot {
+            Some(slot) => arena().unwrap().release(slot),
+            // Safety: ptr was allocated as a Box<T>, whose value was already dropped
+            None => unsafe { drop(Box::from_raw(ptr as *mut mem::MaybeUninit<T>)) },
+        }
+    }
+}
@@ -0,0 +1,426 @@
+use anyhow::{anyhow, Result};
+use core::fmt::Debug;
+use halo2_proofs::arithmetic::{parallelize, CurveExt};
+use halo2_proofs::halo2curves::{pairing::Engine, serde::SerdeObject};
+use halo2_proofs::poly::kzg::commitment::ParamsKZG;
+use halo2_proofs::SerdeFormat;
+use log::{info, warn};
+use memmap2::Mmap;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, Error, ErrorKind};
+use std::marker::PhantomData;
+use std::path::PathBuf;
+
+use crate::contribution::{
+    is_supported_k, params_from_parts, ContributionView, KZGContribution, SerdeCurveAffine,
+};
+use crate::header::{ContributionLayout, CurveIdentifier, CONTRIBUTION_HEADER_LENGTH};
+use crate::proof::ContributionProof;
+
+// Number of points validated by each parallel task
+const VALIDATION_CHUNK_LEN: usize = 1 << 16;
+
+/// A contribution whose G1 points are read from a memory-mapped file.
+///
+/// Only versioned contributions storing uncompressed points (RawBytes) can be mapped.
+/// Points are validated straight from the mapped bytes, and decoded chunk by chunk each time they are accessed
+/// through `g_chunk`, so that they are never all held in memory unless converted to halo2 ParamsKZG.
+/// Points are not checked when the file is mapped: call `validate` before trusting them.
+pub struct MappedContribution<E: Engine> {
+    mmap: Mmap,
+    k: u32,
+    n: usize,
+    s_g: E::G1Affine,
+    g2: E::G2Affine,
+    s_g2: E::G2Affine,
+    proof: ContributionProof<E>,
+    id: u32,
+    _engine: PhantomData<E>,
+}
+
+impl<E: Engine + Debug + CurveIdentifier> MappedContribution<E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: SerdeObject,
+{
+    /// Maps a contribution file in memory
+    pub fn open(filepath: &PathBuf) -> io::Result<Self> {
+        if filepath.is_dir() {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Chunked contributions cannot be memory-mapped",
+            ));
+        }
+
+        let file = File::open(filepath)?;
+        // Safety: the mapping is read-only. Contribution files are not expected to be modified while being read
+        let mmap = unsafe { Mmap::map(&file)? };
+
+        let mut bytes: &[u8] = &mmap;
+        let layout = ContributionLayout::read(&mut bytes)?;
+        let header = match layout {
+            ContributionLayout::Versioned(header) => header,
+            ContributionLayout::Legacy { .. } => {
+                return Err(Error::new(
+                    ErrorKind::Unsupported,
+                    "Legacy contributions cannot be memory-mapped",
+                ))
+            }
+        };
+
+        if header.curve != E::CURVE_ID {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Contribution is defined over {:?} but {:?} was expected",
+                    header.curve,
+                    E::CURVE_ID
+                ),
+            ));
+        }
+
+        if !matches!(header.format, SerdeFormat::RawBytes) {
+            return Err(Error::new(
+                ErrorKind::Unsupported,
+                "Only contributions with uncompressed points can be memory-mapped",
+            ));
+        }
+
+        if !is_supported_k(header.k) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Contribution read has unsupported k {}", header.k),
+            ));
+        }
+
+        let n: usize = 1 << header.k;
+        let g_length = n * E::G1Affine::byte_length(SerdeFormat::RawBytes);
+        if bytes.len() < g_length {
+            return Err(Error::new(
+                ErrorKind::UnexpectedEof,
+                "Contribution file is shorter than declared in its header",
+            ));
+        }
+
+        let point_length = E::G1Affine::byte_length(SerdeFormat::RawBytes);
+        let s_g = E::G1Affine::from_raw_bytes_unchecked(&bytes[point_length..2 * point_length]);
+
+        let mut tail = &bytes[g_length..];
+        let g2 = <E::G2Affine as SerdeCurveAffine>::read(&mut tail, SerdeFormat::RawBytes)?;
+        let s_g2 = <E::G2Affine as SerdeCurveAffine>::read(&mut tail, SerdeFormat::RawBytes)?;
+        let proof = if layout.has_versioned_proof() {
+            ContributionProof::read_versioned(&mut tail)?
+        } else {
+            ContributionProof::read(&mut tail)?
+        };
+
+        Ok(MappedContribution {
+            mmap,
+            k: header.k,
+            n,
+            s_g,
+            g2,
+            s_g2,
+            proof,
+            id: header.id,
+            _engine: PhantomData,
+        })
+    }
+
+    // Returns the bytes encoding the points in G1
+    fn g_bytes(&self) -> &[u8] {
+        let point_length = E::G1Affine::byte_length(SerdeFormat::RawBytes);
+        &self.mmap[CONTRIBUTION_HEADER_LENGTH..CONTRIBUTION_HEADER_LENGTH + self.n * point_length]
+    }
+
+    // Decodes len points in G1 starting from index start from the mapped bytes into an owned buffer.
+    // Points are decoded unchecked: validate() checks them from the mapped bytes
+    fn decode_g(&self, start: usize, len: usize) -> Vec<E::G1Affine> {
+        let point_length = E::G1Affine::byte_length(SerdeFormat::RawBytes);
+        let g_bytes = &self.g_bytes()[start * point_length..(start + len) * point_length];
+        let mut g = vec![E::G1Affine::default(); len];
+        parallelize(&mut g, |g, start| {
+            let bytes = &g_bytes[start * point_length..(start + g.len()) * point_length];
+            for (point, point_bytes) in g.iter_mut().zip(bytes.chunks_exact(point_length)) {
+                *point = E::G1Affine::from_raw_bytes_unchecked(point_bytes);
+            }
+        });
+        g
+    }
+
+    /// Checks that all coordinates are canonical field elements and that all points are on the curve
+    pub fn validate(&self) -> io::Result<()> {
+        info!("Validating memory-mapped contribution with id {}", self.id);
+
+        let point_length = E::G1Affine::byte_length(SerdeFormat::RawBytes);
+        let g_bytes = self.g_bytes();
+        let n = self.n;
+
+        let mut chunks_validity = vec![false; n.div_ceil(VALIDATION_CHUNK_LEN)];
+        parallelize(&mut chunks_validity, |chunks_validity, start| {
+            for (i, chunk_validity) in chunks_validity.iter_mut().enumerate() {
+                let chunk_start = (start + i) * VALIDATION_CHUNK_LEN;
+                let chunk_end = (chunk_start + VALIDATION_CHUNK_LEN).min(n);
+                *chunk_validity = g_bytes[chunk_start * point_length..chunk_end * point_length]
+                    .chunks_exact(point_length)
+                    .all(|point_bytes| E::G1Affine::from_raw_bytes(point_bytes).is_some());
+            }
+        });
+
+        if !chunks_validity.iter().all(|&valid| valid) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Contribution contains one or more points in G1 not on the curve",
+            ));
+        }
+
+        if !Into::<bool>::into(self.proof.p().is_on_curve()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Contribution proof's point p is not on the curve",
+            ));
+        }
+
+        if !Into::<bool>::into(self.g2.is_on_curve()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Contribution has point g2 not on the curve",
+            ));
+        }
+
+        if !Into::<bool>::into(self.s_g2.is_on_curve()) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Contribution has point s_g2 not on the curve",
+            ));
+        }
+
+        Ok(())
+    }
+
+    /// Converts the contribution to halo2 ParamsKZG, decoding all its points from the mapped file at once
+    pub fn to_params(self) -> ParamsKZG<E>
+    where
+        E::G1Affine: SerdeCurveAffine<ScalarExt = <E as Engine>::Fr, CurveExt = <E as Engine>::G1>,
+        E::G1: CurveExt<AffineExt = E::G1Affine>,
+    {
+        let g = self.decode_g(0, self.n);
+        params_from_parts(self.id, self.k, g, self.g2, self.s_g2)
+    }
+}
+
+impl<E: Engine + Debug + CurveIdentifier> ContributionView<E> for MappedContribution<E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: SerdeObject,
+{
+    fn k(&self) -> u32 {
+        self.k
+    }
+
+    fn n(&self) -> usize {
+        self.n
+    }
+
+    fn g_chunk(&self, start: usize, len: usize) -> Cow<'_, [E::G1Affine]> {
+        Cow::Owned(self.decode_g(start, len))
+    }
+
+    fn g2(&self) -> &E::G2Affine {
+        &self.g2
+    }
+
+    fn s_g2(&self) -> &E::G2Affine {
+        &self.s_g2
+    }
+
+    fn proof(&self) -> &ContributionProof<E> {
+        &self.proof
+    }
+
+    fn id(&self) -> u32 {
+        self.id
+    }
+
+    fn s_g(&self) -> &E::G1Affine {
+        &self.s_g
+    }
+}
+
+/// A contribution either memory-mapped or, when mapping is not possible, fully loaded in memory
+pub enum LoadedContribution<E: Engine> {
+    Mapped(MappedContribution<E>),
+    InMemory(KZGContribution<E>),
+}
+
+impl<E: Engine + Debug + CurveIdentifier> LoadedContribution<E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: SerdeObject,
+{
+    /// Opens and validates a contribution, memory-mapping it whenever possible
+    pub fn open(filepath: &PathBuf) -> io::Result<Self> {
+        match MappedContribution::open(filepath) {
+            Ok(contribution) => {
+                info!("Contribution {:#?} memory-mapped", &filepath.display());
+                contribution.validate()?;
+                Ok(LoadedContribution::Mapped(contribution))
+            }
+            Err(e) if e.kind() == ErrorKind::Unsupported => {
+                warn!(
+                    "Contribution {:#?} cannot be memory-mapped ({}): loading it in memory",
+                    &filepath.display(),
+                    e
+                );
+                Ok(LoadedContribution::InMemory(KZGContribution::from(
+                    filepath,
+                )?))
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Converts the contribution to halo2 ParamsKZG
+    pub fn to_params(self) -> ParamsKZG<E>
+    where
+        E::G1Affine: SerdeCurveAffine<ScalarExt = <E as Engine>::Fr, CurveExt = <E as Engine>::G1>,
+        E::G1: CurveExt<AffineExt = E::G1Affine>,
+    {
+        match self {
+            LoadedContribution::Mapped(contribution) => contribution.to_params(),
+            LoadedContribution::InMemory(contribution) => contribution.to_params(),
+        }
+    }
+
+    /// Converts the contribution downsized to 2^k points to halo2 ParamsKZG, computing the Lagrange basis for size 2^k.
+    /// Returns an error if k exceeds the contribution's k
+    pub fn to_downsized_params(&self, k: u32) -> Result<ParamsKZG<E>>
+    where
+        E::G1Affine: SerdeCurveAffine<ScalarExt = <E as Engine>::Fr, CurveExt = <E as Engine>::G1>,
+        E::G1: CurveExt<AffineExt = E::G1Affine>,
+    {
+        if k > self.k() {
+            return Err(anyhow!(
+                "Cannot downsize the contribution with id {} and k {} to the larger k {}",
+                self.id(),
+                self.k(),
+                k
+            ));
+        }
+        Ok(params_from_parts(
+            self.id(),
+            k,
+            self.g_chunk(0, 1 << k).into_owned(),
+            *self.g2(),
+            *self.s_g2(),
+        ))
+    }
+
+    fn view(&self) -> &dyn ContributionView<E> {
+        match self {
+            LoadedContribution::Mapped(contribution) => contribution,
+            LoadedContribution::InMemory(contribution) => contribution,
+        }
+    }
+}
+
+impl<E: Engine + Debug + CurveIdentifier> ContributionView<E> for LoadedContribution<E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: SerdeObject,
+{
+    fn k(&self) -> u32 {
+        self.view().k()
+    }
+
+    fn n(&self) -> usize {
+        self.view().n()
+    }
+
+    fn g_chunk(&self, start: usize, len: usize) -> Cow<'_, [E::G1Affine]> {
+        self.view().g_chunk(start, len)
+    }
+
+    fn g2(&self) -> &E::G2Affine {
+        self.view().g2()
+    }
+
+    fn s_g2(&self) -> &E::G2Affine {
+        self.view().s_g2()
+    }
+
+    fn proof(&self) -> &ContributionProof<E> {
+        self.view().proof()
+    }
+
+    fn id(&self) -> u32 {
+        self.view().id()
+    }
+
+    fn s_g(&self) -> &E::G1Affine {
+        self.view().s_g()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribution::g_chunks;
+    use crate::utils::ScratchDir;
+    use group::{ff::Field, prime::PrimeCurveAffine};
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine, G2Affine};
+    use halo2_proofs::poly::commitment::Params;
+
+    #[test]
+    fn mapped_chunks_match_contribution() {
+        let scratch = ScratchDir::new("mapped_chunks");
+        let k = 5;
+        let tau = Fr::from(7);
+        let mut power = Fr::ONE;
+        let g = (0..1 << k)
+            .map(|_| {
+                let point = (G1Affine::generator() * power).into();
+                power *= tau;
+                point
+            })
+            .collect();
+        let contribution = KZGContribution::<Bn256>::from_parts(
+            k,
+            g,
+            G2Affine::generator(),
+            (G2Affine::generator() * tau).into(),
+            ContributionProof::default(),
+            1,
+        );
+        let filepath = scratch.0.join("contribution.csrs");
+        contribution.write(&filepath).unwrap();
+
+        let mapped = MappedContribution::<Bn256>::open(&filepath).unwrap();
+        mapped.validate().unwrap();
+        assert_eq!(mapped.s_g(), contribution.s_g());
+        for (start, chunk) in g_chunks(&mapped, 3) {
+            assert_eq!(*chunk, contribution.g()[start..start + chunk.len()]);
+        }
+
+        let (mut mapped_params, mut params) = (vec![], vec![]);
+        mapped.to_params().write(&mut mapped_params).unwrap();
+        contribution.to_params().write(&mut params).unwrap();
+        assert_eq!(mapped_params, params);
+    }
+
+    #[test]
+    fn downsizing_to_larger_k_fails() {
+        let scratch = ScratchDir::new("mapped_downsize");
+        let filepath = scratch.0.join("contribution.csrs");
+        KZGContribution::<Bn256>::trivial(3).write(&filepath).unwrap();
+
+        let contribution = LoadedContribution::<Bn256>::open(&filepath).unwrap();
+        assert!(contribution.to_downsized_params(2).is_ok());
+        assert!(contribution.to_downsized_params(4).is_err());
+    }
+}
@@ -1,8 +1,23 @@
+pub mod attestation;
+pub mod beacon;
+pub mod cache;
 pub mod check;
+pub mod checkpoint;
+pub mod chunked;
+pub mod config;
 pub mod contribute;
 pub mod contribution;
+pub mod export;
 pub mod finalize;
+pub mod header;
+pub mod inspect;
+pub mod locked;
+pub mod mapped;
 pub mod ppot;
+pub mod progress;
 pub mod proof;
+pub mod ptau;
+pub mod report;
 pub mod secrets;
+pub mod shares;
 pub mod utils;
@@ -1,7 +1,8 @@
 use anyhow::{anyhow, Result};
 use blake2::{Blake2b512, Digest};
-use byteorder::{BigEndian, ReadBytesExt};
-use halo2_proofs::halo2curves::bn256::{Bn256, Fq, Fq2, G1Affine, G2Affine};
+use group::{ff::Field, prime::PrimeCurveAffine, Group};
+use halo2_proofs::arithmetic::{parallelize, CurveAffine};
+use halo2_proofs::halo2curves::bn256::{Bn256, Fq2, Fr, G1Affine, G2Affine};
 use log::info;
 use std::{
     fs::File,
@@ -10,48 +11,195 @@ use std::{
 };
 
 use super::contribution::KZGContribution;
-use crate::{
-    contribution::{CONTRIBUTION_K, CONTRIBUTION_N},
-    proof::ContributionProof,
+use crate::check::check_srs;
+use crate::contribution::ContributionView;
+use crate::export::{
+    fq2_is_largest, fq_from_be, fq_is_largest, point_from_x, point_from_xy, FQ_LENGTH,
 };
+use crate::progress::Progress;
+use crate::{config::CeremonyConfig, proof::ContributionProof};
 use std::fmt::Write;
 
-fn read_fq(reader: &mut BufReader<File>) -> Result<Fq> {
-    let mut g1_raw: [u64; 4] = [0; 4];
-    for i in 0..4 {
-        g1_raw[3 - i] = reader.read_u64::<BigEndian>()?;
+// Flag of uncompressed points at infinity in PPoT challenge files, stored in the first byte
+const UNCOMPRESSED_INFINITY: u8 = 1 << 6;
+
+// Number of points read and converted at once
+const PPOT_BLOCK_LENGTH: usize = 1 << 20;
+
+// Decodes an uncompressed point from its big-endian coordinates, checking that it is on the curve
+fn decode_uncompressed<C: CurveAffine>(
+    bytes: &[u8],
+    read_coordinate: impl Fn(&[u8]) -> Result<C::Base>,
+) -> Result<C> {
+    if bytes[0] & UNCOMPRESSED_INFINITY != 0 {
+        if bytes[0] != UNCOMPRESSED_INFINITY || bytes[1..].iter().any(|byte| *byte != 0) {
+            return Err(anyhow!("Invalid encoding of the point at infinity"));
+        }
+        return Ok(C::identity());
     }
-    Ok(Fq::from_raw(g1_raw))
+
+    let half = bytes.len() / 2;
+    point_from_xy(
+        read_coordinate(&bytes[..half])?,
+        read_coordinate(&bytes[half..])?,
+    )
 }
 
-fn read_g1(reader: &mut BufReader<File>) -> Result<G1Affine> {
-    Ok(G1Affine {
-        x: read_fq(reader)?,
-        y: read_fq(reader)?,
-    })
+// Uncompressed G1 points store x followed by y
+fn decode_g1(bytes: &[u8]) -> Result<G1Affine> {
+    decode_uncompressed(bytes, fq_from_be)
+}
+
+// Uncompressed G2 points store x.c1, x.c0, y.c1 and y.c0
+fn decode_g2(bytes: &[u8]) -> Result<G2Affine> {
+    decode_uncompressed(bytes, read_fq2)
 }
 
-fn read_g2(reader: &mut BufReader<File>) -> Result<G2Affine> {
-    Ok(G2Affine {
-        x: Fq2 {
-            c1: read_fq(reader)?,
-            c0: read_fq(reader)?,
-        },
-        y: Fq2 {
-            c1: read_fq(reader)?,
-            c0: read_fq(reader)?,
-        },
+// Reads a big-endian Fq2 element stored as c1 followed by c0
+fn read_fq2(bytes: &[u8]) -> Result<Fq2> {
+    Ok(Fq2 {
+        c1: fq_from_be(&bytes[..FQ_LENGTH])?,
+        c0: fq_from_be(&bytes[FQ_LENGTH..])?,
     })
 }
 
+// Reads len consecutive points of point_length bytes each. Points are read in blocks,
+// and the points of each block are decoded in parallel. Progress is reported after each block
+fn read_ppot_points<C: CurveAffine, R: Read>(
+    reader: &mut R,
+    len: usize,
+    point_length: usize,
+    decode: impl Fn(&[u8]) -> Result<C> + Sync,
+    description: &str,
+) -> Result<Vec<C>> {
+    let mut points: Vec<C> = Vec::with_capacity(len);
+    let mut block_buffer = vec![0u8; PPOT_BLOCK_LENGTH.min(len) * point_length];
+    let progress = Progress::start(&format!("Reading {}", description), len);
+
+    while points.len() < len {
+        let block_length = PPOT_BLOCK_LENGTH.min(len - points.len());
+        reader.read_exact(&mut block_buffer[..block_length * point_length])?;
+        let block_bytes = &block_buffer[..block_length * point_length];
+
+        let mut block: Vec<Option<C>> = vec![None; block_length];
+        parallelize(&mut block, |block, start| {
+            for (i, point) in block.iter_mut().enumerate() {
+                let offset = (start + i) * point_length;
+                *point = decode(&block_bytes[offset..offset + point_length]).ok();
+            }
+        });
+
+        for point in block {
+            let point = point.ok_or_else(|| {
+                anyhow!(
+                    "Element {} of {} is not a valid point",
+                    points.len(),
+                    description
+                )
+            })?;
+            points.push(point);
+        }
+
+        info!(
+            "Read {} / {} elements from {} ({}%)",
+            points.len(),
+            len,
+            description,
+            points.len() * 100 / len
+        );
+        progress.advance(block_length);
+    }
+    progress.finish();
+
+    Ok(points)
+}
+
+// Flags of compressed points in PPoT response files, stored in the two most significant bits of the first byte
+const COMPRESSED_INFINITY: u8 = 1 << 6;
+const COMPRESSED_Y_LARGEST: u8 = 1 << 7;
+
+// Decompresses a point from its big-endian x coordinate, prefixed by the compression flags
+fn decompress<C: CurveAffine>(
+    bytes: &[u8],
+    read_x: impl Fn(&[u8]) -> Result<C::Base>,
+    is_largest: impl Fn(&C::Base) -> bool,
+) -> Result<C> {
+    let mut x_bytes = bytes.to_vec();
+    let flags = x_bytes[0] & (COMPRESSED_INFINITY | COMPRESSED_Y_LARGEST);
+    x_bytes[0] &= !flags;
+
+    if flags & COMPRESSED_INFINITY != 0 {
+        if x_bytes.iter().any(|byte| *byte != 0) {
+            return Err(anyhow!("Invalid encoding of the point at infinity"));
+        }
+        return Ok(C::identity());
+    }
+
+    point_from_x(
+        read_x(&x_bytes)?,
+        flags & COMPRESSED_Y_LARGEST != 0,
+        is_largest,
+    )
+}
+
+// Compressed G1 points store x
+fn decompress_g1(bytes: &[u8]) -> Result<G1Affine> {
+    decompress(bytes, fq_from_be, fq_is_largest)
+}
+
+// Compressed G2 points store x.c1 followed by x.c0
+fn decompress_g2(bytes: &[u8]) -> Result<G2Affine> {
+    decompress(bytes, read_fq2, fq2_is_largest)
+}
+
+// Returns true if a G2 point lies in the prime order subgroup, i.e. if r * point is the identity.
+// We compute (r - 1) * point by multiplying by -1 in the scalar field, whose bits are those of r - 1
+fn is_in_g2_subgroup(point: &G2Affine) -> bool {
+    let point = point.to_curve();
+    bool::from((point * -Fr::ONE + point).is_identity())
+}
+
+// Returns the hex-encoded Blake2b hash of a PPoT file, as computed by PPoT
+fn hash_ppot_file(reader: &mut BufReader<File>) -> Result<String> {
+    let mut hasher = Blake2b512::new();
+    // The progress of hashing is reported in bytes
+    let progress = Progress::start(
+        "Hashing the PPOT file",
+        reader.get_ref().metadata()?.len() as usize,
+    );
+
+    // We read the file in chunks of at most 1 GiB
+    let mut file_buffer = vec![0u8; 1024 * 1024 * 1024];
+    loop {
+        let bytes_read = reader.read(&mut file_buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&file_buffer[..bytes_read]);
+        progress.advance(bytes_read);
+    }
+    progress.finish();
+
+    let mut ppot_hash: [u8; 64] = [0; 64];
+    hasher.finalize_into((&mut ppot_hash).into());
+
+    let mut hex_hash = String::with_capacity(ppot_hash.len() * 2);
+    for byte in ppot_hash {
+        write!(hex_hash, "{:02x}", byte)?;
+    }
+
+    Ok(hex_hash)
+}
+
 // This utility reads a challenge file generated for ppot and converts it to a set of KZG Parameters encoded using halo2curves::Bn256
 pub fn read_challenge(
     challenge_path: &str,
     challenge_k: u32,
     hash_challenge: bool,
+    config: &CeremonyConfig,
 ) -> Result<KZGContribution<Bn256>> {
-    if CONTRIBUTION_K > challenge_k {
-        return Err(anyhow!("Ceremony k is {} but the input PPOT challenge has been read with k = {}. It is not possible to create a starting srs for the set ceremony k from the PPOT challenge", CONTRIBUTION_K, challenge_k));
+    if config.k() > challenge_k {
+        return Err(anyhow!("Ceremony k is {} but the input PPOT challenge has been read with k = {}. It is not possible to create a starting srs for the set ceremony k from the PPOT challenge", config.k(), challenge_k));
     }
 
     let ppot_challenge_path = PathBuf::from(challenge_path);
@@ -62,40 +210,20 @@ pub fn read_challenge(
         ));
     }
 
-    let mut ppot_challenge = File::open(ppot_challenge_path)?;
-    let mut reader = BufReader::new(ppot_challenge.try_clone()?);
+    let mut reader = BufReader::new(File::open(ppot_challenge_path)?);
 
     // If enabled, we hash the input challenge
     if hash_challenge {
-        let mut hasher = Blake2b512::new();
-
-        // We read the file in chunks of at most 1 GiB
-        let mut file_buffer = vec![0u8; 1024 * 1024 * 1024];
-        loop {
-            let bytes_read = reader.read(&mut file_buffer)?;
-            if bytes_read == 0 {
-                break;
-            }
-            hasher.update(&file_buffer[..bytes_read]);
-        }
-
-        let mut ppot_challenge_hash: [u8; 64] = [0; 64];
-        hasher.finalize_into((&mut ppot_challenge_hash).into());
-
-        let mut hex_hash = String::with_capacity(ppot_challenge_hash.len() * 2);
-        for byte in ppot_challenge_hash {
-            write!(hex_hash, "{:02x}", byte)?;
-        }
-
+        let hex_hash = hash_ppot_file(&mut reader)?;
         info!("The PPOT Challenge hash is {:#?}", hex_hash);
 
         // We seek back to the beginning of file
-        ppot_challenge.seek(SeekFrom::Start(0))?;
+        reader.seek(SeekFrom::Start(0))?;
     }
 
     // We read the PPOT Challenge hash
     let mut ppot_challenge_hash: [u8; 64] = [0; 64];
-    ppot_challenge.read_exact(&mut ppot_challenge_hash)?;
+    reader.read_exact(&mut ppot_challenge_hash)?;
 
     let mut hex_hash = String::with_capacity(ppot_challenge_hash.len() * 2);
     for byte in ppot_challenge_hash {
@@ -107,30 +235,107 @@ pub fn read_challenge(
         hex_hash
     );
 
-    // We read the elements in G1
-    let mut g: Vec<G1Affine> = Vec::with_capacity(CONTRIBUTION_N);
-    for _ in 0..CONTRIBUTION_N {
-        g.push(read_g1(&mut reader)?);
-    }
-
-    info!("Read {:#?} elements from TauG1", g.len());
+    // We read the elements in G1, converting them in parallel
+    let g = read_ppot_points(&mut reader, config.n(), 2 * FQ_LENGTH, decode_g1, "TauG1")?;
 
     // Challenge's TauG1 consists of 2^{k+1}-1 elements
     let challenge_taug1_length: u64 = (1 << (challenge_k + 1)) - 1;
 
     // Considering the initial 64 bytes hash, TauG2 starts at byte offset 64 + 64*challenge_taug1_length
     let start: u64 = 64 + 64 * challenge_taug1_length;
-    ppot_challenge.seek(SeekFrom::Start(start))?;
-    let mut reader = BufReader::new(ppot_challenge.try_clone()?);
+    reader.seek(SeekFrom::Start(start))?;
 
     // We read the elements in G2
-    let g2 = read_g2(&mut reader)?;
-    let s_g2 = read_g2(&mut reader)?;
+    let g2 = read_ppot_points(&mut reader, 2, 4 * FQ_LENGTH, decode_g2, "TauG2")?;
+
+    Ok(KZGContribution::<Bn256>::from_parts(
+        config.k(),
+        g,
+        g2[0],
+        g2[1],
+        ContributionProof::default(),
+        0,
+    ))
+}
+
+// This utility reads a compressed response file generated for ppot and converts it to a set of KZG Parameters encoded using halo2curves::Bn256.
+// G1 has cofactor 1, so decompressed G1 points, being on the curve, lie in the prime order subgroup; G2 points are explicitly checked
+pub fn read_response(
+    response_path: &str,
+    response_k: u32,
+    hash_response: bool,
+    config: &CeremonyConfig,
+) -> Result<KZGContribution<Bn256>> {
+    if config.k() > response_k {
+        return Err(anyhow!("Ceremony k is {} but the input PPOT response has been read with k = {}. It is not possible to create a starting srs for the set ceremony k from the PPOT response", config.k(), response_k));
+    }
+
+    let ppot_response_path = PathBuf::from(response_path);
+    if !ppot_response_path.is_file() {
+        return Err(anyhow!(
+            "The response path {:#?} is not a file",
+            response_path
+        ));
+    }
+
+    let mut reader = BufReader::new(File::open(ppot_response_path)?);
+
+    // If enabled, we hash the input response
+    if hash_response {
+        let hex_hash = hash_ppot_file(&mut reader)?;
+        info!("The PPOT Response hash is {:#?}", hex_hash);
+
+        // We seek back to the beginning of file
+        reader.seek(SeekFrom::Start(0))?;
+    }
+
+    // We read the PPOT Challenge hash the response has been computed from
+    let mut ppot_challenge_hash: [u8; 64] = [0; 64];
+    reader.read_exact(&mut ppot_challenge_hash)?;
+
+    let mut hex_hash = String::with_capacity(ppot_challenge_hash.len() * 2);
+    for byte in ppot_challenge_hash {
+        write!(hex_hash, "{:02x}", byte)?;
+    }
+
+    info!(
+        "The reported challenge hash in PPOT Response is {:#?}",
+        hex_hash
+    );
+
+    // We read the compressed elements in G1, decompressing them in parallel
+    let g = read_ppot_points(&mut reader, config.n(), FQ_LENGTH, decompress_g1, "TauG1")?;
+
+    // Response's TauG1 consists of 2^{k+1}-1 compressed elements
+    let response_taug1_length: u64 = (1 << (response_k + 1)) - 1;
+
+    // Considering the initial 64 bytes hash, TauG2 starts at byte offset 64 + 32*response_taug1_length
+    let start: u64 = 64 + FQ_LENGTH as u64 * response_taug1_length;
+    reader.seek(SeekFrom::Start(start))?;
+
+    // We read the compressed elements in G2
+    let mut g2_bytes = [0u8; 4 * FQ_LENGTH];
+    reader.read_exact(&mut g2_bytes)?;
+    let g2 = decompress_g2(&g2_bytes[..2 * FQ_LENGTH])?;
+    let s_g2 = decompress_g2(&g2_bytes[2 * FQ_LENGTH..])?;
+
+    if !is_in_g2_subgroup(&g2) || !is_in_g2_subgroup(&s_g2) {
+        return Err(anyhow!(
+            "The PPOT Response has elements of TauG2 not in the G2 subgroup"
+        ));
+    }
 
     info!("Read 2 elements from TauG2");
 
+    // The first powers of tau are the generators
+    if g[0] != G1Affine::generator() || g2 != G2Affine::generator() {
+        return Err(anyhow!(
+            "The PPOT Response first powers of tau are not the generators of G1 and G2"
+        ));
+    }
+
     Ok(KZGContribution::<Bn256>::from_parts(
-        CONTRIBUTION_K,
+        config.k(),
         g,
         g2,
         s_g2,
@@ -138,3 +343,159 @@ pub fn read_challenge(
         0,
     ))
 }
+
+/// Expected hashes of a PPoT challenge or response file, hex encoded
+#[derive(Debug, Clone, Default)]
+pub struct PpotProvenance {
+    /// The Blake2b hash of the whole file, as published in the PPoT transcript
+    pub file_hash: Option<String>,
+    /// The hash embedded in the first 64 bytes of the file, i.e. the hash of the previous response for a challenge,
+    /// or of the challenge the response has been computed from
+    pub embedded_hash: Option<String>,
+}
+
+// Decodes a hex-encoded Blake2b hash
+fn decode_blake2b(hash: &str) -> Result<[u8; 64]> {
+    hex::decode(hash.trim_start_matches("0x"))?
+        .try_into()
+        .map_err(|_| anyhow!("{:#?} is not a valid Blake2b hash", hash))
+}
+
+/// Checks the hashes of a PPoT challenge or response file against their expected values
+pub fn verify_ppot_provenance(ppot_path: &str, provenance: &PpotProvenance) -> Result<()> {
+    let mut reader = BufReader::new(File::open(ppot_path)?);
+
+    if let Some(expected_hash) = &provenance.embedded_hash {
+        let mut embedded_hash: [u8; 64] = [0; 64];
+        reader.read_exact(&mut embedded_hash)?;
+        if embedded_hash != decode_blake2b(expected_hash)? {
+            return Err(anyhow!(
+                "The PPOT file embeds the hash {:#?} but {:#?} was expected",
+                hex::encode(embedded_hash),
+                expected_hash
+            ));
+        }
+        info!(
+            "The PPOT file embeds the expected hash {:#?}",
+            expected_hash
+        );
+        reader.seek(SeekFrom::Start(0))?;
+    }
+
+    if let Some(expected_hash) = &provenance.file_hash {
+        let file_hash = hash_ppot_file(&mut reader)?;
+        if hex::decode(&file_hash)? != decode_blake2b(expected_hash)? {
+            return Err(anyhow!(
+                "The PPOT file hash is {:#?} but {:#?} was expected",
+                file_hash,
+                expected_hash
+            ));
+        }
+        info!("The PPOT file has the expected hash {:#?}", file_hash);
+    }
+
+    Ok(())
+}
+
+/// Reads a PPoT challenge or response file after checking its provenance, and checks the extracted SRS
+/// so that it can be used as the first contribution
+pub fn read_verified_ppot(
+    ppot_path: &str,
+    ppot_k: u32,
+    is_response: bool,
+    hash_ppot: bool,
+    provenance: &PpotProvenance,
+    config: &CeremonyConfig,
+) -> Result<KZGContribution<Bn256>> {
+    verify_ppot_provenance(ppot_path, provenance)?;
+
+    // The file has been already hashed if its hash was checked
+    let hash_ppot = hash_ppot && provenance.file_hash.is_none();
+    let contribution = if is_response {
+        read_response(ppot_path, ppot_k, hash_ppot, config)?
+    } else {
+        read_challenge(ppot_path, ppot_k, hash_ppot, config)?
+    };
+
+    info!("Checking the SRS extracted from the PPOT file");
+    let report = check_srs::<Bn256, _>(&contribution, config.n())?;
+    if !report.is_valid() {
+        return Err(anyhow!("The SRS extracted from the PPOT file is not valid"));
+    }
+
+    Ok(contribution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The generator of G1 is (1, 2), and 2 is not the largest of 2 and -2
+    const G1_GENERATOR_COMPRESSED: &str =
+        "0000000000000000000000000000000000000000000000000000000000000001";
+    // x.c1 and x.c0 of the generator of G2, whose y is not the largest of y and -y
+    const G2_GENERATOR_COMPRESSED: &str = concat!(
+        "198e9393920d483a7260bfb731fb5d25f1aa493335a9e71297e485b7aef312c2",
+        "1800deef121f1e76426a00665e5c4479674322d4f75edadd46debd5cd992f6ed"
+    );
+
+    fn flagged(compressed: &str, flags: u8) -> Vec<u8> {
+        let mut bytes = hex::decode(compressed).unwrap();
+        bytes[0] |= flags;
+        bytes
+    }
+
+    #[test]
+    fn g1_decompression() {
+        let generator = G1Affine::generator();
+        assert_eq!(
+            decompress_g1(&flagged(G1_GENERATOR_COMPRESSED, 0)).unwrap(),
+            generator
+        );
+        assert_eq!(
+            decompress_g1(&flagged(G1_GENERATOR_COMPRESSED, COMPRESSED_Y_LARGEST)).unwrap(),
+            -generator
+        );
+
+        let mut infinity = vec![0u8; FQ_LENGTH];
+        infinity[0] = COMPRESSED_INFINITY;
+        assert_eq!(decompress_g1(&infinity).unwrap(), G1Affine::identity());
+        infinity[FQ_LENGTH - 1] = 1;
+        assert!(decompress_g1(&infinity).is_err());
+    }
+
+    #[test]
+    fn g2_decompression() {
+        let generator = G2Affine::generator();
+        assert_eq!(
+            decompress_g2(&flagged(G2_GENERATOR_COMPRESSED, 0)).unwrap(),
+            generator
+        );
+        assert_eq!(
+            decompress_g2(&flagged(G2_GENERATOR_COMPRESSED, COMPRESSED_Y_LARGEST)).unwrap(),
+            -generator
+        );
+        assert!(is_in_g2_subgroup(&generator));
+
+        let mut infinity = vec![0u8; 2 * FQ_LENGTH];
+        infinity[0] = COMPRESSED_INFINITY;
+        assert_eq!(decompress_g2(&infinity).unwrap(), G2Affine::identity());
+        infinity[2 * FQ_LENGTH - 1] = 1;
+        assert!(decompress_g2(&infinity).is_err());
+    }
+
+    #[test]
+    fn g2_points_outside_the_subgroup_are_rejected() {
+        // We take the first point on the twist with x = (i, 0): points on the twist are almost never in the
+        // prime order subgroup, whose index is the cofactor of about 2^254
+        let point = (1u8..)
+            .find_map(|i| {
+                let mut bytes = vec![0u8; 2 * FQ_LENGTH];
+                bytes[2 * FQ_LENGTH - 1] = i;
+                decompress_g2(&bytes).ok()
+            })
+            .unwrap();
+        assert!(bool::from(point.is_on_curve()));
+        assert!(!is_in_g2_subgroup(&point));
+    }
+}
@@ -0,0 +1,297 @@
+use anyhow::anyhow;
+use serde::Serialize;
+use std::{
+    fmt,
+    io::{self, Write},
+    str::FromStr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, OnceLock,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+// Long-running loops report their progress every PROGRESS_STEP processed items
+pub const PROGRESS_STEP: usize = 1 << 16;
+// Progress updates of a phase are emitted at most once per interval, in addition to its start and its end
+const REPORT_INTERVAL: Duration = Duration::from_secs(1);
+const BAR_WIDTH: usize = 40;
+
+static SINK: OnceLock<Box<dyn ProgressSink>> = OnceLock::new();
+static NO_PROGRESS: NoProgress = NoProgress;
+
+/// A snapshot of the progress of a long-running phase
+#[derive(Debug, Clone, Serialize)]
+pub struct ProgressUpdate<'a> {
+    /// The name of the phase, e.g. "Rescaling powers of tau"
+    pub phase: &'a str,
+    /// The number of items processed so far
+    pub done: u64,
+    /// The total number of items of the phase
+    pub total: u64,
+    /// Seconds elapsed since the phase started
+    pub elapsed: f64,
+    /// Processed items per second
+    pub throughput: f64,
+    /// Estimated seconds remaining, when it can be estimated
+    pub eta: Option<f64>,
+    /// True once the phase is completed
+    pub finished: bool,
+}
+
+/// A destination for progress updates of long-running phases
+pub trait ProgressSink: Send + Sync {
+    fn update(&self, update: &ProgressUpdate);
+}
+
+/// Discards all progress updates
+pub struct NoProgress;
+
+impl ProgressSink for NoProgress {
+    fn update(&self, _update: &ProgressUpdate) {}
+}
+
+/// Draws a progress bar with throughput and ETA on stderr
+pub struct TerminalProgress {
+    // The length of the last drawn line, so that shorter lines fully overwrite it
+    last_length: Mutex<usize>,
+}
+
+impl TerminalProgress {
+    pub fn new() -> Self {
+        TerminalProgress {
+            last_length: Mutex::new(0),
+        }
+    }
+}
+
+impl Default for TerminalProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressSink for TerminalProgress {
+    fn update(&self, update: &ProgressUpdate) {
+        let ratio = if update.total == 0 {
+            1.0
+        } else {
+            update.done as f64 / update.total as f64
+        };
+        let filled = ((ratio * BAR_WIDTH as f64) as usize).min(BAR_WIDTH);
+        let eta = match (update.finished, update.eta) {
+            (true, _) => format!("done in {}", format_duration(update.elapsed)),
+            (false, Some(eta)) => format!("ETA {}", format_duration(eta)),
+            (false, None) => "ETA --".to_string(),
+        };
+        let line = format!(
+            "{} [{}{}] {}/{} ({:.1}%) {:.0}/s {}",
+            update.phase,
+            "#".repeat(filled),
+            "-".repeat(BAR_WIDTH - filled),
+            update.done,
+            update.total,
+            ratio * 100.0,
+            update.throughput,
+            eta
+        );
+
+        let mut last_length = self.last_length.lock().unwrap();
+        let padding = " ".repeat(last_length.saturating_sub(line.len()));
+        let mut stderr = io::stderr().lock();
+        let _ = write!(stderr, "\r{}{}", line, padding);
+        if update.finished {
+            let _ = writeln!(stderr);
+            *last_length = 0;
+        } else {
+            *last_length = line.len();
+        }
+        let _ = stderr.flush();
+    }
+}
+
+/// Emits each progress update as a JSON object on its own line, to stderr or to any writer
+pub struct JsonLinesProgress {
+    writer: Mutex<Box<dyn Write + Send>>,
+}
+
+impl JsonLinesProgress {
+    pub fn new(writer: Box<dyn Write + Send>) -> Self {
+        JsonLinesProgress {
+            writer: Mutex::new(writer),
+        }
+    }
+
+    pub fn stderr() -> Self {
+        Self::new(Box::new(io::stderr()))
+    }
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    // Unix time in seconds
+    timestamp: f64,
+    #[serde(flatten)]
+    update: &'a ProgressUpdate<'a>,
+}
+
+impl ProgressSink for JsonLinesProgress {
+    fn update(&self, update: &ProgressUpdate) {
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|t| t.as_secs_f64())
+            .unwrap_or_default();
+        if let Ok(line) = serde_json::to_string(&JsonLine { timestamp, update }) {
+            let mut writer = self.writer.lock().unwrap();
+            let _ = writeln!(writer, "{}", line);
+            let _ = writer.flush();
+        }
+    }
+}
+
+/// The progress reporting styles selectable from the command line
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProgressStyle {
+    /// A progress bar on stderr
+    Bar,
+    /// JSON lines on stderr
+    Json,
+    /// No progress reporting
+    #[default]
+    None,
+}
+
+impl ProgressStyle {
+    /// Names of the progress styles, as accepted by `from_str`
+    pub const NAMES: [&'static str; 3] = ["bar", "json", "none"];
+
+    /// Installs the corresponding sink as the global progress sink
+    pub fn install(self) {
+        match self {
+            ProgressStyle::Bar => set_progress_sink(Box::new(TerminalProgress::new())),
+            ProgressStyle::Json => set_progress_sink(Box::new(JsonLinesProgress::stderr())),
+            ProgressStyle::None => {}
+        }
+    }
+}
+
+impl fmt::Display for ProgressStyle {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProgressStyle::Bar => write!(f, "bar"),
+            ProgressStyle::Json => write!(f, "json"),
+            ProgressStyle::None => write!(f, "none"),
+        }
+    }
+}
+
+impl FromStr for ProgressStyle {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bar" => Ok(ProgressStyle::Bar),
+            "json" => Ok(ProgressStyle::Json),
+            "none" => Ok(ProgressStyle::None),
+            _ => Err(anyhow!(
+                "Unknown progress style {:?}, the available styles are: {}",
+                s,
+                ProgressStyle::NAMES.join(", ")
+            )),
+        }
+    }
+}
+
+/// Sets the sink receiving the progress of all phases. As for loggers, it can be set only once:
+/// later calls are ignored. Until a sink is set, progress is not reported
+pub fn set_progress_sink(sink: Box<dyn ProgressSink>) {
+    let _ = SINK.set(sink);
+}
+
+fn sink() -> &'static dyn ProgressSink {
+    SINK.get().map(|sink| sink.as_ref()).unwrap_or(&NO_PROGRESS)
+}
+
+/// Tracks the progress of a phase processing a known number of items and reports it to the global sink.
+/// Items can be advanced concurrently, e.g. from the closures passed to parallelize
+pub struct Progress {
+    phase: String,
+    total: u64,
+    done: AtomicU64,
+    started: Instant,
+    // Milliseconds since the start of the phase at which the last update was emitted
+    last_report: AtomicU64,
+}
+
+impl Progress {
+    /// Starts a phase and reports it
+    pub fn start(phase: &str, total: usize) -> Self {
+        let progress = Progress {
+            phase: phase.to_string(),
+            total: total as u64,
+            done: AtomicU64::new(0),
+            started: Instant::now(),
+            last_report: AtomicU64::new(0),
+        };
+        progress.report(0, false);
+        progress
+    }
+
+    /// Records that items were processed, reporting the progress if the last update is old enough
+    pub fn advance(&self, items: usize) {
+        let done = self.done.fetch_add(items as u64, Ordering::Relaxed) + items as u64;
+        let now = self.started.elapsed().as_millis() as u64;
+        let last = self.last_report.load(Ordering::Relaxed);
+        // Only one of the threads advancing concurrently emits the update
+        if now >= last + REPORT_INTERVAL.as_millis() as u64
+            && self
+                .last_report
+                .compare_exchange(last, now, Ordering::Relaxed, Ordering::Relaxed)
+                .is_ok()
+        {
+            self.report(done, false);
+        }
+    }
+
+    /// Completes the phase and reports it
+    pub fn finish(self) {
+        self.report(self.total.max(self.done.load(Ordering::Relaxed)), true);
+    }
+
+    fn report(&self, done: u64, finished: bool) {
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let throughput = if elapsed > 0.0 {
+            done as f64 / elapsed
+        } else {
+            0.0
+        };
+        let eta = if finished {
+            Some(0.0)
+        } else if throughput > 0.0 {
+            Some(self.total.saturating_sub(done) as f64 / throughput)
+        } else {
+            None
+        };
+
+        sink().update(&ProgressUpdate {
+            phase: &self.phase,
+            done,
+            total: self.total,
+            elapsed,
+            throughput,
+            eta,
+            finished,
+        });
+    }
+}
+
+// Formats seconds as h:mm:ss
+fn format_duration(seconds: f64) -> String {
+    let seconds = seconds.round() as u64;
+    format!(
+        "{}:{:02}:{:02}",
+        seconds / 3600,
+        (seconds / 60) % 60,
+        seconds % 60
+    )
+}
@@ -3,25 +3,75 @@ use crate::secrets::Secret;
 use blake2::{Blake2b512, Digest};
 use group::ff::{Field, FromUniformBytes};
 use group::prime::PrimeCurveAffine;
-use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine};
 use halo2_proofs::halo2curves::{pairing::Engine, serde::SerdeObject};
 use halo2_proofs::SerdeFormat;
 use log::{error, info};
+use serde::{Deserialize, Serialize};
 use std::fmt::Debug;
-use std::io;
+use std::io::{self, Error, ErrorKind};
+
+/// Domain separation tag for version 2 contribution proofs
+const PROOF_V2_DOMAIN: &[u8] = b"zircuit-ceremony/contribution-proof/v2";
+
+/// Maximum byte length of the contributor metadata bound to a contribution proof
+pub const MAX_PROOF_METADATA_LENGTH: usize = 1024;
+
+/// Versions of the contribution proof
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofVersion {
+    /// The challenge commits to s_g, the previous s_g and p only
+    V1,
+    /// The challenge also commits to a domain separation tag, the SHA256 of the previous contribution file,
+    /// the new contribution id and the contributor metadata
+    V2,
+}
+
+impl ProofVersion {
+    fn to_byte(self) -> u8 {
+        match self {
+            ProofVersion::V1 => 1,
+            ProofVersion::V2 => 2,
+        }
+    }
+
+    fn from_byte(byte: u8) -> io::Result<Self> {
+        match byte {
+            1 => Ok(ProofVersion::V1),
+            2 => Ok(ProofVersion::V2),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Unknown contribution proof version {}", byte),
+            )),
+        }
+    }
+}
+
+/// The data, besides s_g and the previous s_g, a version 2 contribution proof is bound to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProofContext {
+    /// SHA256 of the previous contribution file
+    pub prev_sha256: [u8; 32],
+    /// Id of the new contribution
+    pub id: u32,
+}
 
 /// KZG public parameters for ceremony contributors
 #[derive(Debug, Clone)]
 pub struct ContributionProof<E: Engine> {
+    version: ProofVersion,
     p: E::G1Affine,
     r: E::Fr,
+    metadata: Vec<u8>,
 }
 
 impl<E: Engine + Debug> Default for ContributionProof<E> {
     fn default() -> Self {
         Self {
+            version: ProofVersion::V1,
             p: <E::G1Affine as PrimeCurveAffine>::identity(),
             r: <E::Fr as Field>::ZERO,
+            metadata: Vec::new(),
         }
     }
 }
@@ -34,7 +84,16 @@ where
 {
     // Computes a zero knowledge proof that the contributor knows secret
     pub fn new(p: E::G1Affine, r: E::Fr) -> Self {
-        ContributionProof { p, r }
+        ContributionProof {
+            version: ProofVersion::V1,
+            p,
+            r,
+            metadata: Vec::new(),
+        }
+    }
+
+    pub fn version(&self) -> ProofVersion {
+        self.version
     }
 
     pub fn p(&self) -> &E::G1Affine {
@@ -45,67 +104,325 @@ where
         &self.r
     }
 
-    /// Writes contribution proof to buffer
+    /// Returns the contributor metadata bound to the proof (always empty for version 1 proofs)
+    pub fn metadata(&self) -> &[u8] {
+        &self.metadata
+    }
+
+    /// Returns true for the null proof carried by the first contribution of a chain, which has no predecessor
+    pub fn is_null(&self) -> bool {
+        self.version == ProofVersion::V1
+            && bool::from(self.p.is_identity())
+            && bool::from(self.r.is_zero())
+            && self.metadata.is_empty()
+    }
+
+    /// Writes contribution proof to buffer, using the layout of legacy and version 1 contribution files.
+    /// Only version 1 proofs can be written with this layout
     pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        if self.version != ProofVersion::V1 {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "Only version 1 contribution proofs can be written without a version",
+            ));
+        }
         self.p.write(writer, SerdeFormat::RawBytes)?;
         self.r.write_raw(writer)?;
         Ok(())
     }
 
-    /// Reads contribution proof from a buffer.
+    /// Reads contribution proof from a buffer, using the layout of legacy and version 1 contribution files.
     pub fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
         let p = <E::G1Affine as SerdeCurveAffine>::read(reader, SerdeFormat::RawBytes)?;
         let r = <E::Fr as SerdeObject>::read_raw(reader)?;
-        Ok(ContributionProof { p, r })
+        Ok(ContributionProof::new(p, r))
+    }
+
+    /// Writes contribution proof to buffer, preceded by its version and followed by the contributor metadata
+    pub fn write_versioned<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[self.version.to_byte()])?;
+        self.p.write(writer, SerdeFormat::RawBytes)?;
+        self.r.write_raw(writer)?;
+        writer.write_all(&(self.metadata.len() as u32).to_le_bytes())?;
+        writer.write_all(&self.metadata)?;
+        Ok(())
+    }
+
+    /// Reads a contribution proof written with `write_versioned`.
+    /// Version 1 proofs are not bound to the previous contribution file, so the only version 1 proof
+    /// accepted in this layout is the null proof of the first contribution
+    pub fn read_versioned<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut version = [0u8; 1];
+        reader.read_exact(&mut version)?;
+        let version = ProofVersion::from_byte(version[0])?;
+
+        let p = <E::G1Affine as SerdeCurveAffine>::read(reader, SerdeFormat::RawBytes)?;
+        let r = <E::Fr as SerdeObject>::read_raw(reader)?;
+
+        let mut metadata_length = [0u8; 4];
+        reader.read_exact(&mut metadata_length)?;
+        let metadata_length = u32::from_le_bytes(metadata_length) as usize;
+        if metadata_length > MAX_PROOF_METADATA_LENGTH {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Contribution proof metadata has length {} but at most {} bytes are allowed",
+                    metadata_length, MAX_PROOF_METADATA_LENGTH
+                ),
+            ));
+        }
+        let mut metadata = vec![0u8; metadata_length];
+        reader.read_exact(&mut metadata)?;
+
+        let proof = ContributionProof {
+            version,
+            p,
+            r,
+            metadata,
+        };
+        if proof.version == ProofVersion::V1 && !proof.is_null() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                "Version 1 contribution proofs are only allowed in legacy and version 1 contribution files",
+            ));
+        }
+        Ok(proof)
     }
 }
 
-// Creates a zero-knowledge proof that the contributor knows the secret employed during the contribution phase
-pub fn create_contribution_proof(
-    g: &G1Affine,
-    s: &Secret<Bn256>,
-    z: &Secret<Bn256>,
-) -> ContributionProof<Bn256> {
-    let s_g: G1Affine = (g * s.get()).into();
-    let p: G1Affine = (g * z.get()).into();
+// Computes the Fiat-Shamir challenge of a version 1 contribution proof
+fn challenge_v1<E: Engine>(s_g: &E::G1Affine, prev_s_g: &E::G1Affine, p: &E::G1Affine) -> E::Fr
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64>,
+{
+    let mut hasher = Blake2b512::new();
+    hasher.update(s_g.to_raw_bytes());
+    hasher.update(prev_s_g.to_raw_bytes());
+    hasher.update(p.to_raw_bytes());
+    E::Fr::from_uniform_bytes(hasher.finalize().as_ref())
+}
 
+// Computes the Fiat-Shamir challenge of a version 2 contribution proof.
+// Variable-length inputs are length-prefixed so that distinct inputs cannot produce the same transcript
+fn challenge_v2<E: Engine>(
+    s_g: &E::G1Affine,
+    prev_s_g: &E::G1Affine,
+    p: &E::G1Affine,
+    context: &ProofContext,
+    metadata: &[u8],
+) -> E::Fr
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64>,
+{
     let mut hasher = Blake2b512::new();
+    hasher.update((PROOF_V2_DOMAIN.len() as u64).to_le_bytes());
+    hasher.update(PROOF_V2_DOMAIN);
+    hasher.update(context.prev_sha256);
+    hasher.update(context.id.to_le_bytes());
+    hasher.update((metadata.len() as u64).to_le_bytes());
+    hasher.update(metadata);
     hasher.update(s_g.to_raw_bytes());
-    hasher.update(g.to_raw_bytes());
+    hasher.update(prev_s_g.to_raw_bytes());
     hasher.update(p.to_raw_bytes());
-    let h = Fr::from_uniform_bytes(hasher.finalize().as_ref());
+    E::Fr::from_uniform_bytes(hasher.finalize().as_ref())
+}
 
-    let r = z.get() + h * s.get();
+// Computes the challenge of a contribution proof according to its version
+fn challenge<E: Engine + Debug>(
+    s_g: &E::G1Affine,
+    prev_s_g: &E::G1Affine,
+    proof: &ContributionProof<E>,
+    context: &ProofContext,
+) -> E::Fr
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
+    match proof.version() {
+        ProofVersion::V1 => challenge_v1::<E>(s_g, prev_s_g, proof.p()),
+        ProofVersion::V2 => challenge_v2::<E>(s_g, prev_s_g, proof.p(), context, proof.metadata()),
+    }
+}
+
+// Creates a zero-knowledge proof that the contributor knows the secret employed during the contribution phase.
+// The proof is bound to the previous contribution file, the new contribution id and the contributor metadata
+pub fn create_contribution_proof<E: Engine + Debug>(
+    g: &E::G1Affine,
+    s: &Secret<E>,
+    z: &Secret<E>,
+    context: &ProofContext,
+    metadata: Vec<u8>,
+) -> ContributionProof<E>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64>,
+{
+    let s_g: E::G1Affine = (*g * s.get()).into();
+    let p: E::G1Affine = (*g * z.get()).into();
 
-    ContributionProof::new(p, r)
+    let h = challenge_v2::<E>(&s_g, g, &p, context, &metadata);
+
+    let r = *z.get() + h * s.get();
+
+    ContributionProof {
+        version: ProofVersion::V2,
+        p,
+        r,
+        metadata,
+    }
 }
 
-// Verifies a contribution proof
-pub fn verify_contribution_proof(
-    prev_s_g: &G1Affine,
-    s_g: &G1Affine,
-    proof: &ContributionProof<Bn256>,
-) -> bool {
+// Verifies a contribution proof.
+// The context is used only by version 2 proofs, version 1 proofs being bound to s_g and the previous s_g only
+pub fn verify_contribution_proof<E: Engine + Debug>(
+    prev_s_g: &E::G1Affine,
+    s_g: &E::G1Affine,
+    proof: &ContributionProof<E>,
+    context: &ProofContext,
+) -> bool
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
     if prev_s_g == s_g {
         error!("The previous s_g is equal to the current s_g. The contribution proof is invalid, or the SRS was not correctly re-randomized.");
         return false;
     }
 
-    let mut hasher = Blake2b512::new();
-    hasher.update(s_g.to_raw_bytes());
-    hasher.update(prev_s_g.to_raw_bytes());
-    hasher.update(proof.p().to_raw_bytes());
-    let h: Fr = Fr::from_uniform_bytes(hasher.finalize().as_ref());
+    if proof.is_null() {
+        error!("The contribution carries the null proof of a first contribution. The contribution proof is invalid.");
+        return false;
+    }
+
+    let h = challenge(s_g, prev_s_g, proof, context);
 
-    let check_result = prev_s_g * proof.r() == proof.p() + s_g * h;
+    let check_result = *prev_s_g * proof.r() == *s_g * h + proof.p();
 
     // When rescaling the srs with a public randomness source, the rescaling factor s should be publicly verifiable
     // To allow public verification of s, the blinding factor z is set to 0 during proof computation
     // We check if the proof is valid and if it has been computed using z = 0 (i.e., p = G1::identity()), and, if so, we recompute s
-    if check_result && proof.p().is_identity().into() {
-        let s = h.invert().unwrap() * proof.r();
-        info!("Contribution generated using a public randomness source. The rescaling factor s is {:#?}", s);
+    if check_result {
+        if let Some(s) = recover_public_s(prev_s_g, s_g, proof, context) {
+            info!("Contribution generated using a public randomness source. The rescaling factor s is {:#?}", s);
+        }
     }
 
     check_result
 }
+
+// Recovers the rescaling factor s from a contribution proof computed with z = 0 (i.e., p = G1::identity()).
+// The proof is assumed to be valid
+pub fn recover_public_s<E: Engine + Debug>(
+    prev_s_g: &E::G1Affine,
+    s_g: &E::G1Affine,
+    proof: &ContributionProof<E>,
+    context: &ProofContext,
+) -> Option<E::Fr>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
+    if proof.p().is_identity().into() {
+        let h = challenge(s_g, prev_s_g, proof, context);
+        Some(h.invert().unwrap() * proof.r())
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr, G1Affine};
+    use rand::rngs::OsRng;
+
+    fn context() -> ProofContext {
+        ProofContext {
+            prev_sha256: [3u8; 32],
+            id: 1,
+        }
+    }
+
+    fn v1_proof(prev_s_g: &G1Affine, s: Fr) -> (G1Affine, ContributionProof<Bn256>) {
+        let z = Fr::random(OsRng);
+        let s_g: G1Affine = (*prev_s_g * s).into();
+        let p: G1Affine = (*prev_s_g * z).into();
+        let h = challenge_v1::<Bn256>(&s_g, prev_s_g, &p);
+        (s_g, ContributionProof::new(p, z + h * s))
+    }
+
+    #[test]
+    fn v1_proof_is_valid_in_unversioned_layout() {
+        let prev_s_g = G1Affine::generator();
+        let (s_g, proof) = v1_proof(&prev_s_g, Fr::random(OsRng));
+
+        let mut bytes = Vec::new();
+        proof.write(&mut bytes).unwrap();
+        let proof = ContributionProof::<Bn256>::read(&mut bytes.as_slice()).unwrap();
+        assert!(verify_contribution_proof(
+            &prev_s_g,
+            &s_g,
+            &proof,
+            &context()
+        ));
+    }
+
+    #[test]
+    fn v1_proof_is_rejected_in_versioned_layout() {
+        let prev_s_g = G1Affine::generator();
+        let (_, proof) = v1_proof(&prev_s_g, Fr::random(OsRng));
+
+        let mut bytes = Vec::new();
+        proof.write_versioned(&mut bytes).unwrap();
+        let err = ContributionProof::<Bn256>::read_versioned(&mut bytes.as_slice()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn null_proof_is_read_but_never_verifies() {
+        let mut bytes = Vec::new();
+        ContributionProof::<Bn256>::default()
+            .write_versioned(&mut bytes)
+            .unwrap();
+        let proof = ContributionProof::<Bn256>::read_versioned(&mut bytes.as_slice()).unwrap();
+        assert!(proof.is_null());
+
+        let prev_s_g = G1Affine::generator();
+        let s_g: G1Affine = (prev_s_g * Fr::random(OsRng)).into();
+        assert!(!verify_contribution_proof(
+            &prev_s_g,
+            &s_g,
+            &proof,
+            &context()
+        ));
+    }
+
+    #[test]
+    fn v2_proof_is_bound_to_its_context() {
+        let prev_s_g = G1Affine::generator();
+        let s = Secret::<Bn256>::new(Fr::random(OsRng));
+        let z = Secret::<Bn256>::new(Fr::random(OsRng));
+        let proof = create_contribution_proof(&prev_s_g, &s, &z, &context(), b"alice".to_vec());
+        let s_g: G1Affine = (prev_s_g * s.get()).into();
+
+        let mut bytes = Vec::new();
+        proof.write_versioned(&mut bytes).unwrap();
+        let proof = ContributionProof::<Bn256>::read_versioned(&mut bytes.as_slice()).unwrap();
+        assert!(verify_contribution_proof(
+            &prev_s_g,
+            &s_g,
+            &proof,
+            &context()
+        ));
+
+        let other_context = ProofContext { id: 2, ..context() };
+        assert!(!verify_contribution_proof(
+            &prev_s_g,
+            &s_g,
+            &proof,
+            &other_context
+        ));
+    }
+}
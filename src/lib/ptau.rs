@@ -0,0 +1,294 @@
+use anyhow::{anyhow, Result};
+use byteorder::{LittleEndian, ReadBytesExt, WriteBytesExt};
+use group::ff::PrimeField;
+use halo2_proofs::halo2curves::bn256::{Bn256, Fq, G1Affine, G2Affine};
+use halo2_proofs::SerdeFormat;
+use log::info;
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+use crate::config::CeremonyConfig;
+use crate::contribution::{
+    g_chunks, read_points, ContributionView, KZGContribution, SerdeCurveAffine, G1_CHUNK_LENGTH,
+};
+use crate::proof::ContributionProof;
+
+// snarkjs binary files start with a 4 bytes magic, followed by the file version and the number of sections
+const PTAU_MAGIC: &[u8; 4] = b"ptau";
+const PTAU_VERSION: u32 = 1;
+
+// The ptau sections we read and write. Sections 4 (alphaTauG1), 5 (betaTauG1) and 6 (betaG2) depend on
+// secrets not committed to by a KZG SRS, and so do all tauG2 powers but the first two: such sections are never written
+const SECTION_HEADER: u32 = 1;
+const SECTION_TAU_G1: u32 = 2;
+const SECTION_TAU_G2: u32 = 3;
+const SECTION_CONTRIBUTIONS: u32 = 7;
+
+// Byte length of a BN256 base field element
+const FQ_LENGTH: u32 = 32;
+
+// Points in ptau files are uncompressed, with coordinates in Montgomery form and little-endian,
+// which is the same layout as halo2curves' RawBytes encoding
+const PTAU_FORMAT: SerdeFormat = SerdeFormat::RawBytes;
+
+// Returns the BN256 base field modulus, little-endian
+fn fq_modulus() -> Result<Vec<u8>> {
+    let mut modulus = hex::decode(Fq::MODULUS.trim_start_matches("0x"))?;
+    modulus.reverse();
+    Ok(modulus)
+}
+
+/// Exports a contribution to a snarkjs .ptau file.
+///
+/// A ptau file of power p contains 2^{p+1}-1 powers of tau in G1: a contribution of size 2^k is thus exported with power k-1,
+/// dropping its last power in G1. Only the first two powers of tau in G2 are available in a contribution,
+/// while alphaTauG1, betaTauG1 and betaG2 cannot be derived from it: these sections are omitted.
+/// The contributions section is written empty
+pub fn write_ptau<V: ContributionView<Bn256>>(contribution: &V, ptau_path: &Path) -> Result<()> {
+    if ptau_path.is_file() {
+        return Err(anyhow!(
+            "The ptau file will be written to {}, but this file already exists.",
+            &ptau_path.display()
+        ));
+    }
+
+    let power = contribution.k() - 1;
+    let tau_g1_length: usize = (1 << (power + 1)) - 1;
+    let modulus = fq_modulus()?;
+
+    let mut writer = BufWriter::new(File::create(ptau_path)?);
+    writer.write_all(PTAU_MAGIC)?;
+    writer.write_u32::<LittleEndian>(PTAU_VERSION)?;
+    // We write 4 sections: header, tauG1, tauG2 and contributions
+    writer.write_u32::<LittleEndian>(4)?;
+
+    // Header: field element length, base field modulus, power and ceremony power
+    writer.write_u32::<LittleEndian>(SECTION_HEADER)?;
+    writer.write_u64::<LittleEndian>(4 + modulus.len() as u64 + 4 + 4)?;
+    writer.write_u32::<LittleEndian>(FQ_LENGTH)?;
+    writer.write_all(&modulus)?;
+    writer.write_u32::<LittleEndian>(power)?;
+    writer.write_u32::<LittleEndian>(power)?;
+
+    writer.write_u32::<LittleEndian>(SECTION_TAU_G1)?;
+    writer
+        .write_u64::<LittleEndian>((tau_g1_length * G1Affine::byte_length(PTAU_FORMAT)) as u64)?;
+    for (start, g) in g_chunks(contribution, G1_CHUNK_LENGTH) {
+        for point in g.iter().take(tau_g1_length.saturating_sub(start)) {
+            point.write(&mut writer, PTAU_FORMAT)?;
+        }
+    }
+
+    writer.write_u32::<LittleEndian>(SECTION_TAU_G2)?;
+    writer.write_u64::<LittleEndian>(2 * G2Affine::byte_length(PTAU_FORMAT) as u64)?;
+    contribution.g2().write(&mut writer, PTAU_FORMAT)?;
+    contribution.s_g2().write(&mut writer, PTAU_FORMAT)?;
+
+    writer.write_u32::<LittleEndian>(SECTION_CONTRIBUTIONS)?;
+    writer.write_u64::<LittleEndian>(4)?;
+    writer.write_u32::<LittleEndian>(0)?;
+
+    writer.flush()?;
+
+    info!(
+        "Contribution with id {} exported with power {} to {:#?}",
+        contribution.id(),
+        power,
+        &ptau_path.display()
+    );
+
+    Ok(())
+}
+
+// Reads the sections table of a ptau file, mapping each section type to its offset and length
+fn read_sections(reader: &mut BufReader<File>) -> Result<HashMap<u32, (u64, u64)>> {
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != PTAU_MAGIC {
+        return Err(anyhow!("The input file is not a ptau file"));
+    }
+
+    let version = reader.read_u32::<LittleEndian>()?;
+    if version != PTAU_VERSION {
+        return Err(anyhow!(
+            "The input ptau file has version {} but only version {} is supported",
+            version,
+            PTAU_VERSION
+        ));
+    }
+
+    let n_sections = reader.read_u32::<LittleEndian>()?;
+    let mut sections = HashMap::new();
+    for _ in 0..n_sections {
+        let section_type = reader.read_u32::<LittleEndian>()?;
+        let section_length = reader.read_u64::<LittleEndian>()?;
+        let offset = reader.stream_position()?;
+        if sections
+            .insert(section_type, (offset, section_length))
+            .is_some()
+        {
+            return Err(anyhow!(
+                "The input ptau file has more than one section of type {}",
+                section_type
+            ));
+        }
+        reader.seek(SeekFrom::Current(section_length as i64))?;
+    }
+
+    Ok(sections)
+}
+
+// Returns the offset and length of a section, if present
+fn section(sections: &HashMap<u32, (u64, u64)>, section_type: u32) -> Result<(u64, u64)> {
+    sections
+        .get(&section_type)
+        .copied()
+        .ok_or_else(|| anyhow!("The input ptau file has no section {}", section_type))
+}
+
+// This utility reads a snarkjs ptau file and converts its first 2^k powers of tau to a set of KZG Parameters encoded using halo2curves::Bn256
+pub fn read_ptau(ptau_path: &str, config: &CeremonyConfig) -> Result<KZGContribution<Bn256>> {
+    let ptau_path = Path::new(ptau_path);
+    if !ptau_path.is_file() {
+        return Err(anyhow!(
+            "The ptau path {:#?} is not a file",
+            &ptau_path.display()
+        ));
+    }
+
+    let mut reader = BufReader::new(File::open(ptau_path)?);
+    let sections = read_sections(&mut reader)?;
+
+    // We check that the ptau file is defined over BN256
+    let (header_offset, _) = section(&sections, SECTION_HEADER)?;
+    reader.seek(SeekFrom::Start(header_offset))?;
+    let fq_length = reader.read_u32::<LittleEndian>()?;
+    if fq_length != FQ_LENGTH {
+        return Err(anyhow!(
+            "The input ptau file has field elements of {} bytes, but BN256 requires {} bytes",
+            fq_length,
+            FQ_LENGTH
+        ));
+    }
+    let mut modulus = vec![0u8; FQ_LENGTH as usize];
+    reader.read_exact(&mut modulus)?;
+    if modulus != fq_modulus()? {
+        return Err(anyhow!("The input ptau file is not defined over BN256"));
+    }
+    let power = reader.read_u32::<LittleEndian>()?;
+    let ceremony_power = reader.read_u32::<LittleEndian>()?;
+
+    info!(
+        "The ptau file has power {} and ceremony power {}",
+        power, ceremony_power
+    );
+
+    // TauG1 consists of 2^{power+1}-1 elements
+    let (tau_g1_offset, tau_g1_length) = section(&sections, SECTION_TAU_G1)?;
+    if config.k() > power
+        || tau_g1_length < (config.n() * G1Affine::byte_length(PTAU_FORMAT)) as u64
+    {
+        return Err(anyhow!("Ceremony k is {} but the input ptau file has power {}. It is not possible to create a starting srs for the set ceremony k from the ptau file", config.k(), power));
+    }
+
+    // We read the elements in G1
+    reader.seek(SeekFrom::Start(tau_g1_offset))?;
+    let g: Vec<G1Affine> = read_points(&mut reader, PTAU_FORMAT, config.n())?;
+
+    info!("Read {:#?} elements from TauG1", g.len());
+
+    // We read the elements in G2
+    let (tau_g2_offset, tau_g2_length) = section(&sections, SECTION_TAU_G2)?;
+    if tau_g2_length < 2 * G2Affine::byte_length(PTAU_FORMAT) as u64 {
+        return Err(anyhow!(
+            "The input ptau file has less than 2 elements in TauG2"
+        ));
+    }
+    reader.seek(SeekFrom::Start(tau_g2_offset))?;
+    let g2 = <G2Affine as SerdeCurveAffine>::read(&mut reader, PTAU_FORMAT)?;
+    let s_g2 = <G2Affine as SerdeCurveAffine>::read(&mut reader, PTAU_FORMAT)?;
+
+    info!("Read 2 elements from TauG2");
+
+    Ok(KZGContribution::<Bn256>::from_parts(
+        config.k(),
+        g,
+        g2,
+        s_g2,
+        ContributionProof::default(),
+        0,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::ScratchDir;
+    use group::{ff::Field, prime::PrimeCurveAffine};
+    use halo2_proofs::halo2curves::bn256::Fr;
+
+    // Header section of a ptau file of power 1: type 1, length 44, field element length 32,
+    // the BN256 base field modulus in little-endian, power 1 and ceremony power 1
+    const POWER_1_HEADER_SECTION: &str = concat!(
+        "01000000",
+        "2c00000000000000",
+        "20000000",
+        "47fd7cd8168c203c8dca7168916a81975d588181b64550b829a031e1724e6430",
+        "01000000",
+        "01000000"
+    );
+
+    // Returns a contribution of size 2^k with tau = 5
+    fn contribution(k: u32) -> KZGContribution<Bn256> {
+        let tau = Fr::from(5);
+        let mut power = Fr::ONE;
+        let g = (0..1 << k)
+            .map(|_| {
+                let point = (G1Affine::generator() * power).into();
+                power *= tau;
+                point
+            })
+            .collect();
+        KZGContribution::from_parts(
+            k,
+            g,
+            G2Affine::generator(),
+            (G2Affine::generator() * tau).into(),
+            ContributionProof::default(),
+            1,
+        )
+    }
+
+    #[test]
+    fn ptau_round_trip() {
+        let scratch = ScratchDir::new("ptau_round_trip");
+        let ptau_path = scratch.0.join("contribution.ptau");
+        let contribution = contribution(2);
+        write_ptau(&contribution, &ptau_path).unwrap();
+
+        // A contribution of size 2^2 is exported with power 1, from which an SRS of size 2^1 is read back
+        let ptau_path_str = ptau_path.to_str().unwrap();
+        let read = read_ptau(ptau_path_str, &CeremonyConfig::new(1).unwrap()).unwrap();
+        assert_eq!(read.g()[..], contribution.g()[..2]);
+        assert_eq!(read.g2(), contribution.g2());
+        assert_eq!(read.s_g2(), contribution.s_g2());
+        assert!(read_ptau(ptau_path_str, &CeremonyConfig::new(2).unwrap()).is_err());
+    }
+
+    #[test]
+    fn ptau_header_matches_fixture() {
+        let scratch = ScratchDir::new("ptau_header");
+        let ptau_path = scratch.0.join("contribution.ptau");
+        write_ptau(&contribution(2), &ptau_path).unwrap();
+
+        let bytes = std::fs::read(&ptau_path).unwrap();
+        // Magic, version 1 and 4 sections
+        assert_eq!(bytes[..12], b"ptau\x01\x00\x00\x00\x04\x00\x00\x00"[..]);
+        let header_section = hex::decode(POWER_1_HEADER_SECTION).unwrap();
+        assert_eq!(bytes[12..12 + header_section.len()], header_section[..]);
+    }
+}
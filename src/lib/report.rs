@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+
+use crate::attestation::AttestationReport;
+use std::path::PathBuf;
+
+/// Results of the checks performed on a SRS
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SrsReport {
+    /// Check #1: the SRS uses the hardcoded group generators
+    pub generators: bool,
+    /// Check #2: g2 and s_g2 have order r
+    pub g2_order: bool,
+    /// Check #3: the g vector has the expected size
+    pub size: bool,
+    /// Check #4: the SRS is well-formed
+    pub well_formed: bool,
+    /// Check #5: the SRS is non-degenerative
+    pub non_degenerative: bool,
+}
+
+impl SrsReport {
+    pub fn is_valid(&self) -> bool {
+        self.generators && self.g2_order && self.size && self.well_formed && self.non_degenerative
+    }
+}
+
+/// Outcome of a contribution proof verification
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProofStatus {
+    Valid,
+    Invalid,
+    /// The first contribution has no contribution proof
+    Skipped,
+    /// The previous contribution is missing, so the proof cannot be verified
+    MissingPrevious,
+}
+
+impl ProofStatus {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, ProofStatus::Valid | ProofStatus::Skipped)
+    }
+}
+
+/// Time spent, in seconds, in each verification phase of a contribution
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Timings {
+    pub hash: f64,
+    pub load: f64,
+    pub srs_check: f64,
+    pub proof_check: f64,
+}
+
+/// Results of the verification of a single contribution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContributionReport {
+    pub id: u32,
+    pub path: PathBuf,
+    /// SHA256 of the contribution file, hex encoded
+    pub sha256: String,
+    /// The contribution has the size set for the ceremony
+    pub ceremony_size: bool,
+    pub srs: SrsReport,
+    pub proof: ProofStatus,
+    /// The rescaling factor s, if revealed by the contribution proof
+    pub public_s: Option<String>,
+    pub timings: Timings,
+    /// The contributor attestation, if any
+    #[serde(default)]
+    pub attestation: Option<AttestationReport>,
+    /// The results were retrieved from the verification cache
+    #[serde(default)]
+    pub cached: bool,
+}
+
+impl ContributionReport {
+    pub fn is_valid(&self) -> bool {
+        self.ceremony_size
+            && self.srs.is_valid()
+            && self.proof.is_valid()
+            && self.attestation_is_valid()
+    }
+
+    /// Returns false only if the contribution has an attestation which is not valid
+    pub fn attestation_is_valid(&self) -> bool {
+        self.attestation
+            .as_ref()
+            .map_or(true, |attestation| attestation.valid)
+    }
+
+    /// Returns the first failure found in the contribution, if any
+    pub fn failure(&self) -> Option<FailureClass> {
+        if !self.ceremony_size {
+            Some(FailureClass::CeremonySize)
+        } else if !self.srs.is_valid() {
+            Some(FailureClass::Srs)
+        } else if self.proof == ProofStatus::MissingPrevious {
+            Some(FailureClass::MissingContribution)
+        } else if !self.proof.is_valid() {
+            Some(FailureClass::Proof)
+        } else if !self.attestation_is_valid() {
+            Some(FailureClass::Attestation)
+        } else {
+            None
+        }
+    }
+}
+
+/// Results of the verification of a contributions' chain
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChainReport {
+    pub contributions: Vec<ContributionReport>,
+    pub valid: bool,
+    /// The last contribution is derived from the random beacon, if a beacon was checked
+    #[serde(default)]
+    pub beacon: Option<bool>,
+    /// The rescaling factor s recomputed from declared inputs matches the revealed one, if a recomputation was requested
+    #[serde(default)]
+    pub recomputed_s: Option<bool>,
+}
+
+impl ChainReport {
+    /// Returns the failure of the earliest invalid contribution, if any, or a beacon or recomputation failure
+    pub fn failure(&self) -> Option<FailureClass> {
+        self.contributions
+            .iter()
+            .find_map(ContributionReport::failure)
+            .or_else(|| (self.beacon == Some(false)).then_some(FailureClass::Beacon))
+            .or_else(|| {
+                (self.recomputed_s == Some(false)).then_some(FailureClass::RecomputedSecret)
+            })
+    }
+}
+
+/// Classes of failures which invalidate a contributions' chain
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    CeremonySize,
+    Srs,
+    Proof,
+    MissingContribution,
+    Attestation,
+    Beacon,
+    RecomputedSecret,
+}
+
+impl FailureClass {
+    /// Process exit code reported for the failure. Exit code 1 is left to runtime errors
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FailureClass::CeremonySize => 2,
+            FailureClass::Srs => 3,
+            FailureClass::Proof => 4,
+            FailureClass::MissingContribution => 5,
+            FailureClass::Attestation => 6,
+            FailureClass::Beacon => 7,
+            FailureClass::RecomputedSecret => 8,
+        }
+    }
+}
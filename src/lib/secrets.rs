@@ -1,23 +1,24 @@
-use anyhow::Result;
+use anyhow::{anyhow, Result};
 use blake2::{Blake2b512, Digest};
-use group::ff::FromUniformBytes;
-use halo2_proofs::halo2curves::{
-    bn256::{Bn256, Fr},
-    pairing::Engine,
-};
+use group::ff::{Field, FromUniformBytes};
+use halo2_proofs::halo2curves::pairing::Engine;
 use log::{debug, info};
 use rand::{rngs::OsRng, RngCore};
 use std::io::stdin;
 use std::{
+    env,
     fs::File,
     io::{BufReader, Read},
     path::PathBuf,
+    process::{Command, Stdio},
 };
 use zeroize::Zeroize;
 
-/// Secret scalars that should be properly discarded
+use crate::locked::Locked;
+
+/// Secret scalars that should be properly discarded. They are stored in locked memory, so they are never written to swap
 pub struct Secret<E: Engine> {
-    pub value: Box<E::Fr>,
+    pub value: Locked<E::Fr>,
 }
 
 #[derive(Debug)]
@@ -27,9 +28,36 @@ pub struct SecretsGenConfig {
     pub random_bytes_size: Option<usize>,
     pub hash_iterations: Option<u32>,
     pub reveal_s: bool,
+    /// Local commands whose standard output is hashed into the hash state, e.g. a webcam or audio capture script
+    pub commands: Option<Vec<String>>,
+    /// Named pipes read until EOF and hashed into the hash state
+    pub fifos: Option<Vec<String>>,
+    /// The name of an environment variable holding a seed to hash into the hash state
+    pub env_seed: Option<String>,
+    /// How sources are encoded into the hash state
+    pub encoding: SecretsEncoding,
+}
+
+/// Versions of the encoding of entropy sources into the hash state
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SecretsEncoding {
+    /// Files, stdin and random bytes are hashed raw, as done by contribute before the tagged encoding was introduced.
+    /// Only meant for recomputing secrets revealed by earlier contributions, as distinct combinations of raw sources
+    /// can produce the same hash state
+    Legacy,
+    /// The hash state starts with a version tag, and every source is prefixed by a tag identifying its kind
+    /// and split in length-prefixed chunks, so that distinct combinations of sources never collide
+    #[default]
+    Tagged,
 }
 
 impl<E: Engine> Secret<E> {
+    pub fn new(value: E::Fr) -> Self {
+        Secret {
+            value: Locked::new(value),
+        }
+    }
+
     pub fn get(&self) -> &E::Fr {
         &self.value
     }
@@ -56,14 +84,103 @@ impl Default for SecretsGenConfig {
             random_bytes_size: Some(1024 * 1024 * 1024),
             hash_iterations: Some(1 << 20),
             reveal_s: false,
+            commands: None,
+            fifos: None,
+            env_seed: None,
+            encoding: SecretsEncoding::Tagged,
         }
     }
 }
 
-pub fn gen_secrets(config: SecretsGenConfig) -> Result<(Secret<Bn256>, Secret<Bn256>)> {
+// Domain separation tag hashed first in the tagged encoding
+const TAGGED_ENCODING_DOMAIN: &str = "zircuit-ceremony/secrets/v2";
+
+// Domain separation tags of the entropy sources
+const FILE_SOURCE_TAG: &str = "file";
+const STDIN_SOURCE_TAG: &str = "stdin";
+const RANDOM_SOURCE_TAG: &str = "random";
+const COMMAND_SOURCE_TAG: &str = "command";
+const FIFO_SOURCE_TAG: &str = "fifo";
+const ENV_SEED_SOURCE_TAG: &str = "env_seed";
+
+// Hashes the length-prefixed source tag, followed by the source content in length-prefixed chunks of 16 MiB.
+// The last chunk is shorter, possibly empty, so that the encoding of a sequence of sources is unambiguous
+fn hash_source<R: Read>(hasher: &mut Blake2b512, tag: &str, reader: &mut R) -> Result<()> {
+    hasher.update((tag.len() as u64).to_le_bytes());
+    hasher.update(tag);
+
+    // Chunks are filled before being hashed, so that the encoding does not depend on how the source is read
+    let mut buffer = vec![0u8; 16 * 1024 * 1024];
+    loop {
+        let mut filled = 0;
+        while filled < buffer.len() {
+            let bytes_read = reader.read(&mut buffer[filled..])?;
+            if bytes_read == 0 {
+                break;
+            }
+            filled += bytes_read;
+        }
+        hasher.update((filled as u64).to_le_bytes());
+        hasher.update(&buffer[..filled]);
+        if filled < buffer.len() {
+            break;
+        }
+    }
+    buffer.zeroize();
+
+    Ok(())
+}
+
+// Runs a local command through the system shell and hashes its standard output
+fn hash_command(hasher: &mut Blake2b512, command: &str) -> Result<()> {
+    let mut child = if cfg!(windows) {
+        Command::new("cmd")
+            .args(["/C", command])
+            .stdout(Stdio::piped())
+            .spawn()
+    } else {
+        Command::new("sh")
+            .args(["-c", command])
+            .stdout(Stdio::piped())
+            .spawn()
+    }
+    .map_err(|e| anyhow!("Cannot run the entropy command {:#?}: {}", command, e))?;
+
+    let mut stdout = child.stdout.take().unwrap();
+    if let Err(e) = hash_source(hasher, COMMAND_SOURCE_TAG, &mut stdout) {
+        // We neither leave the command running nor leave it as a zombie process
+        drop(stdout);
+        let _ = child.kill();
+        let _ = child.wait();
+        return Err(e);
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow!(
+            "The entropy command {:#?} failed with {}",
+            command,
+            status
+        ));
+    }
+
+    Ok(())
+}
+
+pub fn gen_secrets<E: Engine>(config: SecretsGenConfig) -> Result<(Secret<E>, Secret<E>)>
+where
+    E::Fr: FromUniformBytes<64>,
+{
     info!("Generating secrets .. ");
 
-    let mut hasher = Blake2b512::new();
+    // The hasher state is stored in locked memory
+    let mut hasher = Locked::new(Blake2b512::new());
+
+    let tagged = config.encoding == SecretsEncoding::Tagged;
+    if tagged {
+        hasher.update((TAGGED_ENCODING_DOMAIN.len() as u64).to_le_bytes());
+        hasher.update(TAGGED_ENCODING_DOMAIN);
+    }
 
     if let Some(paths) = config.files_to_hash {
         for str_path in paths {
@@ -74,6 +191,11 @@ pub fn gen_secrets(config: SecretsGenConfig) -> Result<(Secret<Bn256>, Secret<Bn
                 let file = File::open(path)?;
                 let mut reader = BufReader::new(file);
 
+                if tagged {
+                    hash_source(&mut hasher, FILE_SOURCE_TAG, &mut reader)?;
+                    continue;
+                }
+
                 // We read the file in chunks of at most 16 MiB
                 let mut file_buffer = vec![0u8; 16 * 1024 * 1024];
                 loop {
@@ -101,46 +223,91 @@ pub fn gen_secrets(config: SecretsGenConfig) -> Result<(Secret<Bn256>, Secret<Bn
         stdin_reader.read_to_end(&mut stdin_buffer)?;
 
         // We hash the stdin and erase its buffer
-        hasher.update(&stdin_buffer);
+        if tagged {
+            hash_source(&mut hasher, STDIN_SOURCE_TAG, &mut stdin_buffer.as_slice())?;
+        } else {
+            hasher.update(&stdin_buffer);
+        }
         stdin_buffer.zeroize();
         drop(stdin_buffer);
     }
 
+    // Pluggable sources are hashed with domain separation in both encodings
+    if let Some(commands) = config.commands {
+        for command in commands {
+            info!("Hashing the output of {:#?}", command);
+            hash_command(&mut hasher, &command)?;
+        }
+    }
+
+    if let Some(fifos) = config.fifos {
+        for fifo in fifos {
+            info!("Hashing named pipe {:#?}", fifo);
+            let mut reader = File::open(&fifo)
+                .map_err(|e| anyhow!("Cannot open the named pipe {:#?}: {}", fifo, e))?;
+            hash_source(&mut hasher, FIFO_SOURCE_TAG, &mut reader)?;
+        }
+    }
+
+    if let Some(env_seed) = config.env_seed {
+        info!("Hashing the seed in environment variable {}", env_seed);
+        let mut seed = env::var(&env_seed).map_err(|e| {
+            anyhow!(
+                "Cannot read the seed in environment variable {}: {}",
+                env_seed,
+                e
+            )
+        })?;
+        // We remove the seed from the environment, so that it is not inherited by other processes
+        env::remove_var(&env_seed);
+        hash_source(&mut hasher, ENV_SEED_SOURCE_TAG, &mut seed.as_bytes())?;
+        seed.zeroize();
+    }
+
     if let Some(random_bytes_size) = config.random_bytes_size {
         info!("Generating and hashing {} random bytes", random_bytes_size);
 
-        // To reduce memory consumptions, we read random bytes in chunks of at most 16MiB
-        let mut rng_buffer = vec![0u8; 16 * 1024 * 1024];
-        for _ in 0..random_bytes_size / rng_buffer.len() {
+        if tagged {
+            let rng: &mut dyn RngCore = &mut OsRng;
+            hash_source(
+                &mut hasher,
+                RANDOM_SOURCE_TAG,
+                &mut rng.take(random_bytes_size as u64),
+            )?;
+        } else {
+            // To reduce memory consumptions, we read random bytes in chunks of at most 16MiB
+            let mut rng_buffer = vec![0u8; 16 * 1024 * 1024];
+            for _ in 0..random_bytes_size / rng_buffer.len() {
+                OsRng.fill_bytes(&mut rng_buffer);
+                hasher.update(&rng_buffer);
+            }
+            rng_buffer.truncate(random_bytes_size % rng_buffer.len());
             OsRng.fill_bytes(&mut rng_buffer);
             hasher.update(&rng_buffer);
-        }
-        rng_buffer.truncate(random_bytes_size % rng_buffer.len());
-        OsRng.fill_bytes(&mut rng_buffer);
-        hasher.update(&rng_buffer);
 
-        rng_buffer.zeroize();
-        drop(rng_buffer);
+            rng_buffer.zeroize();
+            drop(rng_buffer);
+        }
     }
 
     if let Some(hash_iterations) = config.hash_iterations {
         // We perform hash_iterations hashes on current hash state
         info!("Hashing hasher's state {} times", hash_iterations);
+        let mut iteration_hasher = Locked::new(Blake2b512::new());
         for _ in 0..hash_iterations {
-            hasher.update(hasher.clone().finalize_reset());
+            iteration_hasher.clone_from(&hasher);
+            hasher.update(iteration_hasher.finalize_reset());
         }
     }
 
     info!("Extracting secrets");
 
     // We extract the secret s
-    let mut s_hasher = hasher.clone();
+    let mut s_hasher = Locked::new((*hasher).clone());
     s_hasher.update("s");
     let mut s_bytes: [u8; 64] = s_hasher.finalize_reset().into();
 
-    let s = Secret {
-        value: Box::new(Fr::from_uniform_bytes(&s_bytes)),
-    };
+    let s = Secret::new(E::Fr::from_uniform_bytes(&s_bytes));
 
     s_bytes.zeroize();
 
@@ -151,17 +318,13 @@ pub fn gen_secrets(config: SecretsGenConfig) -> Result<(Secret<Bn256>, Secret<Bn
         debug!(
             "The secret z is set to zero to enable the recomputation of the rescaling factor s."
         );
-        Secret {
-            value: Box::new(Fr::zero()),
-        }
+        Secret::new(E::Fr::ZERO)
     } else {
-        let mut z_hasher = hasher.clone();
+        let mut z_hasher = Locked::new((*hasher).clone());
         z_hasher.update("z");
         let mut z_bytes: [u8; 64] = z_hasher.finalize_reset().into();
 
-        let z = Secret {
-            value: Box::new(Fr::from_uniform_bytes(&z_bytes)),
-        };
+        let z = Secret::new(E::Fr::from_uniform_bytes(&z_bytes));
 
         z_bytes.zeroize();
 
@@ -0,0 +1,230 @@
+use anyhow::{anyhow, Result};
+use group::ff::{Field, FromUniformBytes};
+use halo2_proofs::halo2curves::{pairing::Engine, serde::SerdeObject};
+use log::info;
+use std::{
+    collections::HashSet,
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, Read, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::header::{CurveId, CurveIdentifier};
+use crate::locked::Locked;
+use crate::secrets::{gen_secrets, Secret, SecretsGenConfig};
+use crate::utils::hash_file;
+
+/// Magic bytes identifying a secret share file
+pub const SHARE_MAGIC: [u8; 4] = *b"CSSH";
+/// Default extension of secret share files
+pub const SHARE_EXTENSION: &str = "share";
+
+/// Generates a secret share according to the config and writes it to share_path.
+/// A share file stores the curve identifier followed by the share's rescaling factor s_i and blinding factor z_i
+pub fn write_share<E: Engine + CurveIdentifier>(
+    share_path: &Path,
+    config: SecretsGenConfig,
+) -> Result<()>
+where
+    E::Fr: FromUniformBytes<64> + SerdeObject,
+{
+    // We never overwrite an existing file, and on Unix the share is readable by its owner only
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let file = options.open(share_path).map_err(|e| {
+        anyhow!(
+            "Cannot create the share file {:#?}: {}",
+            share_path.display(),
+            e
+        )
+    })?;
+
+    let (s, z) = gen_secrets::<E>(config)?;
+
+    let mut writer = BufWriter::new(file);
+    writer.write_all(&SHARE_MAGIC)?;
+    writer.write_all(&[E::CURVE_ID.to_byte()])?;
+    s.get().write_raw(&mut writer)?;
+    z.get().write_raw(&mut writer)?;
+    writer.flush()?;
+
+    info!("Secret share written to {:#?}", share_path.display());
+
+    Ok(())
+}
+
+// Reads the secrets stored in a share file
+fn read_share<E: Engine + CurveIdentifier>(share_path: &PathBuf) -> Result<(Secret<E>, Secret<E>)>
+where
+    E::Fr: SerdeObject,
+{
+    let mut reader = BufReader::new(File::open(share_path)?);
+
+    let mut magic = [0u8; 4];
+    reader.read_exact(&mut magic)?;
+    if magic != SHARE_MAGIC {
+        return Err(anyhow!(
+            "{:#?} is not a secret share file",
+            share_path.display()
+        ));
+    }
+
+    let mut curve = [0u8; 1];
+    reader.read_exact(&mut curve)?;
+    let curve = CurveId::from_byte(curve[0])?;
+    if curve != E::CURVE_ID {
+        return Err(anyhow!(
+            "The share {:#?} is defined over {:?} but {:?} was expected",
+            share_path.display(),
+            curve,
+            E::CURVE_ID
+        ));
+    }
+
+    let s = Secret::new(<E::Fr as SerdeObject>::read_raw(&mut reader)?);
+    let z = Secret::new(<E::Fr as SerdeObject>::read_raw(&mut reader)?);
+
+    if bool::from(s.get().is_zero()) {
+        return Err(anyhow!(
+            "The share {:#?} has a zero rescaling factor",
+            share_path.display()
+        ));
+    }
+
+    Ok((s, z))
+}
+
+/// Combines secret shares into the secrets of a single contribution: the rescaling factor s is the product of the shares' s_i,
+/// and the blinding factor z is the sum of their z_i. Rescaling by s is equivalent to rescaling sequentially by each s_i,
+/// and the contribution proof computed with s and z is an aggregated proof of knowledge of the product.
+/// Shares are read one at a time and dropped once combined, so only the running product is kept in memory.
+/// A share provided twice, either through the same file or through a copy, is rejected
+pub fn combine_shares<E: Engine + CurveIdentifier>(
+    share_paths: &[PathBuf],
+) -> Result<(Secret<E>, Secret<E>)>
+where
+    E::Fr: SerdeObject,
+{
+    if share_paths.is_empty() {
+        return Err(anyhow!("No secret share provided"));
+    }
+
+    // A share combined twice would contribute its s_i squared: we compare shares by canonical path and by the SHA256 of
+    // their file, so that the secrets of the shares already combined do not have to be kept in memory
+    let mut canonical_paths = HashSet::new();
+    let mut share_hashes = HashSet::new();
+    for share_path in share_paths {
+        let canonical_path = share_path.canonicalize().map_err(|e| {
+            anyhow!(
+                "Cannot read the share file {:#?}: {}",
+                share_path.display(),
+                e
+            )
+        })?;
+        if !canonical_paths.insert(canonical_path) {
+            return Err(anyhow!(
+                "The share {:#?} is provided more than once",
+                share_path.display()
+            ));
+        }
+        if !share_hashes.insert(hash_file(share_path)?) {
+            return Err(anyhow!(
+                "The share {:#?} has the same content as another share provided",
+                share_path.display()
+            ));
+        }
+    }
+
+    let mut s = Locked::new(E::Fr::ONE);
+    let mut z = Locked::new(E::Fr::ZERO);
+    for share_path in share_paths {
+        info!("Combining secret share {:#?}", share_path.display());
+        let (s_i, z_i) = read_share::<E>(share_path)?;
+        *s *= s_i.get();
+        *z += z_i.get();
+    }
+
+    info!("Combined {} secret shares", share_paths.len());
+
+    Ok((Secret::new(*s), Secret::new(*z)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contribute::rescale;
+    use crate::contribution::KZGContribution;
+    use crate::proof::{verify_contribution_proof, ProofContext};
+    use crate::utils::ScratchDir;
+    use halo2_proofs::halo2curves::bn256::{Bn256, Fr};
+    use rand::rngs::OsRng;
+
+    // Writes a share file with random secrets to path, returning its rescaling factor
+    fn write_random_share(path: &Path) -> Fr {
+        let (s, z) = (Fr::random(OsRng), Fr::random(OsRng));
+        let mut bytes = SHARE_MAGIC.to_vec();
+        bytes.push(Bn256::CURVE_ID.to_byte());
+        s.write_raw(&mut bytes).unwrap();
+        z.write_raw(&mut bytes).unwrap();
+        std::fs::write(path, bytes).unwrap();
+        s
+    }
+
+    #[test]
+    fn combined_shares_match_sequential_rescaling() {
+        let scratch = ScratchDir::new("shares_rescaling");
+        let share_paths: Vec<PathBuf> = (0..3)
+            .map(|i| scratch.0.join(format!("{}.{}", i, SHARE_EXTENSION)))
+            .collect();
+        let shares: Vec<Fr> = share_paths
+            .iter()
+            .map(|path| write_random_share(path))
+            .collect();
+
+        let prev_contribution = KZGContribution::<Bn256>::trivial(3);
+        let prev_sha256 = [1u8; 32];
+        let (s, z) = combine_shares::<Bn256>(&share_paths).unwrap();
+        let combined = rescale(&prev_contribution, prev_sha256, &s, &z, vec![]);
+
+        let mut sequential = prev_contribution.clone();
+        for s_i in shares {
+            let z_i = Secret::new(Fr::random(OsRng));
+            sequential = rescale(&sequential, prev_sha256, &Secret::new(s_i), &z_i, vec![]);
+        }
+        assert_eq!(combined.g(), sequential.g());
+        assert_eq!(combined.s_g2(), sequential.s_g2());
+
+        let context = ProofContext {
+            prev_sha256,
+            id: combined.id(),
+        };
+        assert!(verify_contribution_proof(
+            prev_contribution.s_g(),
+            combined.s_g(),
+            combined.proof(),
+            &context
+        ));
+    }
+
+    #[test]
+    fn duplicate_shares_are_rejected() {
+        let scratch = ScratchDir::new("shares_duplicates");
+        let share_path = scratch.0.join("alice.share");
+        let other_share_path = scratch.0.join("bob.share");
+        write_random_share(&share_path);
+        write_random_share(&other_share_path);
+        assert!(combine_shares::<Bn256>(&[share_path.clone(), other_share_path]).is_ok());
+
+        // The same file, also through another path
+        assert!(combine_shares::<Bn256>(&[share_path.clone(), share_path.clone()]).is_err());
+        let alias_path = scratch.0.join(".").join("alice.share");
+        assert!(combine_shares::<Bn256>(&[share_path.clone(), alias_path]).is_err());
+
+        // A copy of the same share
+        let copy_path = scratch.0.join("copy.share");
+        std::fs::copy(&share_path, &copy_path).unwrap();
+        assert!(combine_shares::<Bn256>(&[share_path, copy_path]).is_err());
+    }
+}
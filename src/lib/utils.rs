@@ -1,42 +1,56 @@
 use std::{
     collections::hash_map::Entry,
     collections::HashMap,
-    fs::{self, read_dir, File},
-    io::{Read, Seek, SeekFrom},
+    fs::read_dir,
+    io::{BufReader, Read, Seek, SeekFrom, Write},
     path::PathBuf,
 };
 
 use anyhow::{anyhow, Result};
-use halo2_proofs::halo2curves::bn256::Bn256;
+use core::fmt::Debug;
+use halo2_proofs::halo2curves::{pairing::Engine, serde::SerdeObject};
 use log::debug;
+use sha2::{Digest, Sha256};
 
-use crate::contribution::{KZGContribution, CONTRIBUTION_EXTENSION, CONTRIBUTION_K};
+use crate::chunked::{is_chunked_contribution, ContributionReader};
+use crate::contribution::{
+    is_supported_k, SerdeCurveAffine, CONTRIBUTION_EXTENSION, MAX_CONTRIBUTION_K,
+    MIN_CONTRIBUTION_K,
+};
+use crate::header::{ContributionLayout, CurveIdentifier};
+use crate::mapped::LoadedContribution;
 
+/// Reads the id of a contribution, stored in a single file or chunked
 pub fn read_id(filepath: &PathBuf) -> Result<u32> {
-    let mut fd = File::open(filepath)?;
-    let file_len = fs::metadata(filepath)?.len();
+    let mut fd = ContributionReader::open(filepath)?;
+    let file_len = fd.length()?;
 
     if file_len > 8 {
-        // We read the contribution's k
-        let mut k = [0u8; 4];
-        fd.read_exact(&mut k[..])?;
-        let k: u32 = u32::from_le_bytes(k);
-        if k != CONTRIBUTION_K {
+        // We read the contribution's header, or its k if the contribution has a legacy layout
+        let layout = ContributionLayout::read(&mut fd)?;
+        let k = layout.k();
+        if !is_supported_k(k) {
             return Err(anyhow!(
-                "Contribution read has k {} but should be {}",
+                "Contribution read has k {} but should be between {} and {}",
                 k,
-                CONTRIBUTION_K
+                MIN_CONTRIBUTION_K,
+                MAX_CONTRIBUTION_K
             ));
         }
 
-        // We read the contribution's id
-        // The id is encoded in the last 4 bytes
-        fd.seek(SeekFrom::Start(file_len - 4))?;
-        let mut id = [0u8; 4];
-        fd.read_exact(&mut id[..])?;
-        let id = u32::from_le_bytes(id);
+        match layout {
+            ContributionLayout::Versioned(header) => Ok(header.id),
+            ContributionLayout::Legacy { .. } => {
+                // We read the contribution's id
+                // The id is encoded in the last 4 bytes
+                fd.seek(SeekFrom::Start(file_len - 4))?;
+                let mut id = [0u8; 4];
+                fd.read_exact(&mut id[..])?;
+                let id = u32::from_le_bytes(id);
 
-        Ok(id)
+                Ok(id)
+            }
+        }
     } else {
         Err(anyhow!("Contribution file {:#?} is malformed", filepath))
     }
@@ -49,10 +63,11 @@ pub fn get_contributions_list(path: &PathBuf) -> Result<HashMap<u32, PathBuf>> {
         // Iterate through the directory entries
         if let Ok(entries) = read_dir(path) {
             for entry in entries.flatten() {
-                // Check if entry is a file and has the contribution extension
+                // Check if entry is a file or a chunked contribution directory and has the contribution extension.
+                // Chunked contributions are complete once their index is written
                 let filepath = entry.path();
 
-                if filepath.is_file()
+                if (filepath.is_file() || is_chunked_contribution(&filepath))
                     && filepath.extension().and_then(std::ffi::OsStr::to_str)
                         == Some(CONTRIBUTION_EXTENSION)
                 {
@@ -78,16 +93,143 @@ pub fn get_contributions_list(path: &PathBuf) -> Result<HashMap<u32, PathBuf>> {
     }
 }
 
-pub fn get_last_contribution(path: &PathBuf) -> Result<KZGContribution<Bn256>> {
+pub fn get_last_contribution_path(path: &PathBuf) -> Result<PathBuf> {
     let contributions_list = get_contributions_list(path)?;
     let mut contributions_ids: Vec<u32> = contributions_list.keys().cloned().collect();
     contributions_ids.sort_unstable();
 
     if let Some(last_contribution_id) = contributions_ids.last() {
-        let last_contribution_path = contributions_list.get(last_contribution_id).unwrap();
-        let last_contribution = KZGContribution::from(last_contribution_path)?;
-        Ok(last_contribution)
+        Ok(contributions_list
+            .get(last_contribution_id)
+            .unwrap()
+            .to_owned())
     } else {
         Err(anyhow!("No contribution found in {:#?}", path))
     }
 }
+
+pub fn get_last_contribution<E: Engine + Debug + CurveIdentifier>(
+    path: &PathBuf,
+) -> Result<LoadedContribution<E>>
+where
+    E::G1Affine: SerdeCurveAffine,
+    E::G2Affine: SerdeCurveAffine,
+    E::Fr: SerdeObject,
+{
+    let last_contribution_path = get_last_contribution_path(path)?;
+    let last_contribution = LoadedContribution::open(&last_contribution_path)?;
+    Ok(last_contribution)
+}
+
+/// Returns the hex-encoded SHA256 of a file. The SHA256 of a chunked contribution is the one of its single-file layout
+pub fn hash_file(filepath: &PathBuf) -> Result<String> {
+    let mut reader = BufReader::new(ContributionReader::open(filepath)?);
+    let mut hasher = Sha256::new();
+
+    // We read the file in chunks of at most 16 MiB
+    let mut file_buffer = vec![0u8; 16 * 1024 * 1024];
+    loop {
+        let bytes_read = reader.read(&mut file_buffer)?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&file_buffer[..bytes_read]);
+    }
+
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Decodes a hex-encoded SHA256
+pub fn decode_sha256(sha256: &str) -> Result<[u8; 32]> {
+    hex::decode(sha256)?
+        .try_into()
+        .map_err(|_| anyhow!("{:#?} is not a valid SHA256", sha256))
+}
+
+/// A reader computing the SHA256 of all bytes read through it
+pub struct HashingReader<R: Read> {
+    reader: R,
+    hasher: Sha256,
+}
+
+impl<R: Read> HashingReader<R> {
+    pub fn new(reader: R) -> Self {
+        HashingReader {
+            reader,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Reads the remaining bytes and returns the SHA256 of all bytes read
+    pub fn finalize(mut self) -> Result<[u8; 32]> {
+        std::io::copy(&mut self, &mut std::io::sink())?;
+        Ok(self.hasher.finalize().into())
+    }
+}
+
+impl<R: Read> Read for HashingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let bytes_read = self.reader.read(buf)?;
+        self.hasher.update(&buf[..bytes_read]);
+        Ok(bytes_read)
+    }
+}
+
+/// A writer computing the SHA256 of all bytes written through it
+pub struct HashingWriter<W: Write> {
+    writer: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> HashingWriter<W> {
+    pub fn new(writer: W) -> Self {
+        HashingWriter {
+            writer,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Flushes the writer and returns the SHA256 of all bytes written
+    pub fn finalize(mut self) -> Result<[u8; 32]> {
+        self.writer.flush()?;
+        Ok(self.hasher.finalize().into())
+    }
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let bytes_written = self.writer.write(buf)?;
+        self.hasher.update(&buf[..bytes_written]);
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// A scratch directory for tests, removed when dropped
+#[cfg(test)]
+pub(crate) struct ScratchDir(pub PathBuf);
+
+#[cfg(test)]
+impl ScratchDir {
+    pub fn new(prefix: &str) -> Self {
+        use rand::{rngs::OsRng, Rng};
+
+        let path = std::env::temp_dir().join(format!(
+            "{}_{}",
+            prefix,
+            hex::encode(OsRng.gen::<[u8; 8]>())
+        ));
+        std::fs::create_dir_all(&path).unwrap();
+        ScratchDir(path)
+    }
+}
+
+#[cfg(test)]
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_dir_all(&self.0);
+    }
+}